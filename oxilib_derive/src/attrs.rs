@@ -0,0 +1,108 @@
+use quote::format_ident;
+use syn::{
+    punctuated::Punctuated, token::Comma, Attribute, Data, DeriveInput, Expr, Field, Fields, Ident,
+    Lit, LitStr, Type,
+};
+
+pub(crate) fn named_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<Field, Comma>> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            format!("{} can not be derived for generic structs", derive),
+        ));
+    }
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                format!(
+                    "{} can only be derived for structs with named fields",
+                    derive
+                ),
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{} can only be derived for structs", derive),
+        )),
+    }
+}
+
+pub(crate) struct StructAttrs {
+    pub optional: Ident,
+}
+
+impl StructAttrs {
+    pub(crate) fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut optional = format_ident!("Opt{}", input.ident);
+        for attr in oxiconf_attrs(&input.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("optional") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    optional = name.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown oxiconf attribute"))
+                }
+            })?;
+        }
+        Ok(StructAttrs { optional })
+    }
+}
+
+pub(crate) struct FieldAttrs {
+    pub nested: bool,
+    pub default: Option<Expr>,
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(field: &Field) -> syn::Result<Self> {
+        let mut nested = false;
+        let mut default = None;
+        for attr in oxiconf_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("nested") {
+                    nested = true;
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    // like serde, a string literal contains the expression itself
+                    default = Some(match meta.value()?.parse()? {
+                        Expr::Lit(expr) => match &expr.lit {
+                            Lit::Str(lit) => lit.parse()?,
+                            _ => Expr::Lit(expr),
+                        },
+                        expr => expr,
+                    });
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown oxiconf attribute"))
+                }
+            })?;
+        }
+        Ok(FieldAttrs { nested, default })
+    }
+}
+
+fn oxiconf_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("oxiconf"))
+}
+
+pub(crate) fn serde_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("serde"))
+}
+
+/// Whether the type is spelled `Option<T>`, such fields are not wrapped a second time.
+pub(crate) fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
@@ -0,0 +1,50 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::attrs::{is_option, named_fields, FieldAttrs, StructAttrs};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input, "Config")?;
+    let name = &input.ident;
+    let optional = StructAttrs::parse(input)?.optional;
+
+    let mut assignments = Vec::new();
+    for field in fields {
+        let attrs = FieldAttrs::parse(field)?;
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let create_nested = quote! {
+            <#ty as ::oxilib::Config<<#ty as ::oxilib::HasOptional>::Optional>>::create_from_optional
+        };
+        let default = match attrs.default {
+            Some(default) => quote!(#default),
+            None if attrs.nested => quote!(#create_nested(::core::default::Default::default())),
+            None => quote!(::core::default::Default::default()),
+        };
+        // the default of an `Option` field is an `Option` as well
+        let present = if attrs.nested {
+            quote!(#create_nested(value))
+        } else if is_option(ty) {
+            quote!(::core::option::Option::Some(value))
+        } else {
+            quote!(value)
+        };
+        assignments.push(quote! {
+            #ident: match optional.#ident {
+                ::core::option::Option::Some(value) => #present,
+                ::core::option::Option::None => #default,
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl ::oxilib::Config<#optional> for #name {
+            fn create_from_optional(optional: #optional) -> Self {
+                #name {
+                    #(#assignments,)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,40 @@
+mod attrs;
+mod config;
+mod optional;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Generates the optional counterpart `Opt<Name>` of a config struct, with every field
+/// wrapped in `Option`, and implements `ConfigOptional` and `Overlay` for it.
+/// `ConfigOptional::merge` merges nested fields recursively and replaces all others.
+///
+/// - `#[oxiconf(optional = "Name")]` on the struct changes the name of the generated struct.
+/// - `#[oxiconf(nested)]` on a field uses the optional counterpart of the field type,
+///   which has to derive `OptionalConfig` as well.
+///
+/// `#[serde(...)]` attributes of the struct and its fields are copied to the generated struct.
+#[proc_macro_derive(OptionalConfig, attributes(oxiconf))]
+pub fn derive_optional_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    optional::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Config<Opt<Name>>`, every missing field falls back to its default.
+///
+/// - `#[oxiconf(default = 10)]` or `#[oxiconf(default = "String::from(\"pingpang\")")]` sets
+///   the default of a field, a string contains the expression. Unannotated fields use
+///   `Default::default()`.
+/// - The default of an `Option<T>` field is an `Option<T>`, `None` unless annotated.
+/// - `#[oxiconf(nested)]` fields are created by their own `Config` impl.
+///
+/// Requires `OptionalConfig` to be derived as well.
+#[proc_macro_derive(Config, attributes(oxiconf))]
+pub fn derive_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    config::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,101 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::attrs::{is_option, named_fields, serde_attrs, FieldAttrs, StructAttrs};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input, "OptionalConfig")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let optional = StructAttrs::parse(input)?.optional;
+    let struct_serde = serde_attrs(&input.attrs);
+    let doc = format!("Optional counterpart of [`{}`].", name);
+
+    let mut optional_fields = Vec::new();
+    let mut overlays = Vec::new();
+    let mut merges = Vec::new();
+    for field in fields {
+        let attrs = FieldAttrs::parse(field)?;
+        let field_vis = &field.vis;
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let field_serde = serde_attrs(&field.attrs);
+        let optional_ty = if attrs.nested {
+            quote!(::core::option::Option<<#ty as ::oxilib::HasOptional>::Optional>)
+        } else if is_option(ty) {
+            quote!(#ty)
+        } else {
+            quote!(::core::option::Option<#ty>)
+        };
+        overlays.push(if attrs.nested {
+            quote! {
+                if let ::core::option::Option::Some(value) = self.#ident {
+                    ::oxilib::Overlay::apply(value, &mut config.#ident);
+                }
+            }
+        } else if is_option(ty) {
+            quote! {
+                if let ::core::option::Option::Some(value) = self.#ident {
+                    config.#ident = ::core::option::Option::Some(value);
+                }
+            }
+        } else {
+            quote! {
+                if let ::core::option::Option::Some(value) = self.#ident {
+                    config.#ident = value;
+                }
+            }
+        });
+        merges.push(if attrs.nested {
+            quote! {
+                #ident: match (self.#ident, other.#ident) {
+                    (
+                        ::core::option::Option::Some(base),
+                        ::core::option::Option::Some(other),
+                    ) => ::core::option::Option::Some(::oxilib::ConfigOptional::merge(base, other)),
+                    (base, other) => other.or(base),
+                }
+            }
+        } else {
+            quote!(#ident: other.#ident.or(self.#ident))
+        });
+        optional_fields.push(quote! {
+            #(#field_serde)*
+            #field_vis #ident: #optional_ty
+        });
+    }
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(
+            ::core::fmt::Debug,
+            ::core::default::Default,
+            ::oxilib::__private::serde::Deserialize,
+            ::oxilib::__private::serde::Serialize,
+        )]
+        #[serde(crate = "::oxilib::__private::serde")]
+        #(#struct_serde)*
+        #vis struct #optional {
+            #(#optional_fields,)*
+        }
+
+        impl ::oxilib::ConfigOptional for #optional {
+            fn merge(self, other: Self) -> Self {
+                Self {
+                    #(#merges,)*
+                }
+            }
+        }
+
+        impl ::oxilib::HasOptional for #name {
+            type Optional = #optional;
+        }
+
+        impl ::oxilib::Overlay<#name> for #optional {
+            fn apply(self, config: &mut #name) {
+                #(#overlays)*
+            }
+        }
+    })
+}
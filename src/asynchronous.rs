@@ -0,0 +1,231 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures_util::{stream, Stream};
+use tokio::{io::AsyncReadExt, sync::mpsc};
+
+use crate::{
+    config_file_path, create_from_parsed, parse_config, parse_default_config, warn_unknown_keys,
+    watch::files_watcher, ConfigError, ConfigOptional, Format, TryConfig, DEFAULT_DEBOUNCE,
+    DEFAULT_SIZE_LIMIT,
+};
+
+/// Like [`try_create_config`](crate::try_create_config), reading and creating the file with
+/// `tokio::fs` instead of blocking the thread.
+pub async fn load_config_async<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    load(&config_file, default_config, true).await
+}
+
+/// Like [`watch_config`](crate::watch_config), the reloaded configs are yielded by the returned
+/// stream instead of being passed to a callback, no thread is spawned for them.
+/// Watching stops once the stream is dropped.
+pub fn watch_config_stream<ConcreteConfig, OptionalConfig>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+) -> Result<impl Stream<Item = Result<ConcreteConfig, ConfigError>>, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let path = path.as_ref().to_path_buf();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let watcher = files_watcher(std::slice::from_ref(&path), move |_| {
+        let _ = sender.send(());
+    })?;
+    let state = WatchState {
+        path,
+        default_config: default_config.to_string(),
+        receiver,
+        _watcher: watcher,
+    };
+    Ok(stream::unfold(state, |mut state| async move {
+        loop {
+            state.changed(DEFAULT_DEBOUNCE).await?;
+            // replacing a file removes it for a moment
+            if !is_file(&state.path).await {
+                continue;
+            }
+            let result = load(&state.path, &state.default_config, false).await;
+            return Some((result, state));
+        }
+    }))
+}
+
+struct WatchState {
+    path: PathBuf,
+    default_config: String,
+    receiver: mpsc::UnboundedReceiver<()>,
+    // owns the sender, dropping the stream stops watching
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl WatchState {
+    /// Waits for a change followed by `debounce` without further changes,
+    /// `None` once the watcher is gone.
+    async fn changed(&mut self, debounce: Duration) -> Option<()> {
+        self.receiver.recv().await?;
+        loop {
+            match tokio::time::timeout(debounce, self.receiver.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => return None,
+                Err(_) => return Some(()),
+            }
+        }
+    }
+}
+
+async fn is_file(path: &Path) -> bool {
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => metadata.is_file(),
+        Err(_) => false,
+    }
+}
+
+/// The async counterpart of `load_optional` followed by `create_from_parsed`.
+async fn load<ConcreteConfig, OptionalConfig>(
+    config_file: &Path,
+    default_config: &str,
+    create_missing: bool,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    log_debug!("Loading config from {}", config_file.display());
+    let format = Format::detect(config_file);
+    let mut unknown_keys = Vec::new();
+    let contents = match read_file(config_file, DEFAULT_SIZE_LIMIT).await {
+        Ok(contents) => contents,
+        Err(ConfigError::NotFound(_)) => String::new(),
+        Err(error) => return Err(error),
+    };
+    let parsed_conf = if contents.is_empty() {
+        let parsed_conf = parse_default_config(
+            config_file,
+            default_config,
+            format.into(),
+            &mut unknown_keys,
+        )?;
+        if create_missing {
+            if let Err(error) = tokio::fs::write(config_file, default_config).await {
+                return Err(ConfigError::Io(config_file.to_path_buf(), error));
+            }
+        }
+        parsed_conf
+    } else {
+        parse_config(config_file, &contents, format.into(), &mut unknown_keys)?
+    };
+    warn_unknown_keys(config_file, &unknown_keys);
+    create_from_parsed(config_file, parsed_conf)
+}
+
+async fn read_file(path: &Path, size_limit: u64) -> Result<String, ConfigError> {
+    let from_io = |error: io::Error| ConfigError::from_io(path.to_path_buf(), error);
+    let file = tokio::fs::File::open(path).await.map_err(from_io)?;
+    let metadata = file.metadata().await.map_err(from_io)?;
+    if !metadata.is_file() {
+        return Err(ConfigError::NotAFile(path.to_path_buf()));
+    }
+    if metadata.len() > size_limit {
+        return Err(ConfigError::FileTooLarge {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            limit: size_limit,
+        });
+    }
+    let mut contents = String::new();
+    if let Err(error) = file
+        .take(size_limit + 1)
+        .read_to_string(&mut contents)
+        .await
+    {
+        return Err(from_io(error));
+    }
+    if contents.len() as u64 > size_limit {
+        return Err(ConfigError::FileTooLarge {
+            path: path.to_path_buf(),
+            size: contents.len() as u64,
+            limit: size_limit,
+        });
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use crate::{
+        load_config_async,
+        test_support::{test_dir, Conf, OptConf},
+        watch_config_stream, ConfigError,
+    };
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_load_config_async() {
+        let config_dir = test_dir("test_async_load");
+        let path = config_dir.join("config.toml");
+        let _ = fs::remove_file(&path);
+
+        let config: Conf = load_config_async(&config_dir, "config.toml", "something = 2")
+            .await
+            .expect("Could not load config.");
+        assert_eq!(config.something, 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "something = 2");
+
+        fs::write(&path, "something = 3").expect("Could not write test config.");
+        let config: Conf = load_config_async(&config_dir, "config.toml", "something = 2")
+            .await
+            .expect("Could not load config.");
+        assert_eq!(config.something, 3);
+
+        fs::write(&path, "something = ").expect("Could not write test config.");
+        let error = load_config_async::<Conf, OptConf>(&config_dir, "config.toml", "")
+            .await
+            .expect_err("Broken config was loaded.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[tokio::test]
+    async fn test_watch_config_stream() {
+        let config_dir = test_dir("test_async_watch");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "something = 2").expect("Could not write test config.");
+        let stream = watch_config_stream::<Conf, OptConf>(&path, "").expect("Could not watch.");
+        let mut stream = Box::pin(stream);
+
+        for something in 3..6 {
+            fs::write(&path, format!("something = {}", something))
+                .expect("Could not write test config.");
+        }
+        let config = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("No reload arrived.")
+            .unwrap();
+        assert_eq!(config.unwrap().something, 5);
+
+        fs::write(&path, "something = ").expect("Could not write test config.");
+        let result = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("No reload arrived.")
+            .unwrap();
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+
+        drop(stream);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
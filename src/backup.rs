@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::ConfigError;
+
+/// How many `<file>.broken-<timestamp>` copies are kept per config file.
+pub const MAX_BROKEN_BACKUPS: usize = 3;
+
+/// Copies `path` to `<path>.broken-<timestamp>`, unless the newest backup already has the same
+/// content, and removes the oldest backups beyond [`MAX_BROKEN_BACKUPS`].
+pub(crate) fn backup_broken_file(path: &Path) -> Result<PathBuf, ConfigError> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) => return Err(ConfigError::from_io(path.to_path_buf(), error)),
+    };
+    let mut backups = broken_backups(path)?;
+    if let Some((_, newest)) = backups.last() {
+        if fs::read(newest).is_ok_and(|backup| backup == contents) {
+            return Ok(newest.clone());
+        }
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    let backup = broken_backup_path(path, timestamp);
+    if let Err(error) = fs::write(&backup, &contents) {
+        return Err(ConfigError::Io(backup, error));
+    }
+    backups.push((timestamp, backup.clone()));
+
+    let excess = backups.len().saturating_sub(MAX_BROKEN_BACKUPS);
+    for (_, old_backup) in backups.drain(..excess) {
+        if let Err(error) = fs::remove_file(&old_backup) {
+            return Err(ConfigError::Io(old_backup, error));
+        }
+    }
+    Ok(backup)
+}
+
+/// `<path>.bak`, or `<path>.bak.<n>` with the lowest `n` that is not taken yet.
+pub(crate) fn free_bak_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_os_string();
+    let mut counter = 0;
+    loop {
+        let mut backup_name = file_name.clone();
+        if counter == 0 {
+            backup_name.push(".bak");
+        } else {
+            backup_name.push(format!(".bak.{}", counter));
+        }
+        let backup = path.with_file_name(backup_name);
+        if !backup.exists() {
+            return backup;
+        }
+        counter += 1;
+    }
+}
+
+fn broken_backup_path(path: &Path, timestamp: u128) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".broken-{}", timestamp));
+    path.with_file_name(file_name)
+}
+
+/// Existing backups of `path`, oldest first.
+fn broken_backups(path: &Path) -> Result<Vec<(u128, PathBuf)>, ConfigError> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = format!(
+        "{}.broken-",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => return Err(ConfigError::from_io(dir.to_path_buf(), error)),
+    };
+    let mut backups: Vec<(u128, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let timestamp = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .parse::<u128>()
+                .ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{backup_broken_file, broken_backups, MAX_BROKEN_BACKUPS};
+    use crate::test_support::test_dir;
+
+    #[test]
+    fn test_backup_rotation() {
+        let dir = test_dir("test_backup_rotation");
+        let config_file = dir.join("config.toml");
+
+        fs::write(&config_file, "broken = ").expect("Could not write test config.");
+        let first = backup_broken_file(&config_file).expect("Could not back up config.");
+        let again = backup_broken_file(&config_file).expect("Could not back up config.");
+        assert_eq!(first, again);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "broken = ");
+
+        for attempt in 0..5 {
+            fs::write(&config_file, format!("broken{} = ", attempt))
+                .expect("Could not write test config.");
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            backup_broken_file(&config_file).expect("Could not back up config.");
+        }
+        let backups = broken_backups(&config_file).expect("Could not list backups.");
+        assert_eq!(backups.len(), MAX_BROKEN_BACKUPS);
+        let newest = fs::read_to_string(&backups.last().unwrap().1).unwrap();
+        assert_eq!(newest, "broken4 = ");
+        fs::remove_dir_all(&dir).expect("Could not remove testfolder again.");
+    }
+}
@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{format, value::merge, ConfigError};
+
+/// Merges the set fields of `cli`, e.g. a `clap::Parser` struct of `Option` fields, over `file`.
+/// Nested sections are merged field by field, `None` keeps the value of the file.
+/// Fields of `cli` the optional config does not know, like `--verbose`, are ignored.
+pub fn merge_optional<OptionalConfig, Cli>(
+    file: OptionalConfig,
+    cli: &Cli,
+) -> Result<OptionalConfig, ConfigError>
+where
+    OptionalConfig: Serialize + DeserializeOwned,
+    Cli: Serialize,
+{
+    let mut merged = to_table(&file)?;
+    merge(&mut merged, to_table(cli)?);
+    format::from_value(Path::new(""), toml::Value::Table(merged), &mut Vec::new())
+}
+
+fn to_table<T: Serialize>(value: &T) -> Result<toml::Table, ConfigError> {
+    match toml::Table::try_from(value) {
+        Ok(table) => Ok(table),
+        Err(error) => Err(ConfigError::Serialize(PathBuf::new(), Box::new(error))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{merge_optional, Config, ConfigOptional};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct OptWindow {
+        width: Option<u32>,
+        height: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct OptConf {
+        timeout: Option<u32>,
+        css: Option<String>,
+        window: Option<OptWindow>,
+    }
+
+    impl ConfigOptional for OptConf {}
+
+    #[derive(Debug)]
+    struct Conf {
+        timeout: u32,
+        css: String,
+        width: u32,
+        height: u32,
+    }
+
+    impl Config<OptConf> for Conf {
+        fn create_from_optional(optional: OptConf) -> Self {
+            let window = optional.window.unwrap_or(OptWindow {
+                width: None,
+                height: None,
+            });
+            Conf {
+                timeout: optional.timeout.unwrap_or(3),
+                css: optional.css.unwrap_or_else(|| String::from("style.css")),
+                width: window.width.unwrap_or(100),
+                height: window.height.unwrap_or(20),
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    struct WindowArgs {
+        width: Option<u32>,
+    }
+
+    /// What a `clap::Parser` struct mirroring some of the config fields looks like.
+    #[derive(Serialize)]
+    struct Args {
+        timeout: Option<u32>,
+        css: Option<String>,
+        window: WindowArgs,
+        verbose: bool,
+    }
+
+    #[test]
+    fn test_merge_optional() {
+        let file: OptConf = toml::from_str("css = \"file.css\"\n[window]\nheight = 30").unwrap();
+        let args = Args {
+            timeout: Some(5),
+            css: None,
+            window: WindowArgs { width: Some(200) },
+            verbose: true,
+        };
+        let merged = merge_optional(file, &args).expect("Could not merge arguments.");
+        let conf = Conf::create_from_optional(merged);
+        assert_eq!(conf.timeout, 5);
+        assert_eq!(conf.css, "file.css");
+        assert_eq!((conf.width, conf.height), (200, 30));
+
+        let file: OptConf = toml::from_str("timeout = 1").unwrap();
+        let cli = OptConf {
+            timeout: None,
+            css: Some(String::from("cli.css")),
+            window: None,
+        };
+        let merged = merge_optional(file, &cli).expect("Could not merge arguments.");
+        assert_eq!(
+            merged,
+            OptConf {
+                timeout: Some(1),
+                css: Some(String::from("cli.css")),
+                window: None,
+            }
+        );
+    }
+}
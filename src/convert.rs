@@ -0,0 +1,141 @@
+use std::{fs, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    presence, read_file, value::get_path, write::write_atomic, ConfigError, FieldStatus, Format,
+    DEFAULT_SIZE_LIMIT,
+};
+
+/// What was lost while converting a config file with [`convert_config`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConversionReport {
+    /// Keys of the input unknown to the optional config, they are not written to the output.
+    pub dropped_keys: Vec<String>,
+    /// Fields that were set in the input but are missing in the output,
+    /// because the output format can't represent them.
+    pub unrepresentable_keys: Vec<String>,
+}
+
+/// Parses `input` into `OptionalConfig` and writes it to `output`, both formats detected by
+/// the file extension. Fails with [`ConfigError::AlreadyExists`] if `output` exists and `force`
+/// is not set. Use [`toml::Table`] as `OptionalConfig` to keep every key of the input.
+pub fn convert_config<OptionalConfig>(
+    input: &Path,
+    output: &Path,
+    force: bool,
+) -> Result<ConversionReport, ConfigError>
+where
+    OptionalConfig: DeserializeOwned + Serialize,
+{
+    if !force && output.exists() {
+        return Err(ConfigError::AlreadyExists(output.to_path_buf()));
+    }
+    let contents = read_file(input, DEFAULT_SIZE_LIMIT)?;
+    let mut report = ConversionReport::default();
+    let parsed_conf: OptionalConfig =
+        Format::detect(input).parse_file(input, &contents, &mut report.dropped_keys)?;
+    let output_format = Format::detect(output);
+    let converted = match output_format.serialize(&parsed_conf) {
+        Ok(converted) => converted,
+        Err(error) => return Err(ConfigError::Serialize(output.to_path_buf(), error)),
+    };
+    // reading the output back reveals fields the serializer skipped
+    let written: Result<toml::Table, _> =
+        output_format.parse_file(output, &converted, &mut Vec::new());
+    if let Ok(written) = written {
+        let written = toml::Value::Table(written);
+        report.unrepresentable_keys = presence::field_statuses(&parsed_conf)
+            .into_iter()
+            .filter(|(key, status)| {
+                *status == FieldStatus::Present && get_path(&written, key).is_none()
+            })
+            .map(|(key, _)| key)
+            .collect();
+    }
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        if let Err(error) = fs::create_dir_all(parent) {
+            return Err(ConfigError::Io(parent.to_path_buf(), error));
+        }
+    }
+    write_atomic(output, &converted)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{convert_config, test_support::test_dir, ConfigError, ConfigOptional};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct OptWindow {
+        width: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct OptConf {
+        something: Option<u32>,
+        window: Option<OptWindow>,
+    }
+
+    impl ConfigOptional for OptConf {}
+
+    #[test]
+    fn test_convert_config() {
+        let config_dir = test_dir("test_convert");
+        let input = config_dir.join("config.toml");
+        let output = config_dir.join("converted").join("config.toml");
+        fs::write(
+            &input,
+            "# comments are lost\nsomething = 1\nunknown = true\n[window]\nwidth = 2\n",
+        )
+        .expect("Could not write test config.");
+        let report =
+            convert_config::<OptConf>(&input, &output, false).expect("Could not convert config.");
+        assert_eq!(report.dropped_keys, vec![String::from("unknown")]);
+        assert!(report.unrepresentable_keys.is_empty());
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "something = 1\n\n[window]\nwidth = 2\n"
+        );
+
+        let error = convert_config::<OptConf>(&input, &output, false)
+            .expect_err("Existing output was overwritten.");
+        assert!(matches!(error, ConfigError::AlreadyExists(_)));
+        let report = convert_config::<toml::Table>(&input, &output, true)
+            .expect("Could not convert config.");
+        assert!(report.dropped_keys.is_empty());
+        assert!(fs::read_to_string(&output)
+            .unwrap()
+            .contains("unknown = true"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_convert_config_json() {
+        let config_dir = test_dir("test_convert_json");
+        let input = config_dir.join("config.json");
+        let output = config_dir.join("config.toml");
+        fs::write(&input, "{\"something\": 3, \"window\": {\"width\": 4}}")
+            .expect("Could not write test config.");
+        convert_config::<OptConf>(&input, &output, true).expect("Could not convert config.");
+        let converted: OptConf = toml::from_str(&fs::read_to_string(&output).unwrap())
+            .expect("Could not parse converted config.");
+        assert_eq!(
+            converted,
+            OptConf {
+                something: Some(3),
+                window: Some(OptWindow { width: Some(4) }),
+            }
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
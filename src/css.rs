@@ -0,0 +1,666 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{read_specific_css, try_create_css, ConfigError, CssState};
+
+/// The shipped stylesheet of [`write_css_layers`], replaced on every start.
+pub const DEFAULT_CSS_FILE: &str = "style.css";
+
+/// The stylesheet of the user for [`write_css_layers`], never written once it exists.
+pub const USER_CSS_FILE: &str = "user.css";
+
+/// The combined stylesheet of [`load_css_layers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CssLayers {
+    /// The default css followed by the user css.
+    pub css: String,
+    pub default_found: bool,
+    pub user_found: bool,
+}
+
+/// Writes `default_css` to [`DEFAULT_CSS_FILE`] in `config_dir`, overwriting the previous
+/// version, and creates an empty [`USER_CSS_FILE`] next to it if there is none. Users customize
+/// the latter, so they keep receiving changes of the default css.
+pub fn write_css_layers(config_dir: &Path, default_css: &str) -> Result<(), ConfigError> {
+    let default_file = config_dir.join(DEFAULT_CSS_FILE);
+    if let Err(error) = fs::write(&default_file, default_css) {
+        return Err(ConfigError::from_io(default_file, error));
+    }
+    let user_file = config_dir.join(USER_CSS_FILE);
+    if !user_file.exists() {
+        if let Err(error) = fs::File::create(&user_file) {
+            return Err(ConfigError::from_io(user_file, error));
+        }
+    }
+    Ok(())
+}
+
+/// Reads the layers of [`write_css_layers`] in `config_dir`, the default css first so the
+/// rules of the user win. A missing file is left out.
+pub fn load_css_layers(config_dir: &Path) -> Result<CssLayers, ConfigError> {
+    let mut css = String::new();
+    let mut found = [false; 2];
+    for (file, found) in [DEFAULT_CSS_FILE, USER_CSS_FILE]
+        .into_iter()
+        .zip(&mut found)
+    {
+        let layer = match read_specific_css(config_dir.join(file)) {
+            Ok(layer) => layer,
+            Err(ConfigError::NotFound(_)) => continue,
+            Err(error) => return Err(error),
+        };
+        *found = true;
+        if !css.is_empty() && !layer.is_empty() && !css.ends_with('\n') {
+            css.push('\n');
+        }
+        css.push_str(&layer);
+    }
+    Ok(CssLayers {
+        css,
+        default_found: found[0],
+        user_found: found[1],
+    })
+}
+
+/// A color scheme of the stylesheets of [`create_css_variants`], can be read from a config
+/// as `"light"`, `"dark"` or `"auto"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    Light,
+    Dark,
+    /// Dark if the `GTK_THEME` environment variable names a dark theme, e.g. `Adwaita:dark`,
+    /// light otherwise, including when it is unset.
+    #[default]
+    Auto,
+}
+
+impl Variant {
+    /// Resolves [`Variant::Auto`] to light or dark, the others are returned as they are.
+    pub fn resolve(self) -> Variant {
+        match self {
+            Variant::Auto => auto_variant(std::env::var("GTK_THEME").ok().as_deref()),
+            variant => variant,
+        }
+    }
+
+    fn file_name(self, base_name: &str) -> String {
+        match self.resolve() {
+            Variant::Dark => format!("{}-dark.css", base_name),
+            _ => format!("{}-light.css", base_name),
+        }
+    }
+}
+
+fn auto_variant(gtk_theme: Option<&str>) -> Variant {
+    match gtk_theme {
+        Some(theme) if theme.to_lowercase().contains("dark") => Variant::Dark,
+        _ => Variant::Light,
+    }
+}
+
+/// Like [`create_css`](crate::create_css) for both `{base_name}-light.css` and
+/// `{base_name}-dark.css`, returns the light and the dark file.
+pub fn create_css_variants(
+    config_dir: &Path,
+    base_name: &str,
+    light_content: &str,
+    dark_content: &str,
+) -> (PathBuf, PathBuf) {
+    match try_create_css_variants(config_dir, base_name, light_content, dark_content) {
+        Ok([(light_file, _), (dark_file, _)]) => (light_file, dark_file),
+        Err(error) => panic!("Could not create css file: {}", error),
+    }
+}
+
+/// Like [`try_create_css`] for both variants, see [`create_css_variants`].
+pub fn try_create_css_variants(
+    config_dir: &Path,
+    base_name: &str,
+    light_content: &str,
+    dark_content: &str,
+) -> Result<[(PathBuf, CssState); 2], ConfigError> {
+    let light = try_create_css(
+        config_dir,
+        &Variant::Light.file_name(base_name),
+        light_content,
+    )?;
+    let dark = try_create_css(
+        config_dir,
+        &Variant::Dark.file_name(base_name),
+        dark_content,
+    )?;
+    Ok([light, dark])
+}
+
+/// Reads the `variant` of the stylesheets of [`create_css_variants`].
+pub fn load_css_variant(
+    config_dir: &Path,
+    base_name: &str,
+    variant: Variant,
+) -> Result<String, ConfigError> {
+    read_specific_css(config_dir.join(variant.file_name(base_name)))
+}
+
+/// A likely mistake in a stylesheet found by [`validate_css`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssWarning {
+    /// Starting at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl CssWarning {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        CssWarning {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for CssWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Checks `content` for mistakes that make GTK skip large parts of a stylesheet: unbalanced
+/// braces and parentheses, unterminated strings and comments and empty selectors.
+/// A lint, valid css is not necessarily free of warnings and the other way around.
+/// The warnings are sorted by line.
+pub fn validate_css(content: &str) -> Vec<CssWarning> {
+    let mut warnings = Vec::new();
+    // the open brackets and their lines
+    let mut open: Vec<(char, usize)> = Vec::new();
+    // the text since the end of the last rule or declaration
+    let mut selector = String::new();
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\n' => {
+                line += 1;
+                selector.push(char);
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let start = line;
+                let mut previous = ' ';
+                let mut terminated = false;
+                for char in chars.by_ref() {
+                    if char == '\n' {
+                        line += 1;
+                    } else if previous == '*' && char == '/' {
+                        terminated = true;
+                        break;
+                    }
+                    previous = char;
+                }
+                if !terminated {
+                    warnings.push(CssWarning::new(start, "Unterminated comment"));
+                }
+            }
+            '"' | '\'' => {
+                selector.push(char);
+                let mut terminated = false;
+                while let Some(next) = chars.next() {
+                    match next {
+                        // an escaped newline continues the string
+                        '\\' if chars.next() == Some('\n') => line += 1,
+                        '\n' => break,
+                        next if next == char => {
+                            terminated = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if !terminated {
+                    warnings.push(CssWarning::new(line, "Unterminated string"));
+                    line += 1;
+                }
+                selector.push(char);
+            }
+            '{' => {
+                if is_empty_selector(&selector) {
+                    warnings.push(CssWarning::new(line, "Empty selector"));
+                }
+                selector.clear();
+                open.push((char, line));
+            }
+            '}' => {
+                while let Some(&('(', start)) = open.last() {
+                    warnings.push(CssWarning::new(start, "Unclosed `(`"));
+                    open.pop();
+                }
+                if open.pop().is_none() {
+                    warnings.push(CssWarning::new(line, "Unmatched `}`"));
+                }
+                selector.clear();
+            }
+            '(' => {
+                open.push((char, line));
+                selector.push(char);
+            }
+            ')' => {
+                if let Some(&('(', _)) = open.last() {
+                    open.pop();
+                } else {
+                    warnings.push(CssWarning::new(line, "Unmatched `)`"));
+                }
+                selector.push(char);
+            }
+            ';' if !open.iter().any(|(bracket, _)| *bracket == '(') => selector.clear(),
+            char => selector.push(char),
+        }
+    }
+    for (bracket, start) in open {
+        warnings.push(CssWarning::new(start, format!("Unclosed `{}`", bracket)));
+    }
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+/// Whether the selector is missing or one of a list of selectors is, e.g. `a, { … }`.
+fn is_empty_selector(selector: &str) -> bool {
+    selector.split(',').any(|part| part.trim().is_empty())
+}
+
+/// Like [`read_specific_css`] with the warnings of [`validate_css`] for the content.
+pub fn read_specific_css_with_warnings(
+    absolute_path: impl AsRef<Path>,
+) -> Result<(String, Vec<CssWarning>), ConfigError> {
+    let css = read_specific_css(absolute_path)?;
+    let warnings = validate_css(&css);
+    Ok((css, warnings))
+}
+
+/// Why [`render_css`] failed, offsets are in bytes into the template.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CssError {
+    /// The placeholder names no value of the config.
+    UnknownPlaceholder { name: String, offset: usize },
+    /// The placeholder names a table, or an array containing one.
+    NotAValue { name: String, offset: usize },
+    /// The placeholder is never closed.
+    Unterminated { offset: usize },
+    /// The values can't be serialized to toml.
+    Serialize(toml::ser::Error),
+}
+
+impl Display for CssError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CssError::UnknownPlaceholder { name, offset } => {
+                write!(f, "Unknown placeholder `{}` at byte {}", name, offset)
+            }
+            CssError::NotAValue { name, offset } => write!(
+                f,
+                "Placeholder `{}` at byte {} is a table, not a value",
+                name, offset
+            ),
+            CssError::Unterminated { offset } => {
+                write!(f, "Placeholder at byte {} is never closed", offset)
+            }
+            CssError::Serialize(error) => write!(f, "Could not serialize values: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for CssError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CssError::Serialize(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces the placeholders `{{key}}` and `@oxi(key)` in `template` with the values of the
+/// serialized `values`, e.g. `{{colors.accent}}` with `#ff7f50` for `[colors] accent = "#ff7f50"`.
+/// Keys are dotted paths, array entries are selected by their index, whole arrays are joined
+/// with `, `. `\{{` and `\@oxi(` are written as `{{` and `@oxi(` without replacing anything.
+pub fn render_css<Values>(template: &str, values: &Values) -> Result<String, CssError>
+where
+    Values: Serialize,
+{
+    let values = match toml::Value::try_from(values) {
+        Ok(values) => values,
+        Err(error) => return Err(CssError::Serialize(error)),
+    };
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['\\', '{', '@']) {
+        let offset = template.len() - rest.len() + start;
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (end, close) = if rest.starts_with("{{") {
+            (2, "}}")
+        } else if rest.starts_with("@oxi(") {
+            (5, ")")
+        } else {
+            // the backslash of an escaped placeholder is dropped, other characters are kept
+            let taken = match ["\\{{", "\\@oxi("]
+                .into_iter()
+                .find(|escaped| rest.starts_with(escaped))
+            {
+                Some(escaped) => {
+                    rendered.push_str(&escaped[1..]);
+                    escaped.len()
+                }
+                None => {
+                    rendered.push_str(&rest[..1]);
+                    1
+                }
+            };
+            rest = &rest[taken..];
+            continue;
+        };
+        let Some(len) = rest[end..].find(close) else {
+            return Err(CssError::Unterminated { offset });
+        };
+        let name = rest[end..end + len].trim();
+        match lookup(&values, name) {
+            Some(value) => match stringify(value) {
+                Some(value) => rendered.push_str(&value),
+                None => {
+                    return Err(CssError::NotAValue {
+                        name: name.to_string(),
+                        offset,
+                    })
+                }
+            },
+            None => {
+                return Err(CssError::UnknownPlaceholder {
+                    name: name.to_string(),
+                    offset,
+                })
+            }
+        }
+        rest = &rest[end + len + close.len()..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn lookup<'a>(value: &'a toml::Value, name: &str) -> Option<&'a toml::Value> {
+    if name.is_empty() {
+        return None;
+    }
+    name.split('.').try_fold(value, |value, key| match value {
+        toml::Value::Table(table) => table.get(key),
+        toml::Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn stringify(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(string) => Some(string.clone()),
+        toml::Value::Array(array) => {
+            let values: Option<Vec<String>> = array.iter().map(stringify).collect();
+            Some(values?.join(", "))
+        }
+        toml::Value::Table(_) => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Concatenates the `(name, content)` pairs of `fragments` in the given order, each one
+/// preceded by a comment naming it, e.g. for a default stylesheet split per widget.
+pub fn bundle_css(fragments: &[(&str, &str)]) -> String {
+    let mut bundle = String::new();
+    for (name, content) in fragments {
+        if !bundle.is_empty() {
+            bundle.push('\n');
+        }
+        // a name can't end the comment early
+        bundle.push_str(&format!("/* {} */\n", name.replace("*/", "* /")));
+        bundle.push_str(content);
+        if !content.is_empty() && !content.ends_with('\n') {
+            bundle.push('\n');
+        }
+    }
+    bundle
+}
+
+/// Like [`create_css`](crate::create_css) with the contents of [`bundle_css`].
+/// Without fragments the file is created empty.
+pub fn create_css_bundle(config_dir: &Path, out_file: &str, fragments: &[(&str, &str)]) -> PathBuf {
+    match try_create_css_bundle(config_dir, out_file, fragments) {
+        Ok((css_file, _)) => css_file,
+        Err(error) => panic!("Could not create css file: {}", error),
+    }
+}
+
+/// Like [`try_create_css`] with the contents of [`bundle_css`].
+pub fn try_create_css_bundle(
+    config_dir: &Path,
+    out_file: &str,
+    fragments: &[(&str, &str)],
+) -> Result<(PathBuf, CssState), ConfigError> {
+    try_create_css(config_dir, out_file, &bundle_css(fragments))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde::Serialize;
+
+    use super::auto_variant;
+    use crate::{
+        bundle_css, create_css_bundle, create_css_variants, load_css_layers, load_css_variant,
+        read_specific_css_with_warnings, render_css, test_support::test_dir, try_create_css_bundle,
+        try_create_css_variants, validate_css, write_css_layers, CssError, CssState, CssWarning,
+        Variant, DEFAULT_CSS_FILE, USER_CSS_FILE,
+    };
+
+    #[test]
+    fn test_css_bundle() {
+        let fragments = [
+            ("bar.css", ".bar { color: red; }"),
+            ("popup.css", ".popup {}\n"),
+            ("tray.css", ""),
+        ];
+        assert_eq!(
+            bundle_css(&fragments),
+            "/* bar.css */\n.bar { color: red; }\n\n/* popup.css */\n.popup {}\n\n\
+             /* tray.css */\n"
+        );
+        assert_eq!(bundle_css(&[("a*/b", "")]), "/* a* /b */\n");
+        assert_eq!(bundle_css(&[]), "");
+
+        let config_dir = test_dir("test_css_bundle");
+        let _ = fs::remove_file(config_dir.join("style.css"));
+        let css = create_css_bundle(&config_dir, "style.css", &fragments);
+        assert_eq!(fs::read_to_string(&css).unwrap(), bundle_css(&fragments));
+        let (_, state) = try_create_css_bundle(&config_dir, "style.css", &[("x.css", ".x {}")])
+            .expect("Could not create css file.");
+        assert_eq!(state, CssState::Existing);
+
+        let empty = create_css_bundle(&config_dir, "empty.css", &[]);
+        assert_eq!(fs::read_to_string(empty).unwrap(), "");
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_render_css() {
+        #[derive(Serialize)]
+        struct Colors {
+            accent: String,
+        }
+
+        #[derive(Serialize)]
+        struct Values {
+            colors: Colors,
+            font_size: u32,
+            fonts: Vec<String>,
+        }
+
+        let values = Values {
+            colors: Colors {
+                accent: "#ff7f50".to_string(),
+            },
+            font_size: 12,
+            fonts: vec!["Fira Sans".to_string(), "sans-serif".to_string()],
+        };
+        let rendered = render_css(
+            ".bar { color: {{colors.accent}}; font-size: @oxi(font_size)px; }\n\
+             .tray { font-family: {{ fonts }}, @oxi(fonts.1); }\n\
+             .a::after { content: \"\\{{ \\@oxi( \\31 @media {\"; }",
+            &values,
+        )
+        .expect("Could not render css.");
+        assert_eq!(
+            rendered,
+            ".bar { color: #ff7f50; font-size: 12px; }\n\
+             .tray { font-family: Fira Sans, sans-serif, sans-serif; }\n\
+             .a::after { content: \"{{ @oxi( \\31 @media {\"; }"
+        );
+
+        let error = render_css("a {} b { color: {{colors.other}}; }", &values)
+            .expect_err("Unknown placeholder was rendered.");
+        assert!(
+            matches!(&error, CssError::UnknownPlaceholder { name, offset: 16 } if name == "colors.other")
+        );
+        assert!(error.to_string().contains("colors.other"));
+        assert!(matches!(
+            render_css("@oxi(colors)", &values),
+            Err(CssError::NotAValue { offset: 0, .. })
+        ));
+        assert!(matches!(
+            render_css("a { {{font_size }", &values),
+            Err(CssError::Unterminated { offset: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_css_layers() {
+        let config_dir = test_dir("test_css_layers");
+        let layers = load_css_layers(&config_dir).expect("Could not load css.");
+        assert_eq!(layers.css, "");
+        assert!(!layers.default_found && !layers.user_found);
+
+        write_css_layers(&config_dir, ".bar { color: red; }").expect("Could not write css.");
+        let layers = load_css_layers(&config_dir).expect("Could not load css.");
+        assert_eq!(layers.css, ".bar { color: red; }");
+        assert!(layers.default_found && layers.user_found);
+
+        fs::write(config_dir.join(USER_CSS_FILE), ".bar { color: blue; }")
+            .expect("Could not write css.");
+        write_css_layers(&config_dir, ".bar { color: green; }").expect("Could not write css.");
+        let layers = load_css_layers(&config_dir).expect("Could not load css.");
+        assert_eq!(layers.css, ".bar { color: green; }\n.bar { color: blue; }");
+
+        fs::remove_file(config_dir.join(DEFAULT_CSS_FILE)).expect("Could not remove css.");
+        let layers = load_css_layers(&config_dir).expect("Could not load css.");
+        assert_eq!(layers.css, ".bar { color: blue; }");
+        assert!(!layers.default_found && layers.user_found);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_css_variants() {
+        let config_dir = test_dir("test_css_variants");
+        let (light_file, dark_file) = create_css_variants(
+            &config_dir,
+            "style",
+            "* { color: black; }",
+            "* { color: white; }",
+        );
+        assert_eq!(light_file, config_dir.join("style-light.css"));
+        assert_eq!(dark_file, config_dir.join("style-dark.css"));
+        let css =
+            load_css_variant(&config_dir, "style", Variant::Dark).expect("Could not load css.");
+        assert_eq!(css, "* { color: white; }");
+
+        fs::write(&light_file, "").expect("Could not write css.");
+        let states = try_create_css_variants(&config_dir, "style", "* {}", "* {}")
+            .expect("Could not create css.");
+        assert_eq!(states[0].1, CssState::DefaultWritten);
+        assert_eq!(states[1].1, CssState::Existing);
+        let css =
+            load_css_variant(&config_dir, "style", Variant::Light).expect("Could not load css.");
+        assert_eq!(css, "* {}");
+
+        assert_eq!(auto_variant(Some("Adwaita:dark")), Variant::Dark);
+        assert_eq!(auto_variant(Some("Breeze-Dark")), Variant::Dark);
+        assert_eq!(auto_variant(Some("Adwaita")), Variant::Light);
+        assert_eq!(auto_variant(None), Variant::Light);
+        assert_eq!(Variant::Dark.resolve(), Variant::Dark);
+        let variant: Variant = toml::Value::String("dark".to_string())
+            .try_into()
+            .expect("Could not read variant.");
+        assert_eq!(variant, Variant::Dark);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_validate_css() {
+        let valid = "/* bar { */\n.bar, .popup {\n  content: \"}\";\n  \
+                     background: url('a(.png');\n}\n@media (min-width: 10px) { a { b: c } }\n";
+        assert_eq!(validate_css(valid), vec![]);
+
+        let lines = |css: &str| -> Vec<(usize, String)> {
+            validate_css(css)
+                .into_iter()
+                .map(|warning| (warning.line, warning.message))
+                .collect()
+        };
+        assert_eq!(
+            lines(".bar {\n  color: red;\n\n.popup { color: blue; }"),
+            vec![(1, "Unclosed `{`".to_string())]
+        );
+        assert_eq!(
+            lines(".bar { color: red; }\n}"),
+            vec![(2, "Unmatched `}`".to_string())]
+        );
+        assert_eq!(
+            lines(".bar { color: rgb(1, 2, 3; }\n.a { b: c) }"),
+            vec![
+                (1, "Unclosed `(`".to_string()),
+                (2, "Unmatched `)`".to_string())
+            ]
+        );
+        assert_eq!(
+            lines(".bar { content: \"a;\n}\n"),
+            vec![(1, "Unterminated string".to_string())]
+        );
+        assert_eq!(
+            lines(".bar {}\n/* a\n\n"),
+            vec![(2, "Unterminated comment".to_string())]
+        );
+        assert_eq!(
+            lines("{ color: red; }\n.a, { }\n.b { }"),
+            vec![
+                (1, "Empty selector".to_string()),
+                (2, "Empty selector".to_string())
+            ]
+        );
+        assert_eq!(
+            CssWarning {
+                line: 3,
+                message: "Empty selector".to_string()
+            }
+            .to_string(),
+            "line 3: Empty selector"
+        );
+
+        let config_dir = test_dir("test_css_warnings");
+        let path = config_dir.join("style.css");
+        fs::write(&path, ".bar {").expect("Could not write css.");
+        let (css, warnings) = read_specific_css_with_warnings(&path).expect("Could not read css.");
+        assert_eq!(css, ".bar {");
+        assert_eq!(warnings.len(), 1);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
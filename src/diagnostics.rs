@@ -0,0 +1,102 @@
+use std::fmt::Write;
+
+use crate::ParseError;
+
+impl ParseError {
+    /// Renders the error as an annotated snippet with the offending text underlined:
+    ///
+    /// ```text
+    /// error: invalid type: string "ten", expected u32
+    ///  --> config.toml:2:13
+    ///   |
+    /// 2 | something = "ten"
+    ///   |             ^^^^^
+    /// ```
+    pub fn render(&self) -> String {
+        let mut rendered = String::new();
+        match self.key_path() {
+            Some(key_path) => {
+                let _ = writeln!(rendered, "error: {}: {}", key_path, self.message());
+            }
+            None => {
+                let _ = writeln!(rendered, "error: {}", self.message());
+            }
+        }
+        let (span, (line, column)) = match (self.span(), self.location()) {
+            (Some(span), Some(location)) => (span, location),
+            _ => {
+                let _ = writeln!(rendered, " --> {}", self.path().display());
+                return rendered;
+            }
+        };
+        let contents = self.contents();
+        let line_start = contents[..span.start.min(contents.len())]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let line_text = contents[line_start..].lines().next().unwrap_or("");
+        let line_end = line_start + line_text.len();
+        let underline_end = span.end.clamp(span.start, line_end);
+        let underline = contents
+            .get(span.start.min(line_end)..underline_end)
+            .map_or(0, |text| text.chars().count())
+            .max(1);
+
+        let gutter = " ".repeat(line.to_string().len());
+        let _ = writeln!(
+            rendered,
+            "{}--> {}:{}:{}",
+            gutter,
+            self.path().display(),
+            line,
+            column
+        );
+        let _ = writeln!(rendered, "{} |", gutter);
+        let _ = writeln!(rendered, "{} | {}", line, line_text);
+        let _ = writeln!(
+            rendered,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(column - 1),
+            "^".repeat(underline)
+        );
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde::Deserialize;
+
+    use crate::{parse_config, ConfigError, ConfigOptional, Format};
+
+    #[derive(Debug, Deserialize)]
+    struct OptConf {
+        #[allow(dead_code)]
+        something: Option<u32>,
+    }
+
+    impl ConfigOptional for OptConf {}
+
+    #[test]
+    fn test_render() {
+        let error = parse_config::<OptConf>(
+            Path::new("config.toml"),
+            "# comment\nsomething = \"ten\"\n",
+            Format::Toml.into(),
+            &mut Vec::new(),
+        )
+        .expect_err("Broken toml was parsed successfully.");
+        let rendered = match error {
+            ConfigError::Parse(error) => error.render(),
+            _ => panic!("Expected a parse error, got {:?}", error),
+        };
+        let mut lines = rendered.lines();
+        assert!(lines.next().unwrap().contains("invalid type"));
+        assert_eq!(lines.next(), Some(" --> config.toml:2:13"));
+        assert_eq!(lines.next(), Some("  |"));
+        assert_eq!(lines.next(), Some("2 | something = \"ten\""));
+        assert_eq!(lines.next(), Some("  |             ^^^^^"));
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::ConfigError;
+
+/// The dotted key paths that differ between two versions of a config, e.g. to only restyle
+/// if nothing but `font_size` changed after a reload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigChange {
+    keys: BTreeSet<String>,
+}
+
+impl ConfigChange {
+    /// Compares the serialized configs. Keys missing on one side are reported themselves,
+    /// arrays are compared as a whole, so reordered entries change the array.
+    pub fn between<ConcreteConfig>(
+        old: &ConcreteConfig,
+        new: &ConcreteConfig,
+    ) -> Result<Self, ConfigError>
+    where
+        ConcreteConfig: Serialize,
+    {
+        Ok(ConfigChange::between_values(
+            &to_value(old)?,
+            &to_value(new)?,
+        ))
+    }
+
+    /// Like [`ConfigChange::between`] for already serialized configs.
+    pub fn between_values(old: &toml::Value, new: &toml::Value) -> Self {
+        let mut keys = BTreeSet::new();
+        diff(old, new, &mut String::new(), &mut keys);
+        ConfigChange { keys }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// The changed keys, sorted.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(String::as_str)
+    }
+
+    /// Whether `key` changed, either itself, a key below it or the table containing it,
+    /// e.g. `window` is affected by a change of `window.width` and the other way around.
+    pub fn affects(&self, key: &str) -> bool {
+        self.keys
+            .iter()
+            .any(|changed| is_within(changed, key) || is_within(key, changed))
+    }
+}
+
+/// Whether `key` is `parent` or below it.
+fn is_within(key: &str, parent: &str) -> bool {
+    parent.is_empty()
+        || key == parent
+        || key
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+pub(crate) fn to_value<ConcreteConfig: Serialize>(
+    config: &ConcreteConfig,
+) -> Result<toml::Value, ConfigError> {
+    match toml::Value::try_from(config) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(ConfigError::Serialize(PathBuf::new(), Box::new(error))),
+    }
+}
+
+fn diff(old: &toml::Value, new: &toml::Value, path: &mut String, keys: &mut BTreeSet<String>) {
+    let (toml::Value::Table(old), toml::Value::Table(new)) = (old, new) else {
+        if old != new {
+            keys.insert(path.clone());
+        }
+        return;
+    };
+    let len = path.len();
+    for key in old
+        .keys()
+        .chain(new.keys().filter(|key| !old.contains_key(*key)))
+    {
+        if len > 0 {
+            path.push('.');
+        }
+        path.push_str(key);
+        match (old.get(key), new.get(key)) {
+            (Some(old), Some(new)) => diff(old, new, path, keys),
+            _ => {
+                keys.insert(path.clone());
+            }
+        }
+        path.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use crate::ConfigChange;
+
+    #[derive(Serialize)]
+    struct Window {
+        width: u32,
+        title: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct Conf {
+        font_size: u32,
+        outputs: Vec<String>,
+        window: Window,
+    }
+
+    #[test]
+    fn test_config_change() {
+        let old = Conf {
+            font_size: 12,
+            outputs: vec!["DP-1".to_string(), "HDMI-A-1".to_string()],
+            window: Window {
+                width: 300,
+                title: None,
+            },
+        };
+        let change = ConfigChange::between(&old, &old).unwrap();
+        assert!(!change.is_changed());
+        assert!(!change.affects("window"));
+
+        let new = Conf {
+            font_size: 14,
+            outputs: vec!["HDMI-A-1".to_string(), "DP-1".to_string()],
+            window: Window {
+                width: 300,
+                title: Some("bar".to_string()),
+            },
+        };
+        let change = ConfigChange::between(&old, &new).unwrap();
+        assert!(change.is_changed());
+        assert_eq!(
+            change.keys().collect::<Vec<_>>(),
+            vec!["font_size", "outputs", "window.title"]
+        );
+        assert!(change.affects("window"));
+        assert!(change.affects("window.title"));
+        assert!(change.affects("outputs.0"));
+        assert!(!change.affects("window.width"));
+        assert!(!change.affects("font"));
+
+        let old: toml::Value = toml::from_str("a = 1").unwrap();
+        let new: toml::Value = toml::from_str("a = \"1\"\n[b]\nc = 2").unwrap();
+        let change = ConfigChange::between_values(&old, &new);
+        assert_eq!(change.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+}
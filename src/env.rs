@@ -0,0 +1,492 @@
+use std::fmt::Display;
+
+use serde::{
+    de::{self, value::SeqDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+    forward_to_deserialize_any, Deserializer,
+};
+
+use crate::{value::merge_arrays, ArrayMerge, ConfigError};
+
+/// Separates nested keys in the name of an environment variable,
+/// `APP_WINDOW__WIDTH` overrides `window.width`.
+pub(crate) const ENV_SEPARATOR: &str = "__";
+
+/// The variables of the process environment, skipping the ones that are not valid unicode.
+pub(crate) fn vars() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// A document with environment variables merged over it. The variables stay strings
+/// until the optional config asks for a type, so `APP_WHAT=42` still works for a string field.
+#[derive(Debug)]
+pub(crate) enum Overridden {
+    Value(toml::Value),
+    Var(EnvVar),
+    Table(Vec<(String, Overridden)>),
+}
+
+impl Overridden {
+    /// Merges the variables starting with `<prefix>_` over `document`, keys are lowercased.
+    pub(crate) fn new(
+        document: toml::Table,
+        prefix: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let mut overridden = Overridden::from(toml::Value::Table(document));
+        let prefix = format!("{}_", prefix);
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let path: Vec<String> = key
+                .split(ENV_SEPARATOR)
+                .map(|segment| segment.to_lowercase())
+                .collect();
+            if path.iter().any(String::is_empty) {
+                log_warn!(
+                    "Ignoring environment variable {}, it has an empty key",
+                    name
+                );
+                continue;
+            }
+            overridden.insert(&path, EnvVar { name, value });
+        }
+        overridden
+    }
+
+    /// Merges `other` over this document, tables are merged recursively.
+    pub(crate) fn merge(&mut self, other: Overridden) {
+        // replacing arrays never fails
+        let _ = self.merge_with(other, &[], &mut Vec::new());
+    }
+
+    /// Like [`Overridden::merge`], arrays are combined with the strategy for their path.
+    pub(crate) fn merge_with(
+        &mut self,
+        other: Overridden,
+        strategies: &[(String, ArrayMerge)],
+        path: &mut Vec<String>,
+    ) -> Result<(), ConfigError> {
+        match (self, other) {
+            (Overridden::Table(entries), Overridden::Table(other)) => {
+                for (key, value) in other {
+                    path.push(key.clone());
+                    match entries.iter_mut().find(|(existing, _)| *existing == key) {
+                        Some((_, existing)) => existing.merge_with(value, strategies, path)?,
+                        None => entries.push((key, value)),
+                    }
+                    path.pop();
+                }
+            }
+            (
+                Overridden::Value(toml::Value::Array(base)),
+                Overridden::Value(toml::Value::Array(other)),
+            ) => merge_arrays(base, other, strategies, path)?,
+            (this, other) => *this = other,
+        }
+        Ok(())
+    }
+
+    /// Removes `key` if this is a table.
+    pub(crate) fn remove(&mut self, key: &str) -> Option<Overridden> {
+        let Overridden::Table(entries) = self else {
+            return None;
+        };
+        let index = entries.iter().position(|(existing, _)| existing == key)?;
+        Some(entries.remove(index).1)
+    }
+
+    fn insert(&mut self, path: &[String], var: EnvVar) {
+        let Some((key, rest)) = path.split_first() else {
+            *self = Overridden::Var(var);
+            return;
+        };
+        if !matches!(self, Overridden::Table(_)) {
+            *self = Overridden::Table(Vec::new());
+        }
+        let Overridden::Table(entries) = self else {
+            return;
+        };
+        let index = match entries.iter().position(|(existing, _)| existing == key) {
+            Some(index) => index,
+            None => {
+                entries.push((key.clone(), Overridden::Table(Vec::new())));
+                entries.len() - 1
+            }
+        };
+        entries[index].1.insert(rest, var);
+    }
+}
+
+impl From<toml::Value> for Overridden {
+    fn from(value: toml::Value) -> Self {
+        match value {
+            toml::Value::Table(table) => Overridden::Table(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, Overridden::from(value)))
+                    .collect(),
+            ),
+            value => Overridden::Value(value),
+        }
+    }
+}
+
+macro_rules! forward_overridden {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+                match self {
+                    Overridden::Value(value) => value.$method(visitor),
+                    Overridden::Var(var) => var.$method(visitor),
+                    Overridden::Table(entries) => visit_table(entries, visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Overridden {
+    type Error = toml::de::Error;
+
+    forward_overridden! {
+        deserialize_any, deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32,
+        deserialize_i64, deserialize_i128, deserialize_u8, deserialize_u16, deserialize_u32,
+        deserialize_u64, deserialize_u128, deserialize_f32, deserialize_f64, deserialize_char,
+        deserialize_str, deserialize_string, deserialize_bytes, deserialize_byte_buf,
+        deserialize_unit, deserialize_seq, deserialize_map,
+        deserialize_identifier, deserialize_ignored_any,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        match self {
+            Overridden::Var(var) => var.deserialize_option(visitor),
+            overridden => visitor.visit_some(overridden),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        match self {
+            Overridden::Value(value) => value.deserialize_struct(name, fields, visitor),
+            Overridden::Var(var) => Err(var.invalid("a table")),
+            Overridden::Table(entries) => visit_table(entries, visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        match self {
+            Overridden::Value(value) => value.deserialize_enum(name, variants, visitor),
+            Overridden::Var(var) => var.deserialize_enum(name, variants, visitor),
+            Overridden::Table(entries) => visit_table(entries, visitor),
+        }
+    }
+}
+
+fn visit_table<'de, V: Visitor<'de>>(
+    entries: Vec<(String, Overridden)>,
+    visitor: V,
+) -> Result<V::Value, toml::de::Error> {
+    visitor.visit_map(TableAccess {
+        entries: entries.into_iter(),
+        value: None,
+    })
+}
+
+struct TableAccess {
+    entries: std::vec::IntoIter<(String, Overridden)>,
+    value: Option<Overridden>,
+}
+
+impl<'de> MapAccess<'de> for TableAccess {
+    type Error = toml::de::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, toml::de::Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value requested before its key")),
+        }
+    }
+}
+
+/// The value of an environment variable, converted to the type the optional config asks for.
+/// Sequences are written as comma separated lists, an empty value is `None`.
+#[derive(Debug)]
+pub(crate) struct EnvVar {
+    pub(crate) name: String,
+    value: String,
+}
+
+impl EnvVar {
+    fn invalid(&self, expected: impl Display) -> toml::de::Error {
+        de::Error::custom(format!(
+            "invalid value `{}` of {}, expected {}",
+            self.value, self.name, expected
+        ))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $expected:literal),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+                match self.value.trim().parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(self.invalid($expected)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for EnvVar {
+    type Error = toml::de::Error;
+
+    /// Without a requested type, the value is whatever it looks like.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        let value = self.value.trim();
+        if let Ok(value) = value.parse::<bool>() {
+            visitor.visit_bool(value)
+        } else if let Ok(value) = value.parse::<i64>() {
+            visitor.visit_i64(value)
+        } else if let Ok(value) = value.parse::<f64>() {
+            visitor.visit_f64(value)
+        } else {
+            visitor.visit_string(self.value)
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        match self.value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => visitor.visit_bool(true),
+            "0" | "false" | "no" | "off" => visitor.visit_bool(false),
+            _ => Err(self.invalid("a bool")),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: "an integer",
+        deserialize_i16 => visit_i16: "an integer",
+        deserialize_i32 => visit_i32: "an integer",
+        deserialize_i64 => visit_i64: "an integer",
+        deserialize_i128 => visit_i128: "an integer",
+        deserialize_u8 => visit_u8: "an unsigned integer",
+        deserialize_u16 => visit_u16: "an unsigned integer",
+        deserialize_u32 => visit_u32: "an unsigned integer",
+        deserialize_u64 => visit_u64: "an unsigned integer",
+        deserialize_u128 => visit_u128: "an unsigned integer",
+        deserialize_f32 => visit_f32: "a number",
+        deserialize_f64 => visit_f64: "a number",
+        deserialize_char => visit_char: "a single character",
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_string(self.value)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_string(self.value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    /// Comma separated items, or an inline array like `["a", "b"]` written by [`crate::to_env_vars`].
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        if self.value.trim_start().starts_with('[') {
+            let parsed: Result<toml::Table, _> = toml::from_str(&format!("value = {}", self.value));
+            if let Some(array) = parsed.ok().and_then(|mut parsed| parsed.remove("value")) {
+                return array.deserialize_seq(visitor);
+            }
+        }
+        let items: Vec<EnvVar> = if self.value.trim().is_empty() {
+            Vec::new()
+        } else {
+            self.value
+                .split(',')
+                .map(|item| EnvVar {
+                    name: self.name.clone(),
+                    value: item.trim().to_string(),
+                })
+                .collect()
+        };
+        let items: SeqDeserializer<_, toml::de::Error> = SeqDeserializer::new(items.into_iter());
+        items.deserialize_any(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, toml::de::Error> for EnvVar {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde::Deserialize;
+
+    use crate::{env::Overridden, format::from_value, ConfigError};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct OptWindow {
+        width: Option<u32>,
+        title: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct OptConf {
+        something: Option<u32>,
+        what: Option<String>,
+        enabled: Option<bool>,
+        outputs: Option<Vec<String>>,
+        window: Option<OptWindow>,
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let document: toml::Table =
+            toml::from_str("something = 1\nwhat = \"file\"\n[window]\nwidth = 2\ntitle = \"a\"")
+                .unwrap();
+        let overridden = Overridden::new(
+            document,
+            "MYAPP",
+            vars(&[
+                ("MYAPP_SOMETHING", "42"),
+                ("MYAPP_WHAT", "42"),
+                ("MYAPP_ENABLED", "true"),
+                ("MYAPP_OUTPUTS", "DP-1, HDMI-A-1"),
+                ("MYAPP_WINDOW__WIDTH", "3"),
+                ("OTHER_SOMETHING", "5"),
+                ("MYAPP_BROKEN__", "1"),
+            ]),
+        );
+        let mut unknown_keys = Vec::new();
+        let conf: OptConf = from_value(Path::new(""), overridden, &mut unknown_keys)
+            .expect("Could not apply overrides.");
+        assert_eq!(
+            conf,
+            OptConf {
+                something: Some(42),
+                what: Some(String::from("42")),
+                enabled: Some(true),
+                outputs: Some(vec![String::from("DP-1"), String::from("HDMI-A-1")]),
+                window: Some(OptWindow {
+                    width: Some(3),
+                    title: Some(String::from("a")),
+                }),
+            }
+        );
+        assert!(unknown_keys.is_empty());
+
+        let overridden = Overridden::new(
+            toml::Table::new(),
+            "MYAPP",
+            vars(&[("MYAPP_SOMETHING", "many"), ("MYAPP_TYPO", "1")]),
+        );
+        let error = from_value::<_, OptConf>(Path::new(""), overridden, &mut Vec::new())
+            .expect_err("Invalid override was parsed.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        assert!(error.to_string().contains("MYAPP_SOMETHING"));
+
+        let overridden = Overridden::new(toml::Table::new(), "MYAPP", vars(&[("MYAPP_TYPO", "1")]));
+        let mut unknown_keys = Vec::new();
+        from_value::<_, OptConf>(Path::new(""), overridden, &mut unknown_keys)
+            .expect("Could not apply overrides.");
+        assert_eq!(unknown_keys, vec![String::from("typo")]);
+    }
+}
@@ -0,0 +1,686 @@
+use std::{
+    fmt::{Debug, Display},
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::Format;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    NoHomeDirectory,
+    NotFound(PathBuf),
+    NotAFile(PathBuf),
+    PermissionDenied(PathBuf, io::Error),
+    Io(PathBuf, io::Error),
+    Parse(Box<ParseError>),
+    DefaultConfigInvalid(Box<ParseError>),
+    Conversion(PathBuf, Box<dyn std::error::Error + Send + Sync>),
+    UnknownKeys(PathBuf, Vec<String>),
+    Validation(PathBuf, Vec<ValidationError>),
+    FileTooLarge {
+        path: PathBuf,
+        size: u64,
+        limit: u64,
+    },
+    Serialize(PathBuf, Box<dyn std::error::Error + Send + Sync>),
+    /// The file extension does not belong to a known format, raised by strict loaders
+    /// and when loading with [`Format::Custom`] directly.
+    UnknownFormat(PathBuf),
+    /// The file would have been overwritten.
+    AlreadyExists(PathBuf),
+    /// A file includes itself, the chain of includes ends with the file included again.
+    IncludeCycle(Vec<PathBuf>),
+    /// Includes are nested deeper than [`crate::MAX_INCLUDE_DEPTH`].
+    IncludeTooDeep(Vec<PathBuf>),
+    /// The `include` key is neither a path nor a list of paths.
+    InvalidInclude(PathBuf),
+    /// The section is not a table.
+    InvalidSection(PathBuf, String),
+    /// A `path=value` override, e.g. from the command line, and why it is invalid.
+    InvalidOverride(String, String),
+    /// The key whose value references it and the name of an unset environment variable,
+    /// see [`crate::interpolate_env`].
+    UnsetVariable(String, String),
+    /// The dotted path of an array merged by a key and the key,
+    /// one of its entries is not a table with that key, see [`crate::ArrayMerge::MergeByKey`].
+    MissingMergeKey(String, String),
+    /// The selected profile is not in the file, `available` lists the ones that are.
+    UnknownProfile {
+        path: PathBuf,
+        profile: String,
+        available: Vec<String>,
+    },
+    /// The selected theme is not in the themes folder at `path`, `available` lists the ones
+    /// that are, see [`crate::load_theme`].
+    UnknownTheme {
+        path: PathBuf,
+        theme: String,
+        available: Vec<String>,
+    },
+    /// The file can't be watched for changes.
+    Watch(PathBuf, Box<dyn std::error::Error + Send + Sync>),
+    /// The handler of a signal can't be registered.
+    Signal(io::Error),
+}
+
+impl ConfigError {
+    pub(crate) fn from_io(path: PathBuf, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => ConfigError::NotFound(path),
+            io::ErrorKind::PermissionDenied => ConfigError::PermissionDenied(path, error),
+            _ => ConfigError::Io(path, error),
+        }
+    }
+
+    /// Attributes an error from deserializing the table `section` of `path` to that file,
+    /// key paths are prefixed with the section.
+    pub(crate) fn in_section(self, path: &Path, section: &str) -> Self {
+        let path = path.to_path_buf();
+        match self {
+            ConfigError::Parse(mut error) => {
+                if error.path.as_os_str().is_empty() {
+                    error.path = path;
+                }
+                error.key_path = Some(match error.key_path.take() {
+                    Some(key_path) => format!("{}.{}", section, key_path),
+                    None => section.to_string(),
+                });
+                ConfigError::Parse(error)
+            }
+            ConfigError::Conversion(empty, error) if empty.as_os_str().is_empty() => {
+                ConfigError::Conversion(path, error)
+            }
+            ConfigError::Validation(empty, errors) if empty.as_os_str().is_empty() => {
+                ConfigError::Validation(path, errors)
+            }
+            error => error,
+        }
+    }
+
+    /// The file the error occurred in, `None` for errors converted from a bare
+    /// [`io::Error`] or [`toml::de::Error`].
+    pub fn path(&self) -> Option<&Path> {
+        let path: Option<&Path> = match self {
+            ConfigError::NoHomeDirectory => None,
+            ConfigError::NotFound(path) => Some(path),
+            ConfigError::NotAFile(path) => Some(path),
+            ConfigError::PermissionDenied(path, _) => Some(path),
+            ConfigError::Io(path, _) => Some(path),
+            ConfigError::Parse(error) => Some(error.path()),
+            ConfigError::DefaultConfigInvalid(error) => Some(error.path()),
+            ConfigError::Conversion(path, _) => Some(path),
+            ConfigError::UnknownKeys(path, _) => Some(path),
+            ConfigError::Validation(path, _) => Some(path),
+            ConfigError::FileTooLarge { path, .. } => Some(path),
+            ConfigError::Serialize(path, _) => Some(path),
+            ConfigError::UnknownFormat(path) => Some(path),
+            ConfigError::AlreadyExists(path) => Some(path),
+            ConfigError::IncludeCycle(chain) => chain.first().map(PathBuf::as_path),
+            ConfigError::IncludeTooDeep(chain) => chain.first().map(PathBuf::as_path),
+            ConfigError::InvalidInclude(path) => Some(path),
+            ConfigError::InvalidSection(path, _) => Some(path),
+            ConfigError::InvalidOverride(_, _)
+            | ConfigError::UnsetVariable(_, _)
+            | ConfigError::MissingMergeKey(_, _)
+            | ConfigError::Signal(_) => None,
+            ConfigError::UnknownProfile { path, .. } => Some(path),
+            ConfigError::UnknownTheme { path, .. } => Some(path),
+            ConfigError::Watch(path, _) => Some(path),
+        };
+        path.filter(|path| !path.as_os_str().is_empty())
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        ConfigError::from_io(PathBuf::new(), error)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        ConfigError::Parse(Box::new(ParseError::new(Path::new(""), "", error)))
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::NoHomeDirectory => f.write_str(
+                "There is no home directory, please ensure your PC has a home directory.",
+            ),
+            ConfigError::NotFound(path) => write!(f, "File not found: {}", path.display()),
+            ConfigError::NotAFile(path) => write!(f, "Not a file: {}", path.display()),
+            ConfigError::PermissionDenied(path, _) => {
+                write!(f, "Permission denied: {}", path.display())
+            }
+            ConfigError::Io(path, error) if path.as_os_str().is_empty() => {
+                write!(f, "IO error: {}", error)
+            }
+            ConfigError::Io(path, error) => {
+                write!(f, "IO error on {}: {}", path.display(), error)
+            }
+            ConfigError::Parse(error) => {
+                write!(f, "Could not parse {} (format: {})", error, error.format())
+            }
+            ConfigError::DefaultConfigInvalid(error) => write!(
+                f,
+                "Invalid default config for {} (format: {})",
+                error,
+                error.format()
+            ),
+            ConfigError::Conversion(path, error) => {
+                write!(f, "Invalid config in {}: {}", path.display(), error)
+            }
+            ConfigError::UnknownKeys(path, keys) => {
+                write!(f, "Unknown keys in {}: {}", path.display(), keys.join(", "))
+            }
+            ConfigError::Validation(path, errors) => {
+                write!(f, "Invalid config in {}: ", path.display())?;
+                for (index, error) in errors.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            ConfigError::FileTooLarge { path, size, limit } => write!(
+                f,
+                "{} is too large: {} bytes, the limit is {} bytes",
+                path.display(),
+                size,
+                limit
+            ),
+            ConfigError::Serialize(path, error) if path.as_os_str().is_empty() => {
+                write!(f, "Could not serialize config: {}", error)
+            }
+            ConfigError::Serialize(path, error) => {
+                write!(
+                    f,
+                    "Could not serialize config for {}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            ConfigError::UnknownFormat(path) => {
+                write!(f, "Unknown config format for {}", path.display())
+            }
+            ConfigError::AlreadyExists(path) => {
+                write!(f, "{} already exists", path.display())
+            }
+            ConfigError::IncludeCycle(chain) => {
+                write!(f, "Include cycle: {}", display_chain(chain))
+            }
+            ConfigError::IncludeTooDeep(chain) => write!(
+                f,
+                "Includes are nested deeper than {} files: {}",
+                crate::MAX_INCLUDE_DEPTH,
+                display_chain(chain)
+            ),
+            ConfigError::InvalidInclude(path) => write!(
+                f,
+                "`include` in {} has to be a path or a list of paths",
+                path.display()
+            ),
+            ConfigError::InvalidSection(path, section) => {
+                write!(f, "`{}` in {} has to be a table", section, path.display())
+            }
+            ConfigError::InvalidOverride(item, reason) => {
+                write!(f, "Invalid override `{}`: {}", item, reason)
+            }
+            ConfigError::UnsetVariable(key, name) => {
+                write!(f, "`{}` references the unset variable {}", key, name)
+            }
+            ConfigError::MissingMergeKey(path, key) => write!(
+                f,
+                "Every entry of `{}` has to be a table with the key `{}` to be merged",
+                path, key
+            ),
+            ConfigError::UnknownProfile {
+                path,
+                profile,
+                available,
+            } => write!(
+                f,
+                "Unknown profile `{}` in {}, available profiles: {}",
+                profile,
+                path.display(),
+                available.join(", ")
+            ),
+            ConfigError::UnknownTheme {
+                path,
+                theme,
+                available,
+            } => write!(
+                f,
+                "Unknown theme `{}` in {}, available themes: {}",
+                theme,
+                path.display(),
+                available.join(", ")
+            ),
+            ConfigError::Watch(path, error) => {
+                write!(f, "Could not watch {}: {}", path.display(), error)
+            }
+            ConfigError::Signal(error) => {
+                write!(f, "Could not register the signal handler: {}", error)
+            }
+        }
+    }
+}
+
+fn display_chain(chain: &[PathBuf]) -> String {
+    chain
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::PermissionDenied(_, error) => Some(error),
+            ConfigError::Io(_, error) => Some(error),
+            ConfigError::Parse(error) => Some(error),
+            ConfigError::DefaultConfigInvalid(error) => Some(error),
+            ConfigError::Conversion(_, error) => Some(error.as_ref()),
+            ConfigError::Serialize(_, error) => Some(error.as_ref()),
+            ConfigError::Watch(_, error) => Some(error.as_ref()),
+            ConfigError::Signal(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A violated constraint reported by [`crate::Config::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ValidationError {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[deprecated(note = "use ConfigError instead")]
+pub type ReadConfigFileError = ConfigError;
+
+#[derive(Debug)]
+pub struct ParseError {
+    path: PathBuf,
+    format: Format,
+    location: Option<(usize, usize)>,
+    span: Option<Range<usize>>,
+    key_path: Option<String>,
+    contents: String,
+    message: String,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl ParseError {
+    pub(crate) fn new(path: &Path, contents: &str, source: toml::de::Error) -> Self {
+        let span = source.span();
+        let message = source.message().to_string();
+        ParseError::with_span(
+            path,
+            contents,
+            Format::Toml,
+            message,
+            span,
+            Box::new(source),
+        )
+    }
+
+    #[cfg(feature = "json")]
+    pub(crate) fn from_json(path: &Path, contents: &str, source: serde_json::Error) -> Self {
+        let mut message = source.to_string();
+        let span = if source.line() == 0 {
+            None
+        } else {
+            // serde_json appends the location to its message, it is reported separately here
+            let suffix = format!(" at line {} column {}", source.line(), source.column());
+            if message.ends_with(&suffix) {
+                message.truncate(message.len() - suffix.len());
+            }
+            let start = offset_of(contents, source.line(), source.column());
+            Some(start..(start + 1).min(contents.len()))
+        };
+        ParseError::with_span(
+            path,
+            contents,
+            Format::Json,
+            message,
+            span,
+            Box::new(source),
+        )
+    }
+
+    #[cfg(feature = "yaml")]
+    pub(crate) fn from_yaml(path: &Path, contents: &str, source: serde_yaml::Error) -> Self {
+        let mut message = source.to_string();
+        let span = source.location().map(|location| {
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+            let start = location.index().min(contents.len());
+            start..(start + 1).min(contents.len())
+        });
+        ParseError::with_span(
+            path,
+            contents,
+            Format::Yaml,
+            message,
+            span,
+            Box::new(source),
+        )
+    }
+
+    #[cfg(feature = "ron")]
+    pub(crate) fn from_ron(path: &Path, contents: &str, source: ron::error::SpannedError) -> Self {
+        let message = source.code.to_string();
+        let span = if source.position.line == 0 {
+            None
+        } else {
+            let start = offset_of(contents, source.position.line, source.position.col);
+            Some(start..(start + 1).min(contents.len()))
+        };
+        ParseError::with_span(path, contents, Format::Ron, message, span, Box::new(source))
+    }
+
+    #[cfg(feature = "ini")]
+    pub(crate) fn from_ini(path: &Path, contents: &str, source: crate::ini::IniError) -> Self {
+        let message = source.message().to_string();
+        // ini errors are reported per line, the whole line is marked
+        let span = source.line.map(|line| {
+            let start = offset_of(contents, line, 1);
+            let end = contents[start..]
+                .find('\n')
+                .map_or(contents.len(), |newline| start + newline);
+            start..end
+        });
+        ParseError::with_span(path, contents, Format::Ini, message, span, Box::new(source))
+    }
+
+    #[cfg(feature = "json5")]
+    pub(crate) fn from_json5(path: &Path, contents: &str, source: json5::Error) -> Self {
+        let json5::Error::Message { msg, location } = &source;
+        // syntax errors come with a rendered snippet, only its last line is the actual message
+        let message = match msg.lines().last() {
+            Some(last) if msg.contains('\n') => last.trim().trim_start_matches("= ").to_string(),
+            _ => msg.clone(),
+        };
+        let span = location.as_ref().map(|location| {
+            // json5 counts columns in characters instead of bytes
+            let line_start = offset_of(contents, location.line, 1);
+            let start = contents[line_start..]
+                .char_indices()
+                .nth(location.column - 1)
+                .map_or(contents.len(), |(index, _)| line_start + index);
+            start..(start + 1).min(contents.len())
+        });
+        ParseError::with_span(
+            path,
+            contents,
+            Format::Json5,
+            message,
+            span,
+            Box::new(source),
+        )
+    }
+
+    pub(crate) fn from_format(
+        path: &Path,
+        contents: &str,
+        format: Format,
+        source: FormatError,
+    ) -> Self {
+        let FormatError {
+            message,
+            span,
+            source,
+        } = source;
+        let span = span.map(|span| span.start.min(contents.len())..span.end.min(contents.len()));
+        let source = source.unwrap_or_else(|| message.clone().into());
+        ParseError::with_span(path, contents, format, message, span, source)
+    }
+
+    fn with_span(
+        path: &Path,
+        contents: &str,
+        format: Format,
+        message: String,
+        span: Option<Range<usize>>,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    ) -> Self {
+        let location = span
+            .as_ref()
+            .map(|span| line_and_column(contents, span.start));
+        ParseError {
+            path: path.to_path_buf(),
+            format,
+            location,
+            span,
+            key_path: None,
+            contents: contents.to_string(),
+            message,
+            source,
+        }
+    }
+
+    pub(crate) fn with_key_path(mut self, key_path: String) -> Self {
+        if !key_path.is_empty() && key_path != "." {
+            self.key_path = Some(key_path);
+        }
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The format the contents were parsed as.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// One-based line and column of the start of the offending span, if the parser reported one.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        self.location
+    }
+
+    /// Byte range of the offending text within [`ParseError::contents`].
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    /// The raw text that failed to parse.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Dotted path of the offending key, e.g. `window.margins.top`.
+    /// Only available for config files with the `path-to-error` feature.
+    pub fn key_path(&self) -> Option<&str> {
+        self.key_path.as_deref()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The error reported by toml, `None` if the contents were parsed as a different format.
+    pub fn toml_error(&self) -> Option<&toml::de::Error> {
+        self.source.downcast_ref()
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.as_os_str().is_empty() {
+            write!(f, "{}:", self.path.display())?;
+        }
+        match self.location {
+            Some((line, column)) => write!(f, "{}:{}: ", line, column)?,
+            None if self.path.as_os_str().is_empty() => {}
+            None => f.write_str(" ")?,
+        }
+        if let Some(key_path) = &self.key_path {
+            write!(f, "{}: ", key_path)?;
+        }
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// The error of a [`crate::ConfigFormat`], turned into a [`ParseError`] by the loaders.
+#[derive(Debug)]
+pub struct FormatError {
+    message: String,
+    span: Option<Range<usize>>,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl FormatError {
+    pub fn new(message: impl Into<String>) -> Self {
+        FormatError {
+            message: message.into(),
+            span: None,
+            source: None,
+        }
+    }
+
+    /// Byte range of the offending text, used for the line and column of the [`ParseError`].
+    pub fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// The error of the underlying parser.
+    pub fn with_source(mut self, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+}
+
+impl From<ParseError> for FormatError {
+    fn from(error: ParseError) -> Self {
+        FormatError {
+            message: error.message,
+            span: error.span,
+            source: Some(error.source),
+        }
+    }
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(source.as_ref()),
+            None => None,
+        }
+    }
+}
+
+fn line_and_column(contents: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(contents.len());
+    let before = &contents[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = match before.rfind('\n') {
+        Some(newline) => before[newline + 1..].chars().count() + 1,
+        None => before.chars().count() + 1,
+    };
+    (line, column)
+}
+
+/// Byte offset of a one-based line and column as reported by serde_json and ron.
+#[cfg(any(feature = "json", feature = "ron", feature = "ini", feature = "json5"))]
+fn offset_of(contents: &str, line: usize, column: usize) -> usize {
+    let line_start = contents
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum::<usize>();
+    (line_start + column.saturating_sub(1)).min(contents.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, io};
+
+    use crate::ConfigError;
+
+    fn chain_len(error: &dyn Error) -> usize {
+        let mut len = 1;
+        let mut current = error;
+        while let Some(source) = current.source() {
+            len += 1;
+            current = source;
+        }
+        len
+    }
+
+    #[test]
+    fn test_from_io_error() {
+        let error = ConfigError::from(io::Error::other("disk on fire"));
+        assert!(matches!(error, ConfigError::Io(_, _)));
+        assert_eq!(error.path(), None);
+        assert_eq!(error.to_string(), "IO error: disk on fire");
+        assert_eq!(chain_len(&error), 2);
+
+        let error = ConfigError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(error, ConfigError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_from_toml_error() {
+        fn parse() -> Result<toml::Table, ConfigError> {
+            Ok(toml::from_str("key = ")?)
+        }
+
+        let error = parse().expect_err("Broken toml was parsed successfully.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        assert_eq!(error.path(), None);
+        // ConfigError -> ParseError -> toml::de::Error
+        assert_eq!(chain_len(&error), 3);
+        let root = error.source().and_then(Error::source).unwrap();
+        assert!(root.downcast_ref::<toml::de::Error>().is_some());
+    }
+}
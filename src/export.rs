@@ -0,0 +1,181 @@
+use std::{path::PathBuf, process::Command};
+
+use serde::Serialize;
+
+use crate::{env::ENV_SEPARATOR, ConfigError};
+
+/// Flattens `config` into environment variables named like the ones of
+/// [`crate::ConfigLoader::env_prefix`], so they can be read back: `window.width` becomes
+/// `PREFIX_WINDOW__WIDTH`. Strings are written as they are and other scalars like toml,
+/// arrays are encoded as json, e.g. `["clock","tray"]`. Unset fields are skipped.
+///
+/// Keys are uppercased and every character that is not an ascii letter, digit or `_`
+/// is replaced with `_`, so `font-size` becomes `FONT_SIZE`. Such keys and arrays of tables
+/// can't be read back by the environment overrides.
+pub fn to_env_vars<ConcreteConfig>(
+    config: &ConcreteConfig,
+    prefix: &str,
+) -> Result<Vec<(String, String)>, ConfigError>
+where
+    ConcreteConfig: Serialize,
+{
+    let table = match toml::Table::try_from(config) {
+        Ok(table) => table,
+        Err(error) => return Err(ConfigError::Serialize(PathBuf::new(), Box::new(error))),
+    };
+    let mut vars = Vec::new();
+    flatten(&table, &format!("{}_", prefix), &mut vars);
+    Ok(vars)
+}
+
+/// Sets the variables of [`to_env_vars`] for a child process.
+pub fn apply_env_vars<'a, ConcreteConfig>(
+    command: &'a mut Command,
+    config: &ConcreteConfig,
+    prefix: &str,
+) -> Result<&'a mut Command, ConfigError>
+where
+    ConcreteConfig: Serialize,
+{
+    Ok(command.envs(to_env_vars(config, prefix)?))
+}
+
+fn flatten(table: &toml::Table, name: &str, vars: &mut Vec<(String, String)>) {
+    for (key, value) in table {
+        let name = format!("{}{}", name, env_key(key));
+        match value {
+            toml::Value::Table(table) => {
+                flatten(table, &format!("{}{}", name, ENV_SEPARATOR), vars)
+            }
+            toml::Value::String(string) => vars.push((name, string.clone())),
+            toml::Value::Array(_) => {
+                let mut json = String::new();
+                write_json(value, &mut json);
+                vars.push((name, json));
+            }
+            value => vars.push((name, value.to_string())),
+        }
+    }
+}
+
+fn env_key(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect()
+}
+
+fn write_json(value: &toml::Value, json: &mut String) {
+    match value {
+        toml::Value::String(string) => write_json_string(string, json),
+        toml::Value::Datetime(datetime) => write_json_string(&datetime.to_string(), json),
+        toml::Value::Array(array) => {
+            json.push('[');
+            for (index, item) in array.iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                write_json(item, json);
+            }
+            json.push(']');
+        }
+        toml::Value::Table(table) => {
+            json.push('{');
+            for (index, (key, item)) in table.iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                write_json_string(key, json);
+                json.push(':');
+                write_json(item, json);
+            }
+            json.push('}');
+        }
+        value => json.push_str(&value.to_string()),
+    }
+}
+
+fn write_json_string(string: &str, json: &mut String) {
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{apply_env_vars, env::Overridden, format, to_env_vars};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Window {
+        width: u32,
+        title: String,
+        scale: f64,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Conf {
+        enabled: bool,
+        modules: Vec<String>,
+        output: Option<String>,
+        window: Window,
+    }
+
+    #[test]
+    fn test_to_env_vars() {
+        let conf = Conf {
+            enabled: true,
+            modules: vec![String::from("clock"), String::from("say \"hi\"")],
+            output: None,
+            window: Window {
+                width: 300,
+                title: String::from("bar, top"),
+                scale: 1.5,
+            },
+        };
+        let vars = to_env_vars(&conf, "APP").expect("Could not export config.");
+        assert_eq!(
+            vars,
+            vec![
+                (String::from("APP_ENABLED"), String::from("true")),
+                (
+                    String::from("APP_MODULES"),
+                    String::from("[\"clock\",\"say \\\"hi\\\"\"]")
+                ),
+                (String::from("APP_WINDOW__SCALE"), String::from("1.5")),
+                (String::from("APP_WINDOW__TITLE"), String::from("bar, top")),
+                (String::from("APP_WINDOW__WIDTH"), String::from("300")),
+            ]
+        );
+
+        let document = Overridden::new(toml::Table::new(), "APP", vars);
+        let read_back: Conf = format::from_value(Path::new(""), document, &mut Vec::new())
+            .expect("Could not read the variables back.");
+        assert_eq!(read_back, conf);
+
+        let mut command = std::process::Command::new("true");
+        apply_env_vars(&mut command, &conf, "APP").expect("Could not export config.");
+        assert_eq!(command.get_envs().count(), 5);
+
+        let table: toml::Table = toml::from_str("font-size = 3").unwrap();
+        assert_eq!(
+            to_env_vars(&table, "APP").unwrap(),
+            vec![(String::from("APP_FONT_SIZE"), String::from("3"))]
+        );
+    }
+}
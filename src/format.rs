@@ -0,0 +1,847 @@
+use std::{fmt::Display, path::Path, sync::Arc};
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+
+use crate::{ConfigError, FormatError, ParseError};
+
+/// A parser for a config language, implement it to load formats OxiLib does not know about
+/// via [`crate::ConfigLoader::custom_format`] or [`crate::ConfigLoader::register_format`].
+pub trait ConfigFormat {
+    fn parse<OptionalConfig: DeserializeOwned>(
+        &self,
+        contents: &str,
+    ) -> Result<OptionalConfig, FormatError>;
+
+    /// File extensions without the leading dot, e.g. `["yaml", "yml"]`.
+    fn extensions(&self) -> &[&str];
+
+    /// Shown in parse errors.
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+}
+
+/// The language a config file is written in. The default config has to be written in the same one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Format {
+    #[default]
+    Toml,
+    #[cfg(feature = "json")]
+    Json,
+    /// `key: ~` and `key: null` are treated like a missing key, so the field falls back to its default.
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// Suited for enums carrying data, e.g. `action: Exec("firefox")`.
+    #[cfg(feature = "ron")]
+    Ron,
+    /// Read only, sections map to nested structs. Values are converted leniently,
+    /// e.g. `1`, `true`, `yes` and `on` are all `true`.
+    #[cfg(feature = "ini")]
+    Ini,
+    /// Json with comments, trailing commas and unquoted keys.
+    #[cfg(feature = "json5")]
+    Json5,
+    /// A [`ConfigFormat`] implemented outside of OxiLib, only reported by [`ParseError::format`].
+    /// Loading with it directly fails with [`ConfigError::UnknownFormat`].
+    Custom(&'static str),
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Toml => "toml",
+            #[cfg(feature = "json")]
+            Format::Json => "json",
+            #[cfg(feature = "yaml")]
+            Format::Yaml => "yaml",
+            #[cfg(feature = "ron")]
+            Format::Ron => "ron",
+            #[cfg(feature = "ini")]
+            Format::Ini => "ini",
+            #[cfg(feature = "json5")]
+            Format::Json5 => "json5",
+            Format::Custom(name) => name,
+        }
+    }
+
+    /// The format for a file extension like `yaml`, `None` if unknown or its feature is disabled.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension.to_ascii_lowercase().as_str() {
+            "toml" => Some(Format::Toml),
+            #[cfg(feature = "json")]
+            "json" => Some(Format::Json),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(Format::Yaml),
+            #[cfg(feature = "ron")]
+            "ron" => Some(Format::Ron),
+            #[cfg(feature = "ini")]
+            "ini" => Some(Format::Ini),
+            #[cfg(feature = "json5")]
+            "json5" => Some(Format::Json5),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::from_extension)
+    }
+
+    /// The format of `path` by its extension, toml for unknown extensions and files without one.
+    pub fn detect(path: &Path) -> Format {
+        Format::from_path(path).unwrap_or_default()
+    }
+
+    /// Fields are written in the order they are declared in.
+    pub(crate) fn serialize<T>(
+        self,
+        value: &T,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>>
+    where
+        T: Serialize,
+    {
+        match self {
+            Format::Toml => Ok(toml::to_string_pretty(value)?),
+            #[cfg(feature = "json")]
+            Format::Json => Ok(serde_json::to_string_pretty(value)?),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::to_string(value)?),
+            #[cfg(feature = "ron")]
+            Format::Ron => Ok(ron::ser::to_string_pretty(
+                value,
+                ron::ser::PrettyConfig::default(),
+            )?),
+            #[cfg(feature = "ini")]
+            Format::Ini => Err("ini files can only be read".into()),
+            #[cfg(feature = "json5")]
+            Format::Json5 => Ok(json5::to_string(value)?),
+            Format::Custom(name) => Err(format!("{} can't be written", name).into()),
+        }
+    }
+
+    /// Empty contents are valid in every format, every field of the optional config is then `None`.
+    pub(crate) fn parse_file<T>(
+        self,
+        path: &Path,
+        contents: &str,
+        unknown_keys: &mut Vec<String>,
+    ) -> Result<T, ConfigError>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            Format::Toml => deserialize(toml::Deserializer::new(contents), unknown_keys).map_err(
+                |(error, key_path)| {
+                    let parse_error = ParseError::new(path, contents, error);
+                    ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+                },
+            ),
+            #[cfg(feature = "json")]
+            Format::Json => {
+                let json = if contents.trim().is_empty() {
+                    "{}"
+                } else {
+                    contents
+                };
+                let mut deserializer = serde_json::Deserializer::from_str(json);
+                deserialize(&mut deserializer, unknown_keys)
+                    .and_then(|parsed_conf| match deserializer.end() {
+                        Ok(()) => Ok(parsed_conf),
+                        Err(error) => Err((error, String::new())),
+                    })
+                    .map_err(|(error, key_path)| {
+                        let parse_error = ParseError::from_json(path, contents, error);
+                        ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+                    })
+            }
+            #[cfg(feature = "yaml")]
+            Format::Yaml => {
+                let mut documents = serde_yaml::Deserializer::from_str(contents);
+                let parsed = match documents.next() {
+                    Some(document) => deserialize(document, unknown_keys),
+                    None => deserialize(serde_yaml::Deserializer::from_str("{}"), unknown_keys),
+                };
+                // only using the first document would silently ignore the rest of the file
+                let parsed = match (parsed, documents.next()) {
+                    (Ok(_), Some(_)) => Err((
+                        serde::de::Error::custom(
+                            "found several yaml documents, a config file may only contain one",
+                        ),
+                        String::new(),
+                    )),
+                    (parsed, _) => parsed,
+                };
+                parsed.map_err(|(error, key_path)| {
+                    let parse_error = ParseError::from_yaml(path, contents, error);
+                    ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+                })
+            }
+            #[cfg(feature = "ron")]
+            Format::Ron => {
+                let ron = if contents.trim().is_empty() {
+                    "()"
+                } else {
+                    contents
+                };
+                let to_config_error = |error: ron::error::SpannedError, key_path: String| {
+                    let parse_error = ParseError::from_ron(path, contents, error);
+                    ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+                };
+                // lets optional fields be written as `something: 4` instead of `Some(4)`
+                let options = ron::Options::default()
+                    .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+                let mut deserializer = match ron::Deserializer::from_str_with_options(ron, options)
+                {
+                    Ok(deserializer) => deserializer,
+                    Err(error) => return Err(to_config_error(error, String::new())),
+                };
+                deserialize(&mut deserializer, unknown_keys)
+                    .and_then(|parsed_conf| match deserializer.end() {
+                        Ok(()) => Ok(parsed_conf),
+                        Err(error) => Err((error, String::new())),
+                    })
+                    .map_err(|(error, key_path)| {
+                        to_config_error(deserializer.span_error(error), key_path)
+                    })
+            }
+            #[cfg(feature = "ini")]
+            Format::Ini => crate::ini::parse(contents)
+                .map_err(|error| (error, String::new()))
+                .and_then(|root| deserialize(root, unknown_keys))
+                .map_err(|(error, key_path)| {
+                    let parse_error = ParseError::from_ini(path, contents, error);
+                    ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+                }),
+            #[cfg(feature = "json5")]
+            Format::Json5 => {
+                let json5 = if contents.trim().is_empty() {
+                    "{}"
+                } else {
+                    contents
+                };
+                json5::Deserializer::from_str(json5)
+                    .map_err(|error| (error, String::new()))
+                    .and_then(|mut deserializer| deserialize(&mut deserializer, unknown_keys))
+                    .map_err(|(error, key_path)| {
+                        let parse_error = ParseError::from_json5(path, contents, error);
+                        ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+                    })
+            }
+            Format::Custom(_) => Err(ConfigError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+}
+
+impl ConfigFormat for Format {
+    fn parse<OptionalConfig: DeserializeOwned>(
+        &self,
+        contents: &str,
+    ) -> Result<OptionalConfig, FormatError> {
+        match self.parse_file(Path::new(""), contents, &mut Vec::new()) {
+            Ok(parsed_conf) => Ok(parsed_conf),
+            Err(ConfigError::Parse(error)) => Err(FormatError::from(*error)),
+            Err(error) => Err(FormatError::new(error.to_string())),
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        match self {
+            Format::Toml => &["toml"],
+            #[cfg(feature = "json")]
+            Format::Json => &["json"],
+            #[cfg(feature = "yaml")]
+            Format::Yaml => &["yaml", "yml"],
+            #[cfg(feature = "ron")]
+            Format::Ron => &["ron"],
+            #[cfg(feature = "ini")]
+            Format::Ini => &["ini"],
+            #[cfg(feature = "json5")]
+            Format::Json5 => &["json5"],
+            Format::Custom(_) => &[],
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Format::name(*self)
+    }
+}
+
+/// Object safe counterpart of [`ConfigFormat`], the document is parsed into a [`toml::Value`]
+/// first and the optional config is deserialized from that.
+trait ErasedFormat: Send + Sync {
+    fn parse_value(&self, contents: &str) -> Result<toml::Value, FormatError>;
+
+    fn extensions(&self) -> &[&str];
+
+    fn name(&self) -> &'static str;
+}
+
+impl<F: ConfigFormat + Send + Sync> ErasedFormat for F {
+    fn parse_value(&self, contents: &str) -> Result<toml::Value, FormatError> {
+        ConfigFormat::parse(self, contents)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        ConfigFormat::extensions(self)
+    }
+
+    fn name(&self) -> &'static str {
+        ConfigFormat::name(self)
+    }
+}
+
+/// A [`ConfigFormat`] stored in a [`crate::ConfigLoader`].
+#[derive(Clone)]
+pub(crate) struct CustomFormat(Arc<dyn ErasedFormat>);
+
+impl CustomFormat {
+    pub(crate) fn new(format: impl ConfigFormat + Send + Sync + 'static) -> Self {
+        CustomFormat(Arc::new(format))
+    }
+
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+            return false;
+        };
+        self.0
+            .extensions()
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(extension))
+    }
+}
+
+impl std::fmt::Debug for CustomFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CustomFormat").field(&self.0.name()).finish()
+    }
+}
+
+/// Either a built-in or a custom format, what the loading functions parse with.
+#[derive(Clone, Copy)]
+pub(crate) enum FormatRef<'a> {
+    Builtin(Format),
+    Custom(&'a CustomFormat),
+}
+
+impl FormatRef<'_> {
+    pub(crate) fn parse<T>(
+        self,
+        path: &Path,
+        contents: &str,
+        unknown_keys: &mut Vec<String>,
+    ) -> Result<T, ConfigError>
+    where
+        T: DeserializeOwned,
+    {
+        let custom = match self {
+            FormatRef::Builtin(format) => return format.parse_file(path, contents, unknown_keys),
+            FormatRef::Custom(custom) => &custom.0,
+        };
+        let format = Format::Custom(custom.name());
+        let value = if contents.trim().is_empty() {
+            toml::Value::Table(toml::Table::new())
+        } else {
+            match custom.parse_value(contents) {
+                Ok(value) => value,
+                Err(error) => {
+                    let parse_error = ParseError::from_format(path, contents, format, error);
+                    return Err(ConfigError::Parse(Box::new(parse_error)));
+                }
+            }
+        };
+        deserialize(value, unknown_keys).map_err(|(error, key_path)| {
+            let error = FormatError::new(error.message()).with_source(error);
+            let parse_error = ParseError::from_format(path, contents, format, error);
+            ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+        })
+    }
+}
+
+impl From<Format> for FormatRef<'_> {
+    fn from(format: Format) -> Self {
+        FormatRef::Builtin(format)
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(Format::name(*self))
+    }
+}
+
+/// Deserializes a document that was already parsed, e.g. after merging several files into it.
+pub(crate) fn from_value<'de, D, T>(
+    path: &Path,
+    value: D,
+    unknown_keys: &mut Vec<String>,
+) -> Result<T, ConfigError>
+where
+    D: Deserializer<'de, Error = toml::de::Error>,
+    T: DeserializeOwned,
+{
+    deserialize(value, unknown_keys).map_err(|(error, key_path)| {
+        let parse_error = ParseError::new(path, "", error);
+        ConfigError::Parse(Box::new(parse_error.with_key_path(key_path)))
+    })
+}
+
+/// Deserializes while recording unknown keys, on failure also returns the path of the failing key.
+/// The path is only known with the `path-to-error` feature, it is empty otherwise.
+fn deserialize<'de, D, T>(
+    deserializer: D,
+    unknown_keys: &mut Vec<String>,
+) -> Result<T, (D::Error, String)>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let mut track_unknown = |key: serde_ignored::Path| {
+        let mut key_path = String::new();
+        push_key_path(&mut key_path, &key);
+        unknown_keys.push(key_path);
+    };
+    let deserializer = serde_ignored::Deserializer::new(deserializer, &mut track_unknown);
+    #[cfg(feature = "path-to-error")]
+    {
+        serde_path_to_error::deserialize(deserializer).map_err(|error| {
+            let key_path = error.path().to_string();
+            (error.into_inner(), key_path)
+        })
+    }
+    #[cfg(not(feature = "path-to-error"))]
+    {
+        T::deserialize(deserializer).map_err(|error| (error, String::new()))
+    }
+}
+
+fn push_key_path(key_path: &mut String, path: &serde_ignored::Path) {
+    let segment = match path {
+        serde_ignored::Path::Root => return,
+        serde_ignored::Path::Seq { parent, index } => {
+            push_key_path(key_path, parent);
+            index.to_string()
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            push_key_path(key_path, parent);
+            key.clone()
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => {
+            return push_key_path(key_path, parent);
+        }
+    };
+    if !key_path.is_empty() {
+        key_path.push('.');
+    }
+    key_path.push_str(&segment);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::{
+        test_support::{test_dir, OptWhatConf as OptConf, WhatConf as Conf},
+        try_create_config_with_format, ConfigError, Format,
+    };
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            Format::from_path(Path::new("config.toml")),
+            Some(Format::Toml)
+        );
+        assert_eq!(
+            Format::from_path(Path::new("config.TOML")),
+            Some(Format::Toml)
+        );
+        assert_eq!(Format::from_path(Path::new("config.conf")), None);
+        assert_eq!(Format::detect(Path::new("config.conf")), Format::Toml);
+        assert_eq!(Format::detect(Path::new("config")), Format::Toml);
+        #[cfg(feature = "yaml")]
+        assert_eq!(Format::detect(Path::new("config.yml")), Format::Yaml);
+        #[cfg(not(feature = "yaml"))]
+        assert_eq!(Format::from_path(Path::new("config.yml")), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_detect_format_json() {
+        use crate::{read_specific_config, try_create_config};
+
+        let config_dir = test_dir("test_detect");
+        fs::write(config_dir.join("config.json"), "{\"something\": 3}")
+            .expect("Could not write test config.");
+        let conf = read_specific_config::<Conf, OptConf>(config_dir.join("config.json"))
+            .expect("Could not read config.");
+        assert_eq!(conf.something, 3);
+
+        fs::write(config_dir.join("config.json"), "something = 3")
+            .expect("Could not write test config.");
+        let error = try_create_config::<Conf, OptConf>(&config_dir, "config.json", "")
+            .expect_err("Toml was parsed as json.");
+        match &error {
+            ConfigError::Parse(parse) => assert_eq!(parse.format(), Format::Json),
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        assert!(error.to_string().ends_with("(format: json)"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_toml() {
+        let config_dir = test_dir("test_toml");
+        let conf = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "config.toml",
+            "something = 10",
+            Format::Toml,
+        )
+        .expect("Could not load config.");
+        assert_eq!((conf.something, conf.what.as_str()), (10, "pingpang"));
+
+        fs::write(config_dir.join("config.toml"), "something = \"ten\"")
+            .expect("Could not write test config.");
+        let error = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "config.toml",
+            "",
+            Format::Toml,
+        )
+        .expect_err("Broken toml was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Toml);
+                assert!(parse.toml_error().is_some());
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_config_json() {
+        use crate::{create_config_json, try_create_config_json};
+
+        let config_dir = test_dir("test_json");
+        let conf =
+            create_config_json::<Conf, OptConf>(&config_dir, "config.json", "{\"something\": 10}");
+        assert_eq!(conf.something, 10);
+        assert_eq!(conf.what, String::from("pingpang"));
+
+        fs::write(config_dir.join("empty.json"), "").expect("Could not write test config.");
+        let conf = try_create_config_json::<Conf, OptConf>(&config_dir, "empty.json", "")
+            .expect("Could not load empty config.");
+        assert_eq!((conf.something, conf.what.as_str()), (0, "pingpang"));
+
+        fs::write(
+            config_dir.join("broken.json"),
+            "{\n  \"something\": \"ten\"\n}",
+        )
+        .expect("Could not write test config.");
+        let error = try_create_config_json::<Conf, OptConf>(&config_dir, "broken.json", "")
+            .expect_err("Broken json was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Json);
+                assert_eq!(parse.location(), Some((2, 20)));
+                assert!(!parse.message().contains("line"));
+                assert!(parse.toml_error().is_none());
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        let conf = create_config_json::<Conf, OptConf>(
+            &config_dir,
+            "broken.json",
+            "{\"what\": \"default\"}",
+        );
+        assert_eq!(conf.what, String::from("default"));
+
+        fs::write(config_dir.join("trailing.json"), "{} {}").expect("Could not write test config.");
+        let error = try_create_config_json::<Conf, OptConf>(&config_dir, "trailing.json", "")
+            .expect_err("Trailing json was parsed successfully.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_config_yaml() {
+        let config_dir = test_dir("test_yaml");
+        let conf = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "config.yaml",
+            "something: 10\nwhat: ~\n",
+            Format::Yaml,
+        )
+        .expect("Could not load config.");
+        assert_eq!((conf.something, conf.what.as_str()), (10, "pingpang"));
+
+        fs::write(config_dir.join("comments.yaml"), "# nothing here\n")
+            .expect("Could not write test config.");
+        let conf = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "comments.yaml",
+            "",
+            Format::Yaml,
+        )
+        .expect("Could not load config.");
+        assert_eq!((conf.something, conf.what.as_str()), (0, "pingpang"));
+
+        fs::write(config_dir.join("broken.yaml"), "what: a\nsomething: ten\n")
+            .expect("Could not write test config.");
+        let error = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "broken.yaml",
+            "",
+            Format::Yaml,
+        )
+        .expect_err("Broken yaml was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Yaml);
+                assert_eq!(parse.location(), Some((2, 12)));
+                assert!(!parse.message().contains("line"));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+
+        fs::write(
+            config_dir.join("documents.yaml"),
+            "something: 1\n---\nsomething: 2\n",
+        )
+        .expect("Could not write test config.");
+        let error = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "documents.yaml",
+            "",
+            Format::Yaml,
+        )
+        .expect_err("A file with several documents was accepted.");
+        assert!(error.to_string().contains("several yaml documents"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_config_ron() {
+        use serde::{Deserialize, Serialize};
+
+        use crate::{Config, ConfigOptional};
+
+        #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+        enum Action {
+            Exec(String),
+            Workspace { index: u32 },
+            Quit,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+        struct Keybinding {
+            key: String,
+            action: Action,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Keybindings {
+            bindings: Vec<Keybinding>,
+            fallback: Option<Action>,
+        }
+
+        impl ConfigOptional for Keybindings {}
+
+        impl Config<Keybindings> for Keybindings {
+            fn create_from_optional(optional: Keybindings) -> Self {
+                optional
+            }
+        }
+
+        let keybindings = Keybindings {
+            bindings: vec![
+                Keybinding {
+                    key: String::from("Super+Return"),
+                    action: Action::Exec(String::from("firefox")),
+                },
+                Keybinding {
+                    key: String::from("Super+1"),
+                    action: Action::Workspace { index: 1 },
+                },
+            ],
+            fallback: Some(Action::Quit),
+        };
+        let config_dir = test_dir("test_ron");
+        let serialized = ron::to_string(&keybindings).expect("Could not serialize keybindings.");
+        let conf = try_create_config_with_format::<Keybindings, Keybindings>(
+            &config_dir,
+            "config.ron",
+            &serialized,
+            Format::Ron,
+        )
+        .expect("Could not load config.");
+        assert_eq!(conf, keybindings);
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.ron")).unwrap(),
+            serialized
+        );
+
+        let conf = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "empty.ron",
+            "",
+            Format::Ron,
+        )
+        .expect("Could not load config.");
+        assert_eq!((conf.something, conf.what.as_str()), (0, "pingpang"));
+
+        fs::write(
+            config_dir.join("broken.ron"),
+            "(\n    something: \"ten\",\n)",
+        )
+        .expect("Could not write test config.");
+        let error = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "broken.ron",
+            "",
+            Format::Ron,
+        )
+        .expect_err("Broken ron was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Ron);
+                assert_eq!(parse.location().map(|(line, _)| line), Some(2));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "ini")]
+    #[test]
+    fn test_config_ini() {
+        use serde::Deserialize;
+
+        use crate::{Config, ConfigOptional};
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct OptWindow {
+            width: Option<u32>,
+            floating: Option<bool>,
+            outputs: Option<Vec<String>>,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct OptLegacy {
+            name: Option<String>,
+            verbose: Option<bool>,
+            window: Option<OptWindow>,
+        }
+
+        impl ConfigOptional for OptLegacy {}
+
+        impl Config<OptLegacy> for OptLegacy {
+            fn create_from_optional(optional: OptLegacy) -> Self {
+                optional
+            }
+        }
+
+        let config_dir = test_dir("test_ini");
+        fs::write(
+            config_dir.join("config.ini"),
+            "; converted from the old tool\nname = \"oxi\"\nverbose = yes\n\n\
+             [window]\nwidth = 800\nfloating = 0\noutputs = DP-1, HDMI-A-1\n\n\
+             [plugins]\nclock = on\n",
+        )
+        .expect("Could not write test config.");
+        let conf = try_create_config_with_format::<OptLegacy, OptLegacy>(
+            &config_dir,
+            "config.ini",
+            "",
+            Format::Ini,
+        )
+        .expect("Could not load config.");
+        assert_eq!(
+            conf,
+            OptLegacy {
+                name: Some(String::from("oxi")),
+                verbose: Some(true),
+                window: Some(OptWindow {
+                    width: Some(800),
+                    floating: Some(false),
+                    outputs: Some(vec![String::from("DP-1"), String::from("HDMI-A-1")]),
+                }),
+            }
+        );
+
+        fs::write(config_dir.join("broken.ini"), "[window]\nwidth = wide\n")
+            .expect("Could not write test config.");
+        let error = try_create_config_with_format::<OptLegacy, OptLegacy>(
+            &config_dir,
+            "broken.ini",
+            "",
+            Format::Ini,
+        )
+        .expect_err("Broken ini was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Ini);
+                assert_eq!(parse.location(), Some((2, 1)));
+                assert_eq!(parse.span(), Some(9..21));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_config_json5() {
+        let config_dir = test_dir("test_json5");
+        let conf = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "config.json5",
+            "{\n  // the answer\n  something: 10,\n  /* what: 'no', */\n}",
+            Format::Json5,
+        )
+        .expect("Could not load config.");
+        assert_eq!((conf.something, conf.what.as_str()), (10, "pingpang"));
+
+        fs::write(config_dir.join("broken.json5"), "{\n  something: 'ten',\n}")
+            .expect("Could not write test config.");
+        let error = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "broken.json5",
+            "",
+            Format::Json5,
+        )
+        .expect_err("Broken json5 was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Json5);
+                assert_eq!(parse.location(), Some((2, 14)));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+
+        fs::write(config_dir.join("syntax.json5"), "{\n  something: ,\n}")
+            .expect("Could not write test config.");
+        let error = try_create_config_with_format::<Conf, OptConf>(
+            &config_dir,
+            "syntax.json5",
+            "",
+            Format::Json5,
+        )
+        .expect_err("Broken json5 was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.location(), Some((2, 14)));
+                assert!(!parse.message().contains('\n'));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
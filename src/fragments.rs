@@ -0,0 +1,67 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    parse_config, read_file, warn_unknown_keys, ConfigError, ConfigOptional, Format,
+    SkippedFragment, DEFAULT_SIZE_LIMIT,
+};
+
+/// The folder next to the config file that fragments are read from.
+pub(crate) const FRAGMENT_DIR: &str = "config.d";
+
+/// Reads every `*.toml` file of `fragment_dir` in lexical order, a missing folder has no fragments.
+/// Other files and fragments that can't be read or don't fit `OptionalConfig` are skipped.
+pub(crate) fn read_fragments<OptionalConfig>(
+    fragment_dir: &Path,
+    skipped: &mut Vec<SkippedFragment>,
+) -> Result<Vec<(PathBuf, toml::Table)>, ConfigError>
+where
+    OptionalConfig: ConfigOptional,
+{
+    let entries = match fs::read_dir(fragment_dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(ConfigError::from_io(fragment_dir.to_path_buf(), error)),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => paths.push(entry.path()),
+            Err(error) => return Err(ConfigError::from_io(fragment_dir.to_path_buf(), error)),
+        }
+    }
+    paths.sort();
+
+    let mut fragments = Vec::new();
+    for path in paths.into_iter().filter(|path| path.is_file()) {
+        match read_fragment::<OptionalConfig>(&path) {
+            Ok(fragment) => fragments.push((path, fragment)),
+            Err(reason) => {
+                let fragment = SkippedFragment { path, reason };
+                log_warn!("{}", fragment);
+                skipped.push(fragment);
+            }
+        }
+    }
+    Ok(fragments)
+}
+
+fn read_fragment<OptionalConfig>(path: &Path) -> Result<toml::Table, String>
+where
+    OptionalConfig: ConfigOptional,
+{
+    if Format::from_path(path) != Some(Format::Toml) {
+        return Err(String::from("not a toml file"));
+    }
+    let contents = read_file(path, DEFAULT_SIZE_LIMIT).map_err(|error| error.to_string())?;
+    // checked on its own, so a broken fragment is reported by its name
+    let mut unknown_keys = Vec::new();
+    parse_config::<OptionalConfig>(path, &contents, Format::Toml.into(), &mut unknown_keys)
+        .map_err(|error| error.to_string())?;
+    warn_unknown_keys(path, &unknown_keys);
+    Format::Toml
+        .parse_file(path, &contents, &mut Vec::new())
+        .map_err(|error: ConfigError| error.to_string())
+}
@@ -0,0 +1,115 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::{load_or_create, load_or_create_in, ConfigError, ConfigOptional, TryConfig};
+
+type Globals = HashMap<TypeId, &'static (dyn Any + Send + Sync)>;
+
+static GLOBALS: OnceLock<Mutex<Globals>> = OnceLock::new();
+
+fn globals() -> std::sync::MutexGuard<'static, Globals> {
+    let globals = GLOBALS.get_or_init(Default::default);
+    // the map is never left in an inconsistent state, a panic while loading can be ignored
+    globals.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Loads the config like [`load_or_create`] and stores it for [`global`].
+/// Once a config of this type is stored, it is returned without loading again.
+pub fn init_global<ConcreteConfig, OptionalConfig>(
+    app_dir: &str,
+    file_name: &str,
+    default_config: &str,
+) -> Result<&'static ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + Send + Sync + 'static,
+    OptionalConfig: ConfigOptional,
+{
+    init_global_with(|| {
+        load_or_create::<ConcreteConfig, OptionalConfig>(app_dir, file_name, default_config)
+    })
+}
+
+/// Like [`init_global`], but relative to `base_dir` instead of the user's config directory.
+pub fn init_global_in<ConcreteConfig, OptionalConfig>(
+    base_dir: &Path,
+    app_dir: &str,
+    file_name: &str,
+    default_config: &str,
+) -> Result<&'static ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + Send + Sync + 'static,
+    OptionalConfig: ConfigOptional,
+{
+    init_global_with(|| {
+        load_or_create_in::<ConcreteConfig, OptionalConfig>(
+            base_dir,
+            app_dir,
+            file_name,
+            default_config,
+        )
+    })
+}
+
+fn init_global_with<ConcreteConfig>(
+    load: impl FnOnce() -> Result<(ConcreteConfig, std::path::PathBuf), ConfigError>,
+) -> Result<&'static ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: Send + Sync + 'static,
+{
+    let mut globals = globals();
+    if let Some(config) = globals.get(&TypeId::of::<ConcreteConfig>()) {
+        return Ok(downcast(*config));
+    }
+    let (config, _) = load()?;
+    let config: &'static ConcreteConfig = Box::leak(Box::new(config));
+    globals.insert(TypeId::of::<ConcreteConfig>(), config);
+    Ok(config)
+}
+
+/// The config stored by [`init_global`], `None` if it was not initialized yet.
+pub fn global<ConcreteConfig>() -> Option<&'static ConcreteConfig>
+where
+    ConcreteConfig: Send + Sync + 'static,
+{
+    globals()
+        .get(&TypeId::of::<ConcreteConfig>())
+        .map(|config| downcast(*config))
+}
+
+fn downcast<ConcreteConfig: 'static>(
+    config: &'static (dyn Any + Send + Sync),
+) -> &'static ConcreteConfig {
+    config
+        .downcast_ref()
+        .expect("Global config was stored under the wrong type.")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        global, init_global_in,
+        test_support::{test_dir, Conf, OptConf},
+    };
+
+    #[test]
+    fn test_global() {
+        let base = test_dir("test_global");
+        assert!(global::<Conf>().is_none());
+        let conf = init_global_in::<Conf, OptConf>(&base, "app", "config.toml", "something = 1")
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 1);
+        assert!(base.join("app").join("config.toml").is_file());
+        let again = init_global_in::<Conf, OptConf>(&base, "app", "config.toml", "something = 2")
+            .expect("Could not load config.");
+        assert!(std::ptr::eq(conf, again));
+        assert!(std::ptr::eq(conf, global::<Conf>().unwrap()));
+        assert!(global::<String>().is_none());
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+}
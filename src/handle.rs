@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{diff::to_value, write_config, ConfigChange, ConfigError};
+
+/// Loads the config at the path with the default config,
+/// returning it with whether the file was created and a hash of its contents.
+pub(crate) type LoadFn<ConcreteConfig> =
+    fn(&Path, &str) -> Result<(ConcreteConfig, bool, u64), ConfigError>;
+
+/// A loaded config together with the file it was loaded from, returned by [`crate::load_config`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConfigHandle<ConcreteConfig> {
+    pub config: ConcreteConfig,
+    pub path: PathBuf,
+    /// The file did not exist and was created while loading.
+    pub created: bool,
+    default_config: String,
+    contents_hash: u64,
+    load: LoadFn<ConcreteConfig>,
+}
+
+impl<ConcreteConfig> ConfigHandle<ConcreteConfig> {
+    pub(crate) fn new(
+        config: ConcreteConfig,
+        path: PathBuf,
+        created: bool,
+        default_config: &str,
+        contents_hash: u64,
+        load: LoadFn<ConcreteConfig>,
+    ) -> Self {
+        ConfigHandle {
+            config,
+            path,
+            created,
+            default_config: default_config.to_string(),
+            contents_hash,
+            load,
+        }
+    }
+
+    /// Loads the file again with the same default config.
+    /// Returns whether the contents changed, on errors the current config is kept.
+    pub fn reload(&mut self) -> Result<bool, ConfigError> {
+        let (config, _, contents_hash) = (self.load)(&self.path, &self.default_config)?;
+        if contents_hash == self.contents_hash {
+            return Ok(false);
+        }
+        self.config = config;
+        self.contents_hash = contents_hash;
+        Ok(true)
+    }
+}
+
+impl<ConcreteConfig> ConfigHandle<ConcreteConfig>
+where
+    ConcreteConfig: Serialize,
+{
+    /// Like [`ConfigHandle::reload`], returning which keys changed instead,
+    /// see [`ConfigChange::between`].
+    pub fn reload_with_changes(&mut self) -> Result<ConfigChange, ConfigError> {
+        let previous = to_value(&self.config)?;
+        if !self.reload()? {
+            return Ok(ConfigChange::default());
+        }
+        Ok(ConfigChange::between_values(
+            &previous,
+            &to_value(&self.config)?,
+        ))
+    }
+
+    /// Writes the current config back to [`ConfigHandle::path`].
+    pub fn save(&self) -> Result<(), ConfigError> {
+        write_config(&self.path, &self.config)
+    }
+
+    /// Like [`ConfigHandle::save`], but keeps comments and formatting of the file.
+    #[cfg(feature = "preserve-format")]
+    pub fn save_preserving(&self) -> Result<(), ConfigError> {
+        crate::write_config_preserving(&self.path, &self.config)
+    }
+}
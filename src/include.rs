@@ -0,0 +1,77 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{format::FormatRef, read_file, value::merge, ConfigError};
+
+/// How deeply included files may include further files.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// The key listing the files a config file includes, e.g. `include = ["keybinds.toml"]`.
+pub(crate) const INCLUDE_KEY: &str = "include";
+
+/// Merges the files listed in the `include` key of `document` into it, the including file wins.
+/// Relative paths are resolved against the folder of the including file.
+pub(crate) fn resolve_includes<'a>(
+    path: &Path,
+    document: toml::Table,
+    size_limit: u64,
+    format_for: &dyn Fn(&Path) -> Result<FormatRef<'a>, ConfigError>,
+) -> Result<toml::Table, ConfigError> {
+    let mut chain = vec![path.to_path_buf()];
+    resolve(path, document, &mut chain, size_limit, format_for)
+}
+
+fn resolve<'a>(
+    path: &Path,
+    mut document: toml::Table,
+    chain: &mut Vec<PathBuf>,
+    size_limit: u64,
+    format_for: &dyn Fn(&Path) -> Result<FormatRef<'a>, ConfigError>,
+) -> Result<toml::Table, ConfigError> {
+    let includes = match document.remove(INCLUDE_KEY) {
+        None => return Ok(document),
+        Some(toml::Value::String(include)) => vec![include],
+        Some(toml::Value::Array(includes)) => {
+            let mut paths = Vec::new();
+            for include in includes {
+                match include {
+                    toml::Value::String(include) => paths.push(include),
+                    _ => return Err(ConfigError::InvalidInclude(path.to_path_buf())),
+                }
+            }
+            paths
+        }
+        Some(_) => return Err(ConfigError::InvalidInclude(path.to_path_buf())),
+    };
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut merged = toml::Table::new();
+    for include in includes {
+        let included = base_dir.join(include);
+        let cycle = chain.iter().any(|file| same_file(file, &included));
+        chain.push(included.clone());
+        if cycle {
+            return Err(ConfigError::IncludeCycle(chain.clone()));
+        }
+        if chain.len() > MAX_INCLUDE_DEPTH + 1 {
+            return Err(ConfigError::IncludeTooDeep(chain.clone()));
+        }
+        let contents = read_file(&included, size_limit)?;
+        let table: toml::Table =
+            format_for(&included)?.parse(&included, &contents, &mut Vec::new())?;
+        let table = resolve(&included, table, chain, size_limit, format_for)?;
+        chain.pop();
+        // later includes win over earlier ones
+        merge(&mut merged, table);
+    }
+    merge(&mut merged, document);
+    Ok(merged)
+}
+
+fn same_file(file: &Path, other: &Path) -> bool {
+    match (fs::canonicalize(file), fs::canonicalize(other)) {
+        (Ok(file), Ok(other)) => file == other,
+        _ => file == other,
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt::Display;
+
+use serde::{
+    de::{self, value::SeqDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+    forward_to_deserialize_any,
+};
+
+/// A syntax or conversion error in an ini file, with the one-based line it occurred on if known.
+#[derive(Debug)]
+pub(crate) struct IniError {
+    message: String,
+    pub(crate) line: Option<usize>,
+}
+
+impl IniError {
+    fn at(line: usize, message: impl Into<String>) -> Self {
+        IniError {
+            message: message.into(),
+            line: Some(line),
+        }
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for IniError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} at line {}", self.message, line),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for IniError {}
+
+impl de::Error for IniError {
+    fn custom<T: Display>(msg: T) -> Self {
+        IniError {
+            message: msg.to_string(),
+            line: None,
+        }
+    }
+}
+
+/// A section of an ini file, the keys before the first section header form the root section.
+/// `[window.margins]` is a section `margins` nested in the section `window`.
+#[derive(Debug, Default)]
+pub(crate) struct Section {
+    entries: Vec<(String, Node)>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Value { value: String, line: usize },
+    Section { section: Section, line: usize },
+}
+
+impl Node {
+    fn line(&self) -> usize {
+        match self {
+            Node::Value { line, .. } | Node::Section { line, .. } => *line,
+        }
+    }
+}
+
+impl Section {
+    fn get_mut(&mut self, key: &str) -> Option<&mut Node> {
+        self.entries
+            .iter_mut()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, node)| node)
+    }
+
+    /// Later keys replace earlier ones with the same name.
+    fn insert(&mut self, key: String, node: Node) {
+        match self.get_mut(&key) {
+            Some(existing) => *existing = node,
+            None => self.entries.push((key, node)),
+        }
+    }
+
+    fn section_mut(&mut self, path: &[&str], line: usize) -> Result<&mut Section, IniError> {
+        let Some((name, rest)) = path.split_first() else {
+            return Ok(self);
+        };
+        if self.get_mut(name).is_none() {
+            let section = Section::default();
+            self.entries
+                .push((name.to_string(), Node::Section { section, line }));
+        }
+        match self.get_mut(name) {
+            Some(Node::Section { section, .. }) => section.section_mut(rest, line),
+            _ => Err(IniError::at(
+                line,
+                format!("section `{}` was already defined as a key", name),
+            )),
+        }
+    }
+}
+
+/// Parses `key = value` pairs grouped by `[section]` headers.
+/// Lines starting with `;` or `#` are comments, quotes around values are removed.
+pub(crate) fn parse(contents: &str) -> Result<Section, IniError> {
+    let mut root = Section::default();
+    let mut current: Vec<String> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let Some(name) = header.strip_suffix(']') else {
+                return Err(IniError::at(line_number, "unclosed section header"));
+            };
+            current = name
+                .split('.')
+                .map(|part| part.trim().to_string())
+                .collect();
+            if current.iter().any(String::is_empty) {
+                return Err(IniError::at(line_number, "empty section name"));
+            }
+            let path: Vec<&str> = current.iter().map(String::as_str).collect();
+            root.section_mut(&path, line_number)?;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(IniError::at(line_number, "expected `key = value`"));
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(IniError::at(line_number, "empty key"));
+        }
+        let path: Vec<&str> = current.iter().map(String::as_str).collect();
+        let section = root.section_mut(&path, line_number)?;
+        let value = Node::Value {
+            value: unquote(value.trim()).to_string(),
+            line: line_number,
+        };
+        section.insert(key.to_string(), value);
+    }
+    Ok(root)
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+impl<'de> de::Deserializer<'de> for Section {
+    type Error = IniError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+        visitor.visit_map(SectionAccess {
+            entries: self.entries.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, IniError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+struct SectionAccess {
+    entries: std::vec::IntoIter<(String, Node)>,
+    value: Option<Node>,
+}
+
+impl<'de> MapAccess<'de> for SectionAccess {
+    type Error = IniError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, IniError> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, IniError> {
+        let Some(node) = self.value.take() else {
+            return Err(de::Error::custom("value requested before its key"));
+        };
+        let line = node.line();
+        let result = match node {
+            Node::Value { value, line } => seed.deserialize(ValueDeserializer { value, line }),
+            Node::Section { section, .. } => seed.deserialize(section),
+        };
+        result.map_err(|mut error| {
+            error.line.get_or_insert(line);
+            error
+        })
+    }
+}
+
+/// Every ini value is a string, numbers and bools are converted on demand.
+/// Sequences are written as comma separated lists, an empty value is `None`.
+struct ValueDeserializer {
+    value: String,
+    line: usize,
+}
+
+impl ValueDeserializer {
+    fn invalid(&self, expected: &str) -> IniError {
+        IniError::at(
+            self.line,
+            format!("invalid value `{}`, expected {}", self.value, expected),
+        )
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $expected:literal),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+                match self.value.trim().parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(self.invalid($expected)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = IniError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+        visitor.visit_string(self.value)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+        match self.value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => visitor.visit_bool(true),
+            "0" | "false" | "no" | "off" => visitor.visit_bool(false),
+            _ => Err(self.invalid("a bool")),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: "an integer",
+        deserialize_i16 => visit_i16: "an integer",
+        deserialize_i32 => visit_i32: "an integer",
+        deserialize_i64 => visit_i64: "an integer",
+        deserialize_i128 => visit_i128: "an integer",
+        deserialize_u8 => visit_u8: "an unsigned integer",
+        deserialize_u16 => visit_u16: "an unsigned integer",
+        deserialize_u32 => visit_u32: "an unsigned integer",
+        deserialize_u64 => visit_u64: "an unsigned integer",
+        deserialize_u128 => visit_u128: "an unsigned integer",
+        deserialize_f32 => visit_f32: "a number",
+        deserialize_f64 => visit_f64: "a number",
+        deserialize_char => visit_char: "a single character",
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, IniError> {
+        let line = self.line;
+        let items: Vec<ValueDeserializer> = if self.value.trim().is_empty() {
+            Vec::new()
+        } else {
+            self.value
+                .split(',')
+                .map(|item| ValueDeserializer {
+                    value: unquote(item.trim()).to_string(),
+                    line,
+                })
+                .collect()
+        };
+        let items: SeqDeserializer<_, IniError> = SeqDeserializer::new(items.into_iter());
+        de::Deserializer::deserialize_any(items, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, IniError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, IniError> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, IniError> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{env::Overridden, ConfigError};
+
+/// What [`interpolate_env`] does with a variable that is not set and has no default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsetVar {
+    /// Fail with [`ConfigError::UnsetVariable`].
+    Error,
+    /// Keep the reference as written, e.g. `${XDG_RUNTIME_DIR}`.
+    Keep,
+}
+
+/// Replaces `$VAR`, `${VAR}` and `${VAR:-default}` in every string of `value` with the
+/// environment variable, the default is used if it is unset or empty. `$$` is a literal `$`.
+/// Replaced parts are not expanded again and other values are never touched.
+pub fn interpolate_env(value: &mut toml::Value, unset: UnsetVar) -> Result<(), ConfigError> {
+    interpolate_value(value, &mut Vec::new(), unset, &lookup)
+}
+
+fn lookup(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Like [`interpolate_env`], environment variables merged into `document` are kept as they are.
+pub(crate) fn interpolate_overridden(
+    document: &mut Overridden,
+    unset: UnsetVar,
+) -> Result<(), ConfigError> {
+    interpolate_entries(document, &mut Vec::new(), unset)
+}
+
+fn interpolate_entries(
+    document: &mut Overridden,
+    key: &mut Vec<String>,
+    unset: UnsetVar,
+) -> Result<(), ConfigError> {
+    match document {
+        Overridden::Value(value) => interpolate_value(value, key, unset, &lookup),
+        Overridden::Var(_) => Ok(()),
+        Overridden::Table(entries) => {
+            for (name, entry) in entries {
+                key.push(name.clone());
+                interpolate_entries(entry, key, unset)?;
+                key.pop();
+            }
+            Ok(())
+        }
+    }
+}
+
+fn interpolate_value(
+    value: &mut toml::Value,
+    key: &mut Vec<String>,
+    unset: UnsetVar,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    match value {
+        toml::Value::String(string) => {
+            *string = expand(string, unset, lookup)
+                .map_err(|name| ConfigError::UnsetVariable(key.join("."), name))?;
+        }
+        toml::Value::Array(array) => {
+            for (index, item) in array.iter_mut().enumerate() {
+                key.push(index.to_string());
+                interpolate_value(item, key, unset, lookup)?;
+                key.pop();
+            }
+        }
+        toml::Value::Table(table) => {
+            for (name, item) in table {
+                key.push(name.clone());
+                interpolate_value(item, key, unset, lookup)?;
+                key.pop();
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// On failure returns the name of the unset variable.
+fn expand(
+    string: &str,
+    unset: UnsetVar,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut expanded = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, default, reference_len) = if let Some(after) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                // unterminated, kept as written
+                expanded.push('$');
+                rest = after;
+                continue;
+            };
+            let (name, default) = match braced[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&braced[..end], None),
+            };
+            (name, default, end + 3)
+        } else {
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..len], None, len + 1)
+        };
+        let reference = &rest[start..start + reference_len];
+        rest = &rest[start + reference_len..];
+        if !is_var_name(name) {
+            expanded.push_str(reference);
+            continue;
+        }
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => expanded.push_str(default),
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) if unset == UnsetVar::Keep => expanded.push_str(reference),
+            (None, None) => return Err(name.to_string()),
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn is_var_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigError, UnsetVar};
+
+    use super::{expand, interpolate_value};
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some(String::from("/home/user")),
+            "XDG_RUNTIME_DIR" => Some(String::from("/run/user/1000")),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let expand = |string| expand(string, UnsetVar::Keep, &lookup);
+        for (string, expected) in [
+            ("${XDG_RUNTIME_DIR}/myapp.sock", "/run/user/1000/myapp.sock"),
+            ("$HOME/pics/bg.png", "/home/user/pics/bg.png"),
+            ("${MISSING:-/tmp}/x", "/tmp/x"),
+            ("${EMPTY:-fallback}", "fallback"),
+            ("${HOME:-/root}", "/home/user"),
+            ("$MISSING and ${MISSING}", "$MISSING and ${MISSING}"),
+            ("costs $$5, $${HOME}", "costs $5, ${HOME}"),
+            ("$5 ${unterminated $ ${}", "$5 ${unterminated $ ${}"),
+        ] {
+            assert_eq!(expand(string).as_deref(), Ok(expected), "{}", string);
+        }
+        assert_eq!(
+            super::expand("${MISSING}", UnsetVar::Error, &lookup),
+            Err(String::from("MISSING"))
+        );
+    }
+
+    #[test]
+    fn test_interpolate_value() {
+        let mut value: toml::Value = toml::from_str(
+            "socket = \"${XDG_RUNTIME_DIR}/myapp.sock\"\nsize = 3\n\
+             [window]\npaths = [\"$HOME\", \"${MISSING}\"]",
+        )
+        .unwrap();
+        let error = interpolate_value(
+            &mut value.clone(),
+            &mut Vec::new(),
+            UnsetVar::Error,
+            &lookup,
+        )
+        .expect_err("Unset variable was interpolated.");
+        match &error {
+            ConfigError::UnsetVariable(key, name) => {
+                assert_eq!((key.as_str(), name.as_str()), ("window.paths.1", "MISSING"));
+            }
+            _ => panic!("Expected an unset variable, got {:?}", error),
+        }
+        interpolate_value(&mut value, &mut Vec::new(), UnsetVar::Keep, &lookup)
+            .expect("Could not interpolate.");
+        let expected: toml::Value = toml::from_str(
+            "socket = \"/run/user/1000/myapp.sock\"\nsize = 3\n\
+             [window]\npaths = [\"/home/user\", \"${MISSING}\"]",
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+}
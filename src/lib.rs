@@ -1,177 +1,2541 @@
-use serde::{self, de::DeserializeOwned, Deserialize};
+#[macro_use]
+mod logging;
+
+#[cfg(feature = "async")]
+mod asynchronous;
+mod backup;
+#[cfg(feature = "cli")]
+mod cli;
+mod convert;
+mod css;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod diff;
+mod env;
+mod error;
+mod export;
+mod format;
+mod fragments;
+mod global;
+mod handle;
+mod include;
+#[cfg(feature = "ini")]
+mod ini;
+mod interpolate;
+#[cfg(feature = "hot-swap")]
+mod live;
+mod loader;
+#[cfg(feature = "watch")]
+mod manager;
+mod merge;
+mod overlay;
+mod overrides;
+mod plugin;
+mod presence;
+#[cfg(feature = "preserve-format")]
+mod preserve;
+mod profile;
+mod project;
+mod provenance;
+mod raw;
+mod report;
+mod reset;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+#[cfg(test)]
+mod test_support;
+mod theme;
+mod value;
+#[cfg(feature = "watch")]
+mod watch;
+mod write;
+
+use format::FormatRef;
+use serde::{self, de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    fmt::{Debug, Display},
+    convert::Infallible,
+    fmt::Debug,
     fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, Read},
     path::{Path, PathBuf},
-    str::FromStr,
 };
 
-#[derive(Debug)]
-pub struct ReadConfigFileError {}
-impl Display for ReadConfigFileError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("Error on reading File.")
+/// Files larger than this are rejected with [`ConfigError::FileTooLarge`] unless a different
+/// limit is passed, e.g. via [`try_create_config_with_limit`].
+pub const DEFAULT_SIZE_LIMIT: u64 = 1024 * 1024;
+
+// lets the derive macros refer to `::oxilib` within this crate's tests
+#[cfg(test)]
+extern crate self as oxilib;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+}
+
+#[cfg(feature = "async")]
+pub use asynchronous::{load_config_async, watch_config_stream};
+pub use backup::MAX_BROKEN_BACKUPS;
+#[cfg(feature = "cli")]
+pub use cli::merge_optional;
+pub use convert::{convert_config, ConversionReport};
+pub use css::{
+    bundle_css, create_css_bundle, create_css_variants, load_css_layers, load_css_variant,
+    read_specific_css_with_warnings, render_css, try_create_css_bundle, try_create_css_variants,
+    validate_css, write_css_layers, CssError, CssLayers, CssWarning, Variant, DEFAULT_CSS_FILE,
+    USER_CSS_FILE,
+};
+pub use diff::ConfigChange;
+#[allow(deprecated)]
+pub use error::{ConfigError, FormatError, ParseError, ReadConfigFileError, ValidationError};
+pub use export::{apply_env_vars, to_env_vars};
+pub use format::{ConfigFormat, Format};
+pub use global::{global, init_global, init_global_in};
+pub use handle::ConfigHandle;
+pub use include::MAX_INCLUDE_DEPTH;
+pub use interpolate::{interpolate_env, UnsetVar};
+#[cfg(feature = "hot-swap")]
+pub use live::{LiveConfig, OverrideGuard};
+pub use loader::{ArrayMerge, ConfigLoader, Layer};
+#[cfg(feature = "watch")]
+pub use manager::{ConfigManager, ManagerOverrideGuard};
+pub use overlay::{from_optional_with_default, DefaultConfig, Overlay};
+pub use overrides::apply_overrides;
+#[cfg(feature = "derive")]
+pub use oxilib_derive::{Config, OptionalConfig};
+pub use plugin::{PluginConfigs, PluginRegistry, SectionParser, PLUGIN_SECTION};
+#[cfg(feature = "preserve-format")]
+pub use preserve::write_config_preserving;
+pub use profile::{create_config_with_profile, PROFILE_SECTION};
+pub use project::{find_project_config, find_project_config_in};
+pub use provenance::{Provenance, ValueSource};
+pub use raw::RawConfig;
+pub use report::{ConfigLoadReport, DeprecationNotice, FieldStatus, PathSource, SkippedFragment};
+pub use reset::{reset_config, reset_config_dry_run, ResetPreview};
+#[cfg(all(unix, feature = "signal"))]
+pub use signal::ReloadSignal;
+pub use theme::{list_themes, load_theme, load_theme_or, themes_dir, THEMES_DIR};
+#[cfg(feature = "watch")]
+pub use watch::{
+    watch_config, watch_config_and_css, watch_config_channel, watch_config_if_changed,
+    watch_config_with_changes, watch_config_with_debounce, watch_config_with_options, watch_css,
+    watch_dir, ConfigEvent, ConfigEvents, ConfigReload, ConfigWatcher, WatchBackend, WatchMode,
+    WatchOptions, WatchedChange, DEFAULT_DEBOUNCE, DEFAULT_POLL_INTERVAL,
+};
+pub use write::{dump_config, update_config, write_config};
+
+pub trait Config<Optional: ConfigOptional> {
+    fn create_from_optional(optional: Optional) -> Self;
+
+    /// Creates a nested section, a missing section is created like an empty one:
+    /// `window: Window::create_from_section(optional.window)`.
+    fn create_from_section(section: Option<Optional>) -> Self
+    where
+        Self: Sized,
+        Optional: Default,
+    {
+        Self::create_from_optional(section.unwrap_or_default())
+    }
+
+    /// Checks constraints across fields, e.g. `min_width <= max_width`.
+    /// Called by the loaders after [`Config::create_from_optional`].
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        Ok(())
+    }
+}
+
+pub trait ConfigOptional: for<'de> Deserialize<'de> + DeserializeOwned + Debug {
+    /// Combines two optional configs like the system and the user file, the set fields
+    /// of `other` win. Replaces `self` with `other` unless implemented with [`impl_merge!`]
+    /// or `#[derive(OptionalConfig)]`, which merge nested optional configs field by field.
+    fn merge(self, other: Self) -> Self {
+        let _ = self;
+        other
+    }
+}
+
+/// Lets the untyped document be loaded like an optional config.
+impl ConfigOptional for toml::Table {}
+
+/// Links a config to its optional counterpart, implemented by `#[derive(OptionalConfig)]`.
+pub trait HasOptional {
+    type Optional: ConfigOptional;
+}
+
+/// Fallible counterpart of [`Config`] for configs with fields that have no sensible default.
+/// Every [`Config`] is a `TryConfig` that never fails.
+pub trait TryConfig<Optional: ConfigOptional>: Sized {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn try_create_from_optional(optional: Optional) -> Result<Self, Self::Error>;
+
+    /// Like [`Config::create_from_section`].
+    fn try_create_from_section(section: Option<Optional>) -> Result<Self, Self::Error>
+    where
+        Optional: Default,
+    {
+        Self::try_create_from_optional(section.unwrap_or_default())
+    }
+
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        Ok(())
+    }
+}
+
+impl<ConcreteConfig, OptionalConfig> TryConfig<OptionalConfig> for ConcreteConfig
+where
+    ConcreteConfig: Config<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    type Error = Infallible;
+
+    fn try_create_from_optional(optional: OptionalConfig) -> Result<Self, Self::Error> {
+        Ok(ConcreteConfig::create_from_optional(optional))
+    }
+
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        Config::validate(self)
+    }
+}
+
+pub fn create_config_folder(config_path: impl AsRef<Path>) -> PathBuf {
+    match try_create_config_folder(config_path) {
+        Ok(config_dir) => config_dir,
+        Err(ConfigError::NoHomeDirectory) => {
+            panic!("There is no home directory, please ensure your PC has a home directory.")
+        }
+        Err(error) => panic!("Could not create config folder: {}", error),
+    }
+}
+
+pub fn try_create_config_folder(config_path: impl AsRef<Path>) -> Result<PathBuf, ConfigError> {
+    try_create_config_folder_in(&config_home()?, config_path)
+}
+
+/// Resolves the config folder like [`try_create_config_folder`] without creating it.
+pub fn get_config_dir(config_path: impl AsRef<Path>) -> Result<PathBuf, ConfigError> {
+    Ok(get_config_dir_in(&config_home()?, config_path))
+}
+
+/// Like [`get_config_dir`], but relative to `base_dir` instead of the user's config directory.
+pub fn get_config_dir_in(base_dir: &Path, config_path: impl AsRef<Path>) -> PathBuf {
+    base_dir.join(config_path)
+}
+
+pub fn config_dir_exists(config_path: impl AsRef<Path>) -> bool {
+    match get_config_dir(config_path) {
+        Ok(config_dir) => config_dir.is_dir(),
+        Err(_) => false,
+    }
+}
+
+/// Like [`config_dir_exists`], but relative to `base_dir` instead of the user's config directory.
+pub fn config_dir_exists_in(base_dir: &Path, config_path: impl AsRef<Path>) -> bool {
+    get_config_dir_in(base_dir, config_path).is_dir()
+}
+
+fn config_home() -> Result<PathBuf, ConfigError> {
+    let base = match directories_next::BaseDirs::new() {
+        Some(base) => base,
+        None => return Err(ConfigError::NoHomeDirectory),
+    };
+    let home_dir = base.config_dir();
+    if !home_dir.is_dir() {
+        return Err(ConfigError::NoHomeDirectory);
+    }
+    Ok(home_dir.to_path_buf())
+}
+
+/// Like [`create_config_folder`], but relative to `base_dir` instead of the user's config directory,
+/// e.g. a temporary folder in tests or the folder of a portable install.
+pub fn create_config_folder_in(base_dir: &Path, config_path: impl AsRef<Path>) -> PathBuf {
+    match try_create_config_folder_in(base_dir, config_path) {
+        Ok(config_dir) => config_dir,
+        Err(error) => panic!("Could not create config folder: {}", error),
+    }
+}
+
+/// Like [`try_create_config_folder`], but relative to `base_dir` instead of the user's config directory.
+pub fn try_create_config_folder_in(
+    base_dir: &Path,
+    config_path: impl AsRef<Path>,
+) -> Result<PathBuf, ConfigError> {
+    ensure_dir(base_dir.join(config_path))
+}
+
+/// Like [`try_create_config_folder`], but uses the platform conventions for application folders,
+/// e.g. `~/Library/Application Support/<qualifier>.<organization>.<application>` on macOS.
+pub fn create_project_config_folder(
+    qualifier: &str,
+    organization: &str,
+    application: &str,
+) -> Result<PathBuf, ConfigError> {
+    match directories_next::ProjectDirs::from(qualifier, organization, application) {
+        Some(project) => ensure_dir(project.config_dir().to_path_buf()),
+        None => Err(ConfigError::NoHomeDirectory),
+    }
+}
+
+/// Like [`create_project_config_folder`], but relative to `base_dir` instead of the user's
+/// config directory, the folder is named by the same platform conventions.
+pub fn create_project_config_folder_in(
+    base_dir: &Path,
+    qualifier: &str,
+    organization: &str,
+    application: &str,
+) -> Result<PathBuf, ConfigError> {
+    match directories_next::ProjectDirs::from(qualifier, organization, application) {
+        Some(project) => ensure_dir(base_dir.join(project.project_path())),
+        None => Err(ConfigError::NoHomeDirectory),
+    }
+}
+
+fn ensure_dir(config_dir: PathBuf) -> Result<PathBuf, ConfigError> {
+    if !config_dir.is_dir() {
+        match fs::create_dir_all(&config_dir) {
+            Ok(()) => {}
+            // another process created it in the meantime
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && config_dir.is_dir() => {}
+            Err(error) => return Err(ConfigError::Io(config_dir, error)),
+        }
+    }
+    Ok(config_dir)
+}
+
+/// Reads the css file at `absolute_path`, failing with [`ConfigError::NotFound`],
+/// [`ConfigError::PermissionDenied`] or [`ConfigError::NotAFile`] for paths that can't be read.
+pub fn read_specific_css(absolute_path: impl AsRef<Path>) -> Result<String, ConfigError> {
+    read_file(absolute_path.as_ref(), DEFAULT_SIZE_LIMIT)
+}
+
+/// Reads and parses the config at `absolute_path` without creating anything.
+/// An empty file means every field falls back to its default.
+/// The format is chosen by the file extension, see [`Format::detect`].
+pub fn read_specific_config<ConcreteConfig, OptionalConfig>(
+    absolute_path: impl AsRef<Path>,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let path = absolute_path.as_ref();
+    log_debug!("Reading config from {}", path.display());
+    let contents = read_file(path, DEFAULT_SIZE_LIMIT)?;
+    let mut unknown_keys = Vec::new();
+    let parsed_conf: OptionalConfig = parse_config(
+        path,
+        &contents,
+        Format::detect(path).into(),
+        &mut unknown_keys,
+    )?;
+    warn_unknown_keys(path, &unknown_keys);
+    create_from_parsed(path, parsed_conf)
+}
+
+/// Parses `contents` without touching the filesystem. Empty contents are valid toml,
+/// every field of the optional config is then `None`, so the concrete config is all defaults.
+pub fn parse_config_str<ConcreteConfig, OptionalConfig>(
+    contents: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let path = Path::new("");
+    let mut unknown_keys = Vec::new();
+    let parsed_conf: OptionalConfig =
+        parse_config(path, contents, Format::Toml.into(), &mut unknown_keys)?;
+    warn_unknown_keys(Path::new("<string>"), &unknown_keys);
+    create_from_parsed(path, parsed_conf)
+}
+
+/// Reads the config from e.g. a pipe or [`std::io::stdin`], at most [`DEFAULT_SIZE_LIMIT`] bytes.
+pub fn read_config_from_reader<ConcreteConfig, OptionalConfig, R>(
+    reader: R,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+    R: Read,
+{
+    read_config_from_reader_with_format(reader, Format::Toml)
+}
+
+/// Like [`read_config_from_reader`], for configs written in a different `format`.
+/// Empty input means every field falls back to its default.
+pub fn read_config_from_reader_with_format<ConcreteConfig, OptionalConfig, R>(
+    reader: R,
+    format: Format,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+    R: Read,
+{
+    let path = Path::new("");
+    let contents = read_limited(path, reader, DEFAULT_SIZE_LIMIT)?;
+    let mut unknown_keys = Vec::new();
+    let parsed_conf: OptionalConfig =
+        parse_config(path, &contents, format.into(), &mut unknown_keys)?;
+    warn_unknown_keys(Path::new("<input>"), &unknown_keys);
+    create_from_parsed(path, parsed_conf)
+}
+
+/// Reads the config piped into the program, e.g. for `gen-config | app --config -`.
+/// An empty stdin means every field falls back to its default.
+pub fn read_config_from_stdin<ConcreteConfig, OptionalConfig>(
+    format: Format,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    read_config_from_reader_with_format(io::stdin().lock(), format)
+}
+
+pub fn try_create_config<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    try_create_config_with_limit(
+        config_dir,
+        config_file_name,
+        default_config,
+        DEFAULT_SIZE_LIMIT,
+    )
+}
+
+/// Like [`try_create_config`], but with a custom maximum file size instead of [`DEFAULT_SIZE_LIMIT`].
+pub fn try_create_config_with_limit<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    size_limit: u64,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        size_limit,
+        true,
+        &mut Vec::new(),
+    )?;
+    create_from_parsed(&config_file, loaded.parsed_conf)
+}
+
+/// Like [`try_create_config`], for config files written in a different `format`.
+pub fn try_create_config_with_format<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    format: Format,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        format.into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    create_from_parsed(&config_file, loaded.parsed_conf)
+}
+
+/// [`try_create_config`] for json files, `default_config` has to be json as well.
+#[cfg(feature = "json")]
+pub fn try_create_config_json<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    try_create_config_with_format(config_dir, config_file_name, default_config, Format::Json)
+}
+
+/// Creates the config folder `app_dir`, creates `file_name` inside of it if missing and loads it.
+/// Returns the config together with the path of the config file.
+pub fn load_or_create<ConcreteConfig, OptionalConfig>(
+    app_dir: &str,
+    file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, PathBuf), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    load_or_create_in(&config_home()?, app_dir, file_name, default_config)
+}
+
+/// Like [`load_or_create`], but relative to `base_dir` instead of the user's config directory.
+pub fn load_or_create_in<ConcreteConfig, OptionalConfig>(
+    base_dir: &Path,
+    app_dir: &str,
+    file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, PathBuf), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_dir = try_create_config_folder_in(base_dir, app_dir)?;
+    let handle = load_config(&config_dir, file_name, default_config)?;
+    Ok((handle.config, handle.path))
+}
+
+/// Like [`try_create_config`], additionally returning the path of the config file
+/// and whether it was created.
+pub fn load_config<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ConfigHandle<ConcreteConfig>, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let (config, created, contents_hash) =
+        load_hashed::<ConcreteConfig, OptionalConfig>(&config_file, default_config)?;
+    Ok(ConfigHandle::new(
+        config,
+        config_file,
+        created,
+        default_config,
+        contents_hash,
+        load_hashed::<ConcreteConfig, OptionalConfig>,
+    ))
+}
+
+/// Also returns whether the file was created and a hash of the contents the config was parsed from.
+fn load_hashed<ConcreteConfig, OptionalConfig>(
+    config_file: &Path,
+    default_config: &str,
+) -> Result<(ConcreteConfig, bool, u64), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let loaded = load_optional(
+        config_file,
+        default_config,
+        Format::detect(config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    let mut hasher = DefaultHasher::new();
+    if loaded.contents.is_empty() {
+        default_config.hash(&mut hasher);
+    } else {
+        loaded.contents.hash(&mut hasher);
+    }
+    let config = create_from_parsed(config_file, loaded.parsed_conf)?;
+    Ok((config, loaded.created, hasher.finish()))
+}
+
+/// Like [`try_create_config`], additionally returning the document as it is in the file.
+/// A missing or empty file yields an empty table, even though `default_config` is used.
+pub fn create_config_with_raw<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, toml::Value), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    let raw = RawConfig::parse(
+        &config_file,
+        &loaded.contents,
+        Format::detect(&config_file).into(),
+    )?;
+    let config = create_from_parsed(&config_file, loaded.parsed_conf)?;
+    Ok((config, raw.into_value()))
+}
+
+/// Like [`try_create_config`], additionally reporting unknown keys, whether the file was created
+/// and which fields were present in it, see [`ConfigLoadReport`].
+pub fn create_config_with_report<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, ConfigLoadReport), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional + Serialize,
+{
+    let mut report = ConfigLoadReport::default();
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut report.unknown_keys,
+    )?;
+    report.created = loaded.created;
+    report.fields = presence::file_field_statuses(&loaded.parsed_conf, !loaded.contents.is_empty());
+    let config = create_from_parsed(&config_file, loaded.parsed_conf)?;
+    Ok((config, report))
+}
+
+/// Like [`create_config_with_report`], but never creates or writes anything.
+/// A missing file falls back to `default_config` and sets [`ConfigLoadReport::file_missing`].
+pub fn load_config_readonly<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, ConfigLoadReport), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional + Serialize,
+{
+    let mut report = ConfigLoadReport::default();
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        false,
+        &mut report.unknown_keys,
+    )?;
+    report.file_missing = loaded.missing;
+    report.fields = presence::file_field_statuses(&loaded.parsed_conf, !loaded.contents.is_empty());
+    let config = create_from_parsed(&config_file, loaded.parsed_conf)?;
+    Ok((config, report))
+}
+
+/// Like [`create_config_with_report`], additionally reporting every `(old, new)` key pair
+/// of `deprecated_keys` whose old key is still used in the file.
+pub fn create_config_with_deprecations<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    deprecated_keys: &[(&str, &str)],
+) -> Result<(ConcreteConfig, ConfigLoadReport), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional + Serialize,
+{
+    let mut report = ConfigLoadReport::default();
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut report.unknown_keys,
+    )?;
+    if let Ok(document) = loaded.contents.parse::<toml::Table>() {
+        report.deprecations =
+            report::find_deprecations(&toml::Value::Table(document), deprecated_keys);
+    }
+    for notice in &report.deprecations {
+        log_warn!("{}: {}", config_file.display(), notice);
+    }
+    report.created = loaded.created;
+    report.fields = presence::file_field_statuses(&loaded.parsed_conf, !loaded.contents.is_empty());
+    let config = create_from_parsed(&config_file, loaded.parsed_conf)?;
+    Ok((config, report))
+}
+
+/// Like [`create_config_with_report`], but an unparsable file is copied to
+/// `<file>.broken-<timestamp>` and the default config is used instead.
+/// The copy is recorded in [`ConfigLoadReport::backup`].
+pub fn create_config_with_backup<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, ConfigLoadReport), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional + Serialize,
+{
+    match create_config_with_report(config_dir, config_file_name, default_config) {
+        Err(ConfigError::Parse(error)) => {
+            let config_file = config_file_path(config_dir, config_file_name);
+            let backup = backup::backup_broken_file(&config_file)?;
+            log_warn!(
+                "{}, using the default config. The broken file was saved to {}",
+                ConfigError::Parse(error),
+                backup.display()
+            );
+            let mut report = ConfigLoadReport::default();
+            let parsed_conf: OptionalConfig = parse_default_config(
+                &config_file,
+                default_config,
+                Format::detect(&config_file).into(),
+                &mut report.unknown_keys,
+            )?;
+            report.fields = presence::file_field_statuses(&parsed_conf, false);
+            report.backup = Some(backup);
+            let config = create_from_parsed(&config_file, parsed_conf)?;
+            Ok((config, report))
+        }
+        result => result,
+    }
+}
+
+/// Like [`create_config_with_report`], additionally merging every `*.toml` file of the
+/// `config.d` folder next to the config file in lexical order, later files win.
+/// Fragments that are no toml files or can't be parsed are skipped and listed in
+/// [`ConfigLoadReport::skipped_fragments`].
+pub fn create_config_with_fragments<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<(ConcreteConfig, ConfigLoadReport), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional + Serialize,
+{
+    let mut report = ConfigLoadReport::default();
+    let config_file = config_file_path(config_dir, config_file_name);
+    let format = Format::detect(&config_file);
+    let loaded = load_optional::<OptionalConfig>(
+        &config_file,
+        default_config,
+        format.into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    report.created = loaded.created;
+    let contents = if loaded.contents.is_empty() {
+        default_config
+    } else {
+        &loaded.contents
+    };
+    let mut merged: toml::Table = format.parse_file(&config_file, contents, &mut Vec::new())?;
+    let fragment_dir = match config_file.parent() {
+        Some(parent) => parent.join(fragments::FRAGMENT_DIR),
+        None => PathBuf::from(fragments::FRAGMENT_DIR),
+    };
+    let fragments =
+        fragments::read_fragments::<OptionalConfig>(&fragment_dir, &mut report.skipped_fragments)?;
+    for (path, fragment) in fragments {
+        value::merge(&mut merged, fragment);
+        report.fragments.push(path);
+    }
+    let parsed_conf: OptionalConfig = format::from_value(
+        &config_file,
+        toml::Value::Table(merged),
+        &mut report.unknown_keys,
+    )?;
+    report.fields = presence::field_statuses(&parsed_conf);
+    let config = create_from_parsed(&config_file, parsed_conf)?;
+    Ok((config, report))
+}
+
+/// Loads the top-level table `section` of the config file, e.g. `[notifications]`, so several
+/// configs can share one file. `default_config` only contains the keys of the section and is used
+/// if the file or the section is missing. Nothing is created or written.
+pub fn create_section_config<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    section: &str,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let format = Format::detect(&config_file);
+    let mut loaded = load_optional::<toml::Table>(
+        &config_file,
+        "",
+        format.into(),
+        DEFAULT_SIZE_LIMIT,
+        false,
+        &mut Vec::new(),
+    )?;
+    let parsed_conf = match loaded.parsed_conf.remove(section) {
+        Some(value @ toml::Value::Table(_)) => {
+            let mut unknown_keys = Vec::new();
+            let parsed_conf = format::from_value(&config_file, value, &mut unknown_keys)
+                .map_err(|error| error.in_section(&config_file, section))?;
+            let unknown_keys: Vec<String> = unknown_keys
+                .iter()
+                .map(|key| format!("{}.{}", section, key))
+                .collect();
+            warn_unknown_keys(&config_file, &unknown_keys);
+            parsed_conf
+        }
+        Some(_) => {
+            return Err(ConfigError::InvalidSection(
+                config_file,
+                section.to_string(),
+            ))
+        }
+        None => parse_default_config(&config_file, default_config, format.into(), &mut Vec::new())?,
+    };
+    create_from_parsed(&config_file, parsed_conf)
+}
+
+struct LoadedFile<OptionalConfig> {
+    parsed_conf: OptionalConfig,
+    /// Empty if the default config was used.
+    contents: String,
+    /// The file did not exist before and was created.
+    created: bool,
+    /// The file did not exist and `create_missing` was not set.
+    missing: bool,
+}
+
+/// A missing or empty file is only created and filled with `default_config`
+/// if `create_missing` is set.
+fn load_optional<OptionalConfig>(
+    config_file: &Path,
+    default_config: &str,
+    format: FormatRef<'_>,
+    size_limit: u64,
+    create_missing: bool,
+    unknown_keys: &mut Vec<String>,
+) -> Result<LoadedFile<OptionalConfig>, ConfigError>
+where
+    OptionalConfig: ConfigOptional,
+{
+    log_debug!("Loading config from {}", config_file.display());
+    let existed = config_file.is_file();
+    if !existed && !create_missing && !config_file.exists() {
+        let parsed_conf = parse_default_config(config_file, default_config, format, unknown_keys)?;
+        warn_unknown_keys(config_file, unknown_keys);
+        return Ok(LoadedFile {
+            parsed_conf,
+            contents: String::new(),
+            created: false,
+            missing: true,
+        });
+    }
+    let contents = if existed || !create_missing {
+        read_file(config_file, size_limit)?
+    } else {
+        String::new()
+    };
+    let parsed_conf = if contents.is_empty() {
+        if existed {
+            log_warn!(
+                "Config file {} is empty, using the default config",
+                config_file.display()
+            );
+        }
+        let parsed_conf = parse_default_config(config_file, default_config, format, unknown_keys)?;
+        // only written once it is known to be valid, so the file documents the available options
+        if create_missing {
+            if let Err(error) = fs::write(config_file, default_config) {
+                return Err(ConfigError::Io(config_file.to_path_buf(), error));
+            }
+        }
+        parsed_conf
+    } else {
+        parse_config(config_file, &contents, format, unknown_keys)?
+    };
+    warn_unknown_keys(config_file, unknown_keys);
+    Ok(LoadedFile {
+        parsed_conf,
+        contents,
+        created: !existed,
+        missing: false,
+    })
+}
+
+fn warn_unknown_keys(path: &Path, unknown_keys: &[String]) {
+    if !unknown_keys.is_empty() {
+        log_warn!(
+            "Unknown keys in {}: {}",
+            path.display(),
+            unknown_keys.join(", ")
+        );
+    }
+}
+
+/// Like [`try_create_config`], but fails with [`ConfigError::UnknownKeys`]
+/// if the file contains any key the optional config does not know about.
+pub fn create_config_strict<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let mut unknown_keys = Vec::new();
+    let loaded = load_optional(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut unknown_keys,
+    )?;
+    if !unknown_keys.is_empty() {
+        return Err(ConfigError::UnknownKeys(config_file, unknown_keys));
+    }
+    create_from_parsed(&config_file, loaded.parsed_conf)
+}
+
+fn config_file_path(config_dir: &Path, config_file_name: &str) -> PathBuf {
+    if config_file_name.is_empty() {
+        PathBuf::from(config_dir)
+    } else {
+        config_dir.join(config_file_name)
+    }
+}
+
+fn create_from_parsed<ConcreteConfig, OptionalConfig>(
+    path: &Path,
+    parsed_conf: OptionalConfig,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config = match ConcreteConfig::try_create_from_optional(parsed_conf) {
+        Ok(config) => config,
+        Err(error) => return Err(ConfigError::Conversion(path.to_path_buf(), Box::new(error))),
+    };
+    if let Err(errors) = TryConfig::validate(&config) {
+        return Err(ConfigError::Validation(path.to_path_buf(), errors));
+    }
+    Ok(config)
+}
+
+fn read_file(path: &Path, size_limit: u64) -> Result<String, ConfigError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(error) => return Err(ConfigError::from_io(path.to_path_buf(), error)),
+    };
+    let metadata = match file.metadata() {
+        Ok(metadata) => metadata,
+        Err(error) => return Err(ConfigError::from_io(path.to_path_buf(), error)),
+    };
+    if !metadata.is_file() {
+        return Err(ConfigError::NotAFile(path.to_path_buf()));
+    }
+    if metadata.len() > size_limit {
+        return Err(ConfigError::FileTooLarge {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            limit: size_limit,
+        });
+    }
+    // the file might grow after the metadata check, never read more than the limit
+    read_limited(path, file, size_limit)
+}
+
+fn read_limited(path: &Path, reader: impl Read, size_limit: u64) -> Result<String, ConfigError> {
+    let mut contents = String::new();
+    if let Err(error) = reader.take(size_limit + 1).read_to_string(&mut contents) {
+        return Err(ConfigError::from_io(path.to_path_buf(), error));
+    }
+    if contents.len() as u64 > size_limit {
+        return Err(ConfigError::FileTooLarge {
+            path: path.to_path_buf(),
+            size: contents.len() as u64,
+            limit: size_limit,
+        });
+    }
+    Ok(contents)
+}
+
+fn parse_default_config<OptionalConfig>(
+    path: &Path,
+    default_config: &str,
+    format: FormatRef<'_>,
+    unknown_keys: &mut Vec<String>,
+) -> Result<OptionalConfig, ConfigError>
+where
+    OptionalConfig: ConfigOptional,
+{
+    parse_config(path, default_config, format, unknown_keys).map_err(|error| match error {
+        ConfigError::Parse(error) => ConfigError::DefaultConfigInvalid(error),
+        error => error,
+    })
+}
+
+fn parse_config<OptionalConfig>(
+    path: &Path,
+    contents: &str,
+    format: FormatRef<'_>,
+    unknown_keys: &mut Vec<String>,
+) -> Result<OptionalConfig, ConfigError>
+where
+    OptionalConfig: ConfigOptional,
+{
+    format.parse(path, contents, unknown_keys)
+}
+
+/// Loads the config, falling back to `default_config` if the file can't be parsed.
+/// Panics if the file can't be created or read, or if `default_config` itself is invalid.
+pub fn create_config<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> ConcreteConfig
+where
+    ConcreteConfig: Config<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let format = Format::detect(&config_file_path(config_dir, config_file_name));
+    create_config_with_format(config_dir, config_file_name, default_config, format)
+}
+
+/// Like [`create_config`], for config files written in a different `format`.
+pub fn create_config_with_format<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    format: Format,
+) -> ConcreteConfig
+where
+    ConcreteConfig: Config<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    match try_create_config_with_format(config_dir, config_file_name, default_config, format) {
+        Ok(config) => config,
+        Err(ConfigError::Parse(error)) => {
+            log_warn!("{}, using the default config", ConfigError::Parse(error));
+            let config_file = config_file_path(config_dir, config_file_name);
+            match parse_default_config(&config_file, default_config, format.into(), &mut Vec::new())
+                .and_then(|parsed_conf| create_from_parsed(&config_file, parsed_conf))
+            {
+                Ok(config) => config,
+                Err(error) => panic!("Could not create config: {}", error),
+            }
+        }
+        Err(error) => panic!("Could not create config: {}", error),
+    }
+}
+
+/// [`create_config`] for json files, `default_config` has to be json as well.
+#[cfg(feature = "json")]
+pub fn create_config_json<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> ConcreteConfig
+where
+    ConcreteConfig: Config<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    create_config_with_format(config_dir, config_file_name, default_config, Format::Json)
+}
+
+/// Like [`try_create_config`], with the default config generated from `ConcreteConfig::default()`
+/// instead of a hand-written string, so the created file and the fallback can't drift apart.
+/// The default is serialized in the format detected from the file extension.
+pub fn try_create_config_from_default<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + Serialize + Default,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let format = Format::detect(&config_file);
+    let default_config = match format.serialize(&ConcreteConfig::default()) {
+        Ok(default_config) => default_config,
+        Err(error) => return Err(ConfigError::Serialize(config_file, error)),
+    };
+    let loaded = load_optional::<OptionalConfig>(
+        &config_file,
+        &default_config,
+        format.into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    if loaded.contents.is_empty() {
+        return Ok(ConcreteConfig::default());
+    }
+    create_from_parsed(&config_file, loaded.parsed_conf)
+}
+
+/// Like [`create_config`], with the default config generated from `ConcreteConfig::default()`.
+/// Falls back to `ConcreteConfig::default()` if the file can't be parsed.
+pub fn create_config_from_default<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+) -> ConcreteConfig
+where
+    ConcreteConfig: Config<OptionalConfig> + Serialize + Default,
+    OptionalConfig: ConfigOptional,
+{
+    match try_create_config_from_default(config_dir, config_file_name) {
+        Ok(config) => config,
+        Err(ConfigError::Parse(error)) => {
+            log_warn!("{}, using the default config", ConfigError::Parse(error));
+            ConcreteConfig::default()
+        }
+        Err(error) => panic!("Could not create config: {}", error),
+    }
+}
+
+/// Like [`try_create_config`], with the set fields of `overrides` merged over the file with
+/// [`ConfigOptional::merge`] before the config is created, e.g. values detected at runtime.
+/// The overrides are never written to the file.
+pub fn try_create_config_with_overrides<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    overrides: OptionalConfig,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional::<OptionalConfig>(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    create_from_parsed(&config_file, loaded.parsed_conf.merge(overrides))
+}
+
+/// Like [`create_config`], with `overrides` merged over the file or the default config,
+/// see [`try_create_config_with_overrides`].
+pub fn create_config_with_overrides<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    overrides: OptionalConfig,
+) -> ConcreteConfig
+where
+    ConcreteConfig: Config<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let format = Format::detect(&config_file);
+    let parsed_conf = match load_optional::<OptionalConfig>(
+        &config_file,
+        default_config,
+        format.into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    ) {
+        Ok(loaded) => loaded.parsed_conf,
+        Err(ConfigError::Parse(error)) => {
+            log_warn!("{}, using the default config", ConfigError::Parse(error));
+            match parse_default_config(&config_file, default_config, format.into(), &mut Vec::new())
+            {
+                Ok(parsed_conf) => parsed_conf,
+                Err(error) => panic!("Could not create config: {}", error),
+            }
+        }
+        Err(error) => panic!("Could not create config: {}", error),
+    };
+    match create_from_parsed(&config_file, parsed_conf.merge(overrides)) {
+        Ok(config) => config,
+        Err(error) => panic!("Could not create config: {}", error),
+    }
+}
+
+pub fn create_css(config_dir: &Path, css_file: &str, css_content: &str) -> PathBuf {
+    match try_create_css(config_dir, css_file, css_content) {
+        Ok((css_file, _)) => css_file,
+        Err(error) => panic!("Could not create css file: {}", error),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssState {
+    /// The file already had content, nothing was written.
+    Existing,
+    /// The file was missing or empty and the default content was written.
+    DefaultWritten,
+}
+
+pub fn try_create_css(
+    config_dir: &Path,
+    css_file: &str,
+    css_content: &str,
+) -> Result<(PathBuf, CssState), ConfigError> {
+    let css_file = config_dir.join(css_file);
+    if !css_file.is_file() {
+        if let Err(error) = fs::File::create(&css_file) {
+            return Err(ConfigError::Io(css_file, error));
+        }
+    }
+    let is_empty = match fs::read(&css_file) {
+        Ok(content) => content.is_empty(),
+        Err(error) => return Err(ConfigError::Io(css_file, error)),
+    };
+    if !is_empty {
+        return Ok((css_file, CssState::Existing));
+    }
+    if let Err(error) = fs::write(&css_file, css_content) {
+        return Err(ConfigError::Io(css_file, error));
+    }
+    Ok((css_file, CssState::DefaultWritten))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Cursor, Write},
+        path::Path,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        config_dir_exists_in, create_config, create_config_folder, create_config_folder_in,
+        create_config_from_default, create_config_strict, create_config_with_backup,
+        create_config_with_deprecations, create_config_with_fragments,
+        create_config_with_overrides, create_config_with_raw, create_config_with_report,
+        create_css, create_project_config_folder_in, create_section_config,
+        from_optional_with_default, get_config_dir_in, load_config, load_config_readonly,
+        load_or_create_in, parse_config_str, read_config_from_reader,
+        read_config_from_reader_with_format, read_specific_config, read_specific_css,
+        test_support::{test_dir, OptWhatConf as OptConf, WhatConf as Conf},
+        try_create_config, try_create_config_folder_in, try_create_config_from_default,
+        try_create_config_with_limit, try_create_config_with_overrides, try_create_css, value,
+        Config, ConfigError, ConfigOptional, CssState, DefaultConfig, FieldStatus, Format,
+        TryConfig, ValidationError, DEFAULT_SIZE_LIMIT,
+    };
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct OptSomething {
+        something: Option<u32>,
+    }
+
+    impl ConfigOptional for OptSomething {}
+
+    impl Config<OptSomething> for OptSomething {
+        fn create_from_optional(optional: OptSomething) -> Self {
+            optional
+        }
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    struct DefaultConf {
+        something: u32,
+        what: String,
+    }
+
+    impl DefaultConfig for DefaultConf {}
+
+    #[derive(Debug, Deserialize)]
+    struct OptDefaultConf {
+        something: Option<u32>,
+        what: Option<String>,
+    }
+
+    impl ConfigOptional for OptDefaultConf {}
+
+    crate::impl_overlay!(OptDefaultConf => DefaultConf { something, what });
+
+    #[test]
+    fn test_default_config() {
+        let conf = parse_config_str::<DefaultConf, OptDefaultConf>("what = \"a\"")
+            .expect("Could not parse config.");
+        assert_eq!((conf.something, conf.what.as_str()), (0, "a"));
+
+        let conf: DefaultConf = from_optional_with_default(OptDefaultConf {
+            something: Some(3),
+            what: None,
+        });
+        assert_eq!((conf.something, conf.what.as_str()), (3, ""));
+    }
+
+    #[test]
+    fn test_config_folder() {
+        let config_dir = create_config_folder("testfolder");
+        assert!(&config_dir.is_dir());
+        fs::remove_dir(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_get_config_dir() {
+        let base = test_dir("testfolder_get_dir");
+        let config_dir = get_config_dir_in(&base, "app");
+        assert_eq!(config_dir, base.join("app"));
+        assert!(!config_dir.exists());
+        assert!(!config_dir_exists_in(&base, "app"));
+        assert_eq!(create_config_folder_in(&base, "app"), config_dir);
+        assert!(config_dir_exists_in(&base, "app"));
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_folder_nested() {
+        let base = test_dir("testfolder_nested");
+        let config_dir = create_config_folder_in(&base, "app/widgets");
+        assert!(config_dir.is_dir());
+        assert!(config_dir.ends_with("app/widgets"));
+        assert_eq!(create_config_folder_in(&base, "app/widgets"), config_dir);
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_project_config_folder() {
+        let base = test_dir("testfolder_project");
+        let config_dir = create_project_config_folder_in(&base, "org", "Oxi", "testproject")
+            .expect("Could not create project folder.");
+        assert!(config_dir.is_dir());
+        assert!(config_dir.starts_with(&base));
+        assert!(config_dir.to_string_lossy().contains("testproject"));
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_folder_in() {
+        let base = test_dir("test_folder_in");
+        let config_dir =
+            try_create_config_folder_in(&base, "app").expect("Could not create config folder.");
+        assert_eq!(config_dir, base.join("app"));
+        assert!(config_dir.is_dir());
+
+        let file_base = base.join("file");
+        fs::write(&file_base, "").expect("Could not create test file.");
+        let error = try_create_config_folder_in(&file_base, "app")
+            .expect_err("Folder inside of a file was created.");
+        assert!(matches!(error, ConfigError::Io(_, _)));
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config() {
+        let config_dir = create_config_folder("testfolder2");
+        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 10");
+        assert_eq!(conf.something, 10);
+        assert_eq!(conf.what, String::from("pingpang"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_load_or_create() {
+        let base = test_dir("testfolder_load_or_create");
+        let (conf, path) =
+            load_or_create_in::<Conf, OptConf>(&base, "app", "config.toml", "something = 4")
+                .expect("Could not load config.");
+        assert_eq!(conf.something, 4);
+        assert_eq!(path, base.join("app").join("config.toml"));
+        assert!(path.is_file());
+
+        fs::write(&path, "something = ").expect("Could not write test config.");
+        let error = load_or_create_in::<Conf, OptConf>(&base, "app", "config.toml", "")
+            .expect_err("Broken config was parsed successfully.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_load_config_handle() {
+        let config_dir = test_dir("testfolder_handle");
+        let handle = load_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 8")
+            .expect("Could not load config.");
+        assert_eq!(handle.config.something, 8);
+        assert_eq!(handle.path, config_dir.join("config.toml"));
+        assert!(handle.created);
+        let handle = load_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 8")
+            .expect("Could not load config.");
+        assert!(!handle.created);
+
+        let handle =
+            load_config::<Conf, OptConf>(&handle.path, "", "").expect("Could not load config.");
+        assert_eq!(handle.path, config_dir.join("config.toml"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_report_created() {
+        let config_dir = test_dir("testfolder_report_created");
+        let (_, report) =
+            create_config_with_report::<OptSomething, OptSomething>(&config_dir, "config.toml", "")
+                .expect("Could not load config.");
+        assert!(report.created);
+        let (_, report) =
+            create_config_with_report::<OptSomething, OptSomething>(&config_dir, "config.toml", "")
+                .expect("Could not load config.");
+        assert!(!report.created);
+
+        fs::write(config_dir.join("empty.toml"), "").expect("Could not write test config.");
+        let handle = load_config::<OptSomething, OptSomething>(&config_dir, "empty.toml", "")
+            .expect("Could not load config.");
+        assert!(!handle.created);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_handle_reload() {
+        let config_dir = test_dir("testfolder_reload");
+        let mut handle = load_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 1")
+            .expect("Could not load config.");
+        assert!(!handle.reload().expect("Could not reload config."));
+        assert_eq!(handle.config.something, 1);
+
+        fs::write(&handle.path, "something = 2").expect("Could not write test config.");
+        assert!(handle.reload().expect("Could not reload config."));
+        assert_eq!(handle.config.something, 2);
+
+        fs::write(&handle.path, "something = ").expect("Could not write test config.");
+        let error = handle.reload().expect_err("Broken config was reloaded.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        assert_eq!(handle.config.something, 2);
+
+        fs::write(&handle.path, "something = 2").expect("Could not write test config.");
+        let change = handle
+            .reload_with_changes()
+            .expect("Could not reload config.");
+        assert!(!change.is_changed());
+        fs::write(&handle.path, "something = 3").expect("Could not write test config.");
+        let change = handle
+            .reload_with_changes()
+            .expect("Could not reload config.");
+        assert_eq!(change.keys().collect::<Vec<_>>(), vec!["something"]);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_with_raw() {
+        let config_dir = test_dir("testfolder_with_raw");
+        let (conf, raw) =
+            create_config_with_raw::<Conf, OptConf>(&config_dir, "config.toml", "something = 1")
+                .expect("Could not load config.");
+        assert_eq!(conf.something, 1);
+        assert_eq!(raw, toml::Value::Table(toml::Table::new()));
+
+        fs::write(
+            config_dir.join("config.toml"),
+            "what = \"a\"\n[inspector]\nshown = true\n",
+        )
+        .expect("Could not write test config.");
+        let (conf, raw) =
+            create_config_with_raw::<Conf, OptConf>(&config_dir, "config.toml", "something = 1")
+                .expect("Could not load config.");
+        assert_eq!(conf.something, 0);
+        assert_eq!(raw["what"].as_str(), Some("a"));
+        assert_eq!(raw["inspector"]["shown"].as_bool(), Some(true));
+        assert!(raw.get("something").is_none());
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_parse_config_str() {
+        let conf = parse_config_str::<Conf, OptConf>("something = 3\nwhat = \"str\"")
+            .expect("Could not parse config.");
+        assert_eq!((conf.something, conf.what.as_str()), (3, "str"));
+
+        let conf = parse_config_str::<Conf, OptConf>("").expect("Could not parse config.");
+        assert_eq!((conf.something, conf.what.as_str()), (0, "pingpang"));
+
+        let error = parse_config_str::<Conf, OptConf>("something = \"ten\"")
+            .expect_err("Broken toml was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.location(), Some((1, 13)));
+                assert!(error.to_string().starts_with("Could not parse 1:13: "));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        assert_eq!(error.path(), None);
+    }
+
+    #[test]
+    fn test_read_config_from_reader() {
+        let conf = read_config_from_reader::<Conf, OptConf, _>(Cursor::new("something = 6"))
+            .expect("Could not read config.");
+        assert_eq!(conf.something, 6);
+
+        let error = read_config_from_reader::<Conf, OptConf, _>(Cursor::new(b"what = \"\xff\""))
+            .expect_err("Invalid utf-8 was read successfully.");
+        assert!(matches!(error, ConfigError::Io(_, _)));
+
+        let too_large = "#".repeat(DEFAULT_SIZE_LIMIT as usize + 1);
+        let error = read_config_from_reader::<Conf, OptConf, _>(Cursor::new(too_large))
+            .expect_err("Too large config was read.");
+        assert!(matches!(error, ConfigError::FileTooLarge { .. }));
+    }
+
+    #[test]
+    fn test_read_config_from_reader_with_format() {
+        let conf =
+            read_config_from_reader_with_format::<Conf, OptConf, _>(Cursor::new(""), Format::Toml)
+                .expect("Could not read empty config.");
+        assert_eq!((conf.something, conf.what.as_str()), (0, "pingpang"));
+
+        let too_large = " ".repeat(DEFAULT_SIZE_LIMIT as usize + 1);
+        let error = read_config_from_reader_with_format::<Conf, OptConf, _>(
+            Cursor::new(too_large),
+            Format::Toml,
+        )
+        .expect_err("Too large config was read.");
+        assert!(matches!(error, ConfigError::FileTooLarge { .. }));
+
+        #[cfg(feature = "json")]
+        {
+            let conf = read_config_from_reader_with_format::<Conf, OptConf, _>(
+                Cursor::new("{\"something\": 6}"),
+                Format::Json,
+            )
+            .expect("Could not read config.");
+            assert_eq!(conf.something, 6);
+        }
+    }
+
+    #[test]
+    fn test_config_readonly() {
+        let base = test_dir("test_readonly");
+        let config_dir = base.join("app");
+        let (conf, report) = load_config_readonly::<OptSomething, OptSomething>(
+            &config_dir,
+            "config.toml",
+            "something = 1",
+        )
+        .expect("Could not load config.");
+        assert_eq!(conf.something, Some(1));
+        assert!(report.file_missing);
+        assert!(!config_dir.exists());
+
+        fs::create_dir_all(&config_dir).expect("Could not create test folder.");
+        fs::write(config_dir.join("config.toml"), "something = 2")
+            .expect("Could not write test config.");
+        let (conf, report) = load_config_readonly::<OptSomething, OptSomething>(
+            &config_dir,
+            "config.toml",
+            "something = 1",
+        )
+        .expect("Could not load config.");
+        assert_eq!(conf.something, Some(2));
+        assert!(!report.file_missing);
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_writes_default() {
+        let config_dir = test_dir("testfolder_writes_default");
+        let default_config = "# how often\nsomething = 10\n";
+        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", default_config);
+        assert_eq!(conf.something, 10);
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            default_config
+        );
+
+        fs::write(config_dir.join("config.toml"), "").expect("Could not write test config.");
+        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", default_config);
+        assert_eq!(conf.something, 10);
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            default_config
+        );
+
+        fs::write(config_dir.join("config.toml"), "something = 2")
+            .expect("Could not write test config.");
+        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", default_config);
+        assert_eq!(conf.something, 2);
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            "something = 2"
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_from_default() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Timeouts {
+            timeout: u32,
+            what: String,
+        }
+
+        impl Default for Timeouts {
+            fn default() -> Self {
+                Timeouts {
+                    timeout: 3,
+                    what: String::from("pingpang"),
+                }
+            }
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct OptTimeouts {
+            timeout: Option<u32>,
+        }
+
+        impl ConfigOptional for OptTimeouts {}
+
+        impl Config<OptTimeouts> for Timeouts {
+            fn create_from_optional(optional: OptTimeouts) -> Self {
+                Timeouts {
+                    timeout: optional.timeout.unwrap_or(3),
+                    ..Timeouts::default()
+                }
+            }
+        }
+
+        let config_dir = test_dir("testfolder_from_default");
+        let conf = create_config_from_default::<Timeouts, OptTimeouts>(&config_dir, "config.toml");
+        assert_eq!(conf.timeout, 3);
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            "timeout = 3\nwhat = \"pingpang\"\n"
+        );
+
+        fs::write(config_dir.join("config.toml"), "timeout = 5")
+            .expect("Could not write test config.");
+        let conf = create_config_from_default::<Timeouts, OptTimeouts>(&config_dir, "config.toml");
+        assert_eq!(conf.timeout, 5);
+
+        fs::write(config_dir.join("config.toml"), "timeout = ")
+            .expect("Could not write test config.");
+        let error =
+            try_create_config_from_default::<Timeouts, OptTimeouts>(&config_dir, "config.toml")
+                .expect_err("Invalid config was parsed.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        let conf = create_config_from_default::<Timeouts, OptTimeouts>(&config_dir, "config.toml");
+        assert_eq!(conf.timeout, 3);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_fragments() {
+        let config_dir = test_dir("testfolder_fragments");
+        let fragment_dir = config_dir.join("config.d");
+        fs::create_dir_all(&fragment_dir).expect("Could not create test folder.");
+        fs::write(
+            config_dir.join("config.toml"),
+            "something = 1\nwhat = \"main\"",
+        )
+        .expect("Could not write test config.");
+        fs::write(fragment_dir.join("20-what.toml"), "what = \"second\"")
+            .expect("Could not write test config.");
+        fs::write(
+            fragment_dir.join("10-what.toml"),
+            "what = \"first\"\nunknown = 1",
+        )
+        .expect("Could not write test config.");
+        fs::write(fragment_dir.join("15-broken.toml"), "something = \"a\"")
+            .expect("Could not write test config.");
+        fs::write(fragment_dir.join("README"), "not a config").expect("Could not write README.");
+
+        let (conf, report) =
+            create_config_with_fragments::<Conf, OptConf>(&config_dir, "config.toml", "")
+                .expect("Could not load config.");
+        assert_eq!((conf.something, conf.what.as_str()), (1, "second"));
+        assert_eq!(
+            report.fragments,
+            vec![
+                fragment_dir.join("10-what.toml"),
+                fragment_dir.join("20-what.toml")
+            ]
+        );
+        assert_eq!(report.unknown_keys, vec![String::from("unknown")]);
+        let skipped: Vec<&Path> = report
+            .skipped_fragments
+            .iter()
+            .map(|fragment| fragment.path.as_path())
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                fragment_dir.join("15-broken.toml").as_path(),
+                fragment_dir.join("README").as_path()
+            ]
+        );
+        assert!(report.skipped_fragments[0].reason.contains("invalid type"));
+        assert_eq!(report.skipped_fragments[1].reason, "not a toml file");
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_section_config() {
+        let config_dir = test_dir("testfolder_section");
+        fs::write(
+            config_dir.join("config.toml"),
+            "[bar]\nsomething = 1\nwhat = \"bar\"\n[launcher]\nsomething = 2",
+        )
+        .expect("Could not write test config.");
+        let bar = create_section_config::<Conf, OptConf>(&config_dir, "config.toml", "bar", "")
+            .expect("Could not load section.");
+        assert_eq!((bar.something, bar.what.as_str()), (1, "bar"));
+        let launcher = create_section_config::<OptSomething, OptSomething>(
+            &config_dir,
+            "config.toml",
+            "launcher",
+            "",
+        )
+        .expect("Could not load section.");
+        assert_eq!(launcher.something, Some(2));
+        let notifications = create_section_config::<Conf, OptConf>(
+            &config_dir,
+            "config.toml",
+            "notifications",
+            "something = 3",
+        )
+        .expect("Could not load section.");
+        assert_eq!(
+            (notifications.something, notifications.what.as_str()),
+            (3, "pingpang")
+        );
+
+        fs::write(config_dir.join("config.toml"), "[bar]\nsomething = \"a\"")
+            .expect("Could not write test config.");
+        let error = create_section_config::<Conf, OptConf>(&config_dir, "config.toml", "bar", "")
+            .expect_err("Invalid section was parsed.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert!(parse.key_path().is_some_and(|key| key.starts_with("bar")))
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::write(config_dir.join("config.toml"), "bar = 1").expect("Could not write config.");
+        let error = create_section_config::<Conf, OptConf>(&config_dir, "config.toml", "bar", "")
+            .expect_err("Invalid section was parsed.");
+        assert!(matches!(error, ConfigError::InvalidSection(_, _)));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+        let conf = create_section_config::<Conf, OptConf>(&config_dir, "config.toml", "bar", "")
+            .expect("Could not load section.");
+        assert_eq!(conf.something, 0);
+        assert!(!config_dir.exists());
+    }
+
+    #[test]
+    fn test_merge_tables() {
+        let mut base: toml::Table =
+            toml::from_str("a = 1\n[window]\nwidth = 1\nheight = 2").unwrap();
+        let overlay: toml::Table = toml::from_str("a = [2]\n[window]\nwidth = 3").unwrap();
+        value::merge(&mut base, overlay);
+        let expected: toml::Table =
+            toml::from_str("a = [2]\n[window]\nwidth = 3\nheight = 2").unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn test_config_unknown_keys() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptWindow {
+            #[allow(dead_code)]
+            height: Option<u32>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptWindowConf {
+            #[allow(dead_code)]
+            something: Option<u32>,
+            #[allow(dead_code)]
+            window: Option<OptWindow>,
+        }
+
+        impl ConfigOptional for OptWindowConf {}
+
+        impl Config<OptWindowConf> for OptWindowConf {
+            fn create_from_optional(optional: OptWindowConf) -> Self {
+                optional
+            }
+        }
+
+        let config_dir = test_dir("testfolder_unknown_keys");
+        fs::write(
+            config_dir.join("config.toml"),
+            "something = 1\nwidht = 300\n[window]\nheight = 2\nheigth = 3\n",
+        )
+        .expect("Could not write test config.");
+        let (_, report) = create_config_with_report::<OptWindowConf, OptWindowConf>(
+            &config_dir,
+            "config.toml",
+            "",
+        )
+        .expect("Could not create config.");
+        assert_eq!(report.unknown_keys, vec!["widht", "window.heigth"]);
+
+        fs::write(
+            config_dir.join("config.toml"),
+            "something = 1\n[window]\nheight = 2\n",
+        )
+        .expect("Could not write test config.");
+        let (_, report) = create_config_with_report::<OptWindowConf, OptWindowConf>(
+            &config_dir,
+            "config.toml",
+            "",
+        )
+        .expect("Could not create config.");
+        assert!(report.unknown_keys.is_empty());
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-}
-impl std::error::Error for ReadConfigFileError {}
 
-pub trait Config<Optional: ConfigOptional>: for<'de> Deserialize<'de> {
-    fn create_from_optional(optional: Optional) -> Self;
-}
+    #[test]
+    fn test_config_field_report() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptWindow {
+            width: Option<u32>,
+            height: Option<u32>,
+        }
 
-pub trait ConfigOptional: for<'de> Deserialize<'de> + DeserializeOwned + Debug {}
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptWindowConf {
+            something: Option<u32>,
+            what: Option<String>,
+            window: Option<OptWindow>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            margins: Option<OptWindow>,
+        }
 
-pub fn create_config_folder(config_path: &'static str) -> PathBuf {
-    let base = directories_next::BaseDirs::new().unwrap();
-    let home_dir = base.config_dir();
-    if !home_dir.is_dir() {
-        panic!("There is no home directory, please ensure your PC has a home directory.");
+        impl ConfigOptional for OptWindowConf {}
+
+        impl Config<OptWindowConf> for OptWindowConf {
+            fn create_from_optional(optional: OptWindowConf) -> Self {
+                optional
+            }
+        }
+
+        let config_dir = test_dir("testfolder_field_report");
+        fs::write(
+            config_dir.join("config.toml"),
+            "something = 1\n[window]\nheight = 2\n",
+        )
+        .expect("Could not write test config.");
+        let (_, report) = create_config_with_report::<OptWindowConf, OptWindowConf>(
+            &config_dir,
+            "config.toml",
+            "",
+        )
+        .expect("Could not create config.");
+        assert_eq!(
+            report.fields,
+            vec![
+                ("something".to_string(), FieldStatus::Present),
+                ("what".to_string(), FieldStatus::Missing),
+                ("window".to_string(), FieldStatus::Present),
+                ("window.width".to_string(), FieldStatus::Missing),
+                ("window.height".to_string(), FieldStatus::Present),
+                ("margins".to_string(), FieldStatus::Missing),
+            ]
+        );
+        assert_eq!(report.field_status("what"), Some(FieldStatus::Missing));
+
+        // values of the default config are not in the file
+        fs::write(config_dir.join("config.toml"), "").expect("Could not write test config.");
+        let (conf, report) = create_config_with_report::<OptWindowConf, OptWindowConf>(
+            &config_dir,
+            "config.toml",
+            "something = 1",
+        )
+        .expect("Could not create config.");
+        assert_eq!(conf.something, Some(1));
+        assert_eq!(report.field_status("something"), Some(FieldStatus::Missing));
+        assert!(report
+            .fields
+            .iter()
+            .all(|(_, status)| *status == FieldStatus::Missing));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-    let config_dir = home_dir.join(config_path);
-    if !config_dir.is_dir() {
-        fs::create_dir(&config_dir).expect("Could not create config folder");
+
+    #[test]
+    fn test_config_strict() {
+        let config_dir = test_dir("testfolder_strict");
+        let conf =
+            create_config_strict::<Conf, OptConf>(&config_dir, "config.toml", "what = \"a\"")
+                .expect("Could not create strict config.");
+        assert_eq!(conf.what, "a");
+
+        fs::write(
+            config_dir.join("config.toml"),
+            "somthing = 1\nwhat = \"b\"\n[window]\nwidth = 2\n",
+        )
+        .expect("Could not write test config.");
+        let error = create_config_strict::<Conf, OptConf>(&config_dir, "config.toml", "")
+            .expect_err("Config with unknown keys was accepted.");
+        match &error {
+            ConfigError::UnknownKeys(_, keys) => assert_eq!(keys, &["somthing", "window"]),
+            _ => panic!("Expected unknown keys, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-    config_dir
-}
 
-pub fn read_specific_css(absolute_path: &'static str) -> Result<String, ReadConfigFileError> {
-    let path = PathBuf::from_str(absolute_path);
-    if path.is_err() {
-        return Err(ReadConfigFileError {});
+    #[test]
+    fn test_config_valid_user_invalid_default() {
+        let config_dir = test_dir("testfolder_fallback_valid_user");
+        fs::write(config_dir.join("config.toml"), "something = 5")
+            .expect("Could not write test config.");
+        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", "something = ");
+        assert_eq!(conf.something, 5);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-    let path = path.unwrap();
-    if !path.is_file() {
-        return Err(ReadConfigFileError {});
+
+    #[test]
+    fn test_config_invalid_user_valid_default() {
+        let config_dir = test_dir("testfolder_fallback_valid_default");
+        fs::write(config_dir.join("config.toml"), "something = ")
+            .expect("Could not write test config.");
+        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 7");
+        assert_eq!(conf.something, 7);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
 
-    let content = fs::read_to_string(path);
-    if content.is_err() {
-        return Err(ReadConfigFileError {});
+    #[test]
+    fn test_config_invalid_user_invalid_default() {
+        let config_dir = test_dir("testfolder_fallback_invalid");
+        fs::write(config_dir.join("config.toml"), "something = ")
+            .expect("Could not write test config.");
+        let panic = std::panic::catch_unwind(|| {
+            create_config::<Conf, OptConf>(&config_dir, "config.toml", "what = ")
+        })
+        .expect_err("Invalid default config did not panic.");
+        let message = panic
+            .downcast_ref::<String>()
+            .expect("Panic message was not a string.");
+        assert!(message.contains("Invalid default config"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
 
-    Ok(content.unwrap())
-}
+    #[test]
+    fn test_config_deprecations() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptTimeoutConf {
+            #[serde(alias = "timeout")]
+            notification_timeout: Option<u32>,
+        }
 
-pub fn read_specific_config<ConcreteConfig, OptionalConfig>(
-    absolute_path: &'static str,
-) -> Result<ConcreteConfig, ReadConfigFileError>
-where
-    ConcreteConfig: Config<OptionalConfig>,
-    OptionalConfig: ConfigOptional,
-{
-    let path = PathBuf::from_str(absolute_path);
-    if path.is_err() {
-        return Err(ReadConfigFileError {});
+        impl ConfigOptional for OptTimeoutConf {}
+
+        impl Config<OptTimeoutConf> for OptTimeoutConf {
+            fn create_from_optional(optional: OptTimeoutConf) -> Self {
+                optional
+            }
+        }
+
+        let config_dir = test_dir("testfolder_deprecations");
+        fs::write(config_dir.join("config.toml"), "timeout = 5")
+            .expect("Could not write test config.");
+        let deprecated_keys = [("timeout", "notification_timeout"), ("size", "width")];
+        let (conf, report) = create_config_with_deprecations::<OptTimeoutConf, OptTimeoutConf>(
+            &config_dir,
+            "config.toml",
+            "",
+            &deprecated_keys,
+        )
+        .expect("Could not create config.");
+        assert_eq!(conf.notification_timeout, Some(5));
+        assert_eq!(report.deprecations.len(), 1);
+        assert_eq!(report.deprecations[0].old_key, "timeout");
+        assert_eq!(report.deprecations[0].new_key, "notification_timeout");
+        assert_eq!(
+            report.deprecations[0].to_string(),
+            "`timeout` is deprecated, use `notification_timeout` instead"
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-    let path = path.unwrap();
-    if !path.is_file() {
-        return Err(ReadConfigFileError {});
+
+    #[test]
+    fn test_config_backup() {
+        let config_dir = test_dir("testfolder_backup");
+        fs::write(config_dir.join("config.toml"), "something = ")
+            .expect("Could not write test config.");
+        let (conf, report) = create_config_with_backup::<OptSomething, OptSomething>(
+            &config_dir,
+            "config.toml",
+            "something = 3",
+        )
+        .expect("Could not create config.");
+        assert_eq!(conf.something, Some(3));
+        let backup = report.backup.expect("No backup was created.");
+        assert_eq!(fs::read_to_string(backup).unwrap(), "something = ");
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            "something = "
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-    Ok(create_config(&path, "", ""))
-}
 
-pub fn create_config<ConcreteConfig, OptionalConfig>(
-    config_dir: &Path,
-    config_file_name: &'static str,
-    default_config: &'static str,
-) -> ConcreteConfig
-where
-    ConcreteConfig: Config<OptionalConfig>,
-    OptionalConfig: ConfigOptional,
-{
-    let config_file = if config_file_name.is_empty() {
-        PathBuf::from(config_dir)
-    } else {
-        config_dir.join(config_file_name)
-    };
-    if !config_file.is_file() {
-        fs::File::create(&config_file).expect("Could not create config file");
+    #[test]
+    fn test_try_config_parse_location() {
+        let config_dir = test_dir("testfolder_parse_location");
+        fs::write(
+            config_dir.join("config.toml"),
+            "what = \"no\"\nsomething = \"ten\"\n",
+        )
+        .expect("Could not write test config.");
+        let error = try_create_config::<Conf, OptConf>(&config_dir, "config.toml", "")
+            .expect_err("Broken toml was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.location(), Some((2, 13)));
+                assert!(error.to_string().contains("config.toml:2:13: "));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
-    let contents = match fs::read_to_string(config_file) {
-        Ok(c) => {
-            if c.is_empty() {
-                default_config.to_string()
-            } else {
-                c
+
+    #[cfg(feature = "path-to-error")]
+    #[test]
+    fn test_parse_error_key_path() {
+        #[derive(Debug, Deserialize)]
+        struct OptWindow {
+            #[allow(dead_code)]
+            top: Option<u32>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct OptNested {
+            #[allow(dead_code)]
+            window: Option<OptWindow>,
+        }
+
+        impl ConfigOptional for OptNested {}
+
+        let error = crate::parse_config::<OptNested>(
+            Path::new("nested.toml"),
+            "[window]\ntop = \"high\"\n",
+            crate::Format::Toml.into(),
+            &mut Vec::new(),
+        )
+        .expect_err("Broken toml was parsed successfully.");
+        match error {
+            ConfigError::Parse(parse) => assert_eq!(parse.key_path(), Some("window.top")),
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_optional_config() {
+        use crate::{HasOptional, OptionalConfig};
+
+        #[derive(Debug, Deserialize, OptionalConfig)]
+        struct Window {
+            width: u32,
+        }
+
+        #[derive(Debug, Deserialize, OptionalConfig)]
+        #[oxiconf(optional = "OptDerived")]
+        struct Derived {
+            something: u32,
+            #[serde(rename = "label")]
+            what: String,
+            output: Option<String>,
+            #[oxiconf(nested)]
+            window: Window,
+        }
+
+        impl Config<OptDerived> for Derived {
+            fn create_from_optional(optional: OptDerived) -> Self {
+                let window = optional.window.unwrap_or_default();
+                Derived {
+                    something: optional.something.unwrap_or(1),
+                    what: optional.what.unwrap_or_default(),
+                    output: optional.output,
+                    window: Window {
+                        width: window.width.unwrap_or(300),
+                    },
+                }
             }
         }
-        Err(_) => default_config.to_string(),
-    };
-    let parsed_conf: OptionalConfig = match toml::from_str(&contents) {
-        Ok(d) => d,
-        Err(_) => toml::from_str(&contents).unwrap(),
-    };
-    ConcreteConfig::create_from_optional(parsed_conf)
-}
 
-pub fn create_css(config_dir: &Path, css_file: &'static str, css_content: &'static str) -> PathBuf {
-    let css_file = config_dir.join(css_file);
-    if !css_file.is_file() {
-        fs::File::create(&css_file).expect("Could not create css file.");
+        let optional: <Derived as HasOptional>::Optional =
+            toml::from_str("label = \"a\"\noutput = \"DP-1\"\n[window]\n")
+                .expect("Could not parse optional config.");
+        assert_eq!(optional.what.as_deref(), Some("a"));
+        assert_eq!(optional.something, None);
+        let conf = parse_config_str::<Derived, OptDerived>("[window]\nwidth = 5")
+            .expect("Could not parse config.");
+        assert_eq!(conf.something, 1);
+        assert_eq!(conf.what, "");
+        assert_eq!(conf.output, None);
+        assert_eq!(conf.window.width, 5);
+
+        #[derive(Debug, Default, Deserialize, OptionalConfig)]
+        struct Plain {
+            width: u32,
+            height: u32,
+        }
+
+        impl DefaultConfig for Plain {}
+
+        let conf =
+            parse_config_str::<Plain, OptPlain>("width = 2").expect("Could not parse config.");
+        assert_eq!((conf.width, conf.height), (2, 0));
+
+        let base: OptDerived =
+            toml::from_str("something = 1\noutput = \"DP-1\"\n[window]\nwidth = 2").unwrap();
+        let other: OptDerived = toml::from_str("output = \"HDMI-A-1\"\n[window]\n").unwrap();
+        let merged = base.merge(other);
+        assert_eq!(merged.something, Some(1));
+        assert_eq!(merged.output.as_deref(), Some("HDMI-A-1"));
+        assert_eq!(merged.window.and_then(|window| window.width), Some(2));
     }
-    if fs::read(&css_file)
-        .expect("Could check css file content")
-        .is_empty()
-    {
-        fs::write(&css_file, css_content).expect("Could not write default css content.");
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_config() {
+        use crate::OptionalConfig;
+
+        #[derive(Debug, Deserialize, OptionalConfig, Config)]
+        struct Margins {
+            #[oxiconf(default = 4)]
+            top: u32,
+            bottom: u32,
+        }
+
+        #[derive(Debug, Deserialize, OptionalConfig, Config)]
+        struct Derived {
+            #[oxiconf(default = 10)]
+            something: u32,
+            #[oxiconf(default = "String::from(\"pingpang\")")]
+            what: String,
+            #[oxiconf(default = "Some(2 * 3)")]
+            timeout: Option<u32>,
+            output: Option<String>,
+            #[oxiconf(nested)]
+            margins: Margins,
+        }
+
+        let conf = parse_config_str::<Derived, OptDerived>("").expect("Could not parse config.");
+        assert_eq!(conf.something, 10);
+        assert_eq!(conf.what, "pingpang");
+        assert_eq!(conf.timeout, Some(6));
+        assert_eq!(conf.output, None);
+        assert_eq!((conf.margins.top, conf.margins.bottom), (4, 0));
+
+        let conf = parse_config_str::<Derived, OptDerived>(
+            "something = 1\noutput = \"DP-1\"\n[margins]\nbottom = 2",
+        )
+        .expect("Could not parse config.");
+        assert_eq!(conf.something, 1);
+        assert_eq!(conf.output.as_deref(), Some("DP-1"));
+        assert_eq!((conf.margins.top, conf.margins.bottom), (4, 2));
+
+        #[derive(Debug, Deserialize, OptionalConfig, Config)]
+        struct Window {
+            #[oxiconf(default = 300)]
+            width: u32,
+            #[oxiconf(nested)]
+            margins: Margins,
+        }
+
+        #[derive(Debug, Deserialize, OptionalConfig, Config)]
+        struct Outer {
+            #[oxiconf(nested)]
+            window: Window,
+        }
+
+        for contents in ["", "[window]", "[window.margins]"] {
+            let conf =
+                parse_config_str::<Outer, OptOuter>(contents).expect("Could not parse config.");
+            assert_eq!(conf.window.width, 300);
+            assert_eq!(
+                (conf.window.margins.top, conf.window.margins.bottom),
+                (4, 0)
+            );
+        }
+        let conf = parse_config_str::<Outer, OptOuter>("[window.margins]\ntop = 1")
+            .expect("Could not parse config.");
+        assert_eq!((conf.window.width, conf.window.margins.top), (300, 1));
     }
-    css_file
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{fs, io::Write};
+    #[test]
+    fn test_nested_sections() {
+        #[derive(Debug, Default, Deserialize)]
+        struct OptMargins {
+            top: Option<u32>,
+        }
 
-    use serde::Deserialize;
+        impl ConfigOptional for OptMargins {}
 
-    use crate::{
-        create_config, create_config_folder, create_css, read_specific_config, read_specific_css,
-        Config, ConfigOptional,
-    };
+        #[derive(Debug, Default, Deserialize)]
+        struct OptWindow {
+            width: Option<u32>,
+            margins: Option<OptMargins>,
+        }
+
+        impl ConfigOptional for OptWindow {}
+
+        #[derive(Debug, Deserialize)]
+        struct OptNested {
+            window: Option<OptWindow>,
+        }
+
+        impl ConfigOptional for OptNested {}
+
+        #[derive(Debug, PartialEq)]
+        struct Margins {
+            top: u32,
+        }
+
+        impl Config<OptMargins> for Margins {
+            fn create_from_optional(optional: OptMargins) -> Self {
+                Margins {
+                    top: optional.top.unwrap_or(4),
+                }
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Window {
+            width: u32,
+            margins: Margins,
+        }
+
+        impl Config<OptWindow> for Window {
+            fn create_from_optional(optional: OptWindow) -> Self {
+                Window {
+                    width: optional.width.unwrap_or(300),
+                    margins: Margins::create_from_section(optional.margins),
+                }
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Nested {
+            window: Window,
+        }
+
+        impl Config<OptNested> for Nested {
+            fn create_from_optional(optional: OptNested) -> Self {
+                Nested {
+                    window: Window::create_from_section(optional.window),
+                }
+            }
+        }
+
+        let parse = |contents| {
+            parse_config_str::<Nested, OptNested>(contents).expect("Could not parse config.")
+        };
+        let defaults = Nested {
+            window: Window {
+                width: 300,
+                margins: Margins { top: 4 },
+            },
+        };
+        assert_eq!(parse(""), defaults);
+        assert_eq!(parse("[window]"), defaults);
+        assert_eq!(parse("[window.margins]"), defaults);
+        assert_eq!(
+            parse("[window.margins]\ntop = 1").window,
+            Window {
+                width: 300,
+                margins: Margins { top: 1 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_with_overrides() {
+        #[derive(Debug, Deserialize)]
+        struct OptOutput {
+            scale: Option<f64>,
+            count: Option<u32>,
+        }
+
+        crate::impl_merge!(OptOutput { scale, count });
+
+        #[derive(Debug, Deserialize)]
+        struct OptCompositor {
+            name: Option<String>,
+            output: Option<OptOutput>,
+        }
+
+        crate::impl_merge!(OptCompositor { name }, nested { output });
+
+        struct Compositor {
+            name: String,
+            scale: f64,
+            count: u32,
+        }
+
+        impl Config<OptCompositor> for Compositor {
+            fn create_from_optional(optional: OptCompositor) -> Self {
+                let output = optional.output.unwrap_or(OptOutput {
+                    scale: None,
+                    count: None,
+                });
+                Compositor {
+                    name: optional.name.unwrap_or_default(),
+                    scale: output.scale.unwrap_or(1.0),
+                    count: output.count.unwrap_or(1),
+                }
+            }
+        }
+
+        let config_dir = test_dir("testfolder_overrides");
+        let detected = || OptCompositor {
+            name: None,
+            output: Some(OptOutput {
+                scale: Some(2.0),
+                count: None,
+            }),
+        };
+        fs::write(
+            config_dir.join("config.toml"),
+            "name = \"hypr\"\n[output]\nscale = 1.5\ncount = 3",
+        )
+        .expect("Could not write test config.");
+        let conf = create_config_with_overrides::<Compositor, OptCompositor>(
+            &config_dir,
+            "config.toml",
+            "",
+            detected(),
+        );
+        assert_eq!(
+            (conf.name.as_str(), conf.scale, conf.count),
+            ("hypr", 2.0, 3)
+        );
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            "name = \"hypr\"\n[output]\nscale = 1.5\ncount = 3"
+        );
+
+        fs::write(config_dir.join("config.toml"), "name = ").expect("Could not write test config.");
+        let error = try_create_config_with_overrides::<Compositor, OptCompositor>(
+            &config_dir,
+            "config.toml",
+            "",
+            detected(),
+        )
+        .err()
+        .expect("Invalid config was parsed.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        let conf = create_config_with_overrides::<Compositor, OptCompositor>(
+            &config_dir,
+            "config.toml",
+            "name = \"sway\"",
+            detected(),
+        );
+        assert_eq!(
+            (conf.name.as_str(), conf.scale, conf.count),
+            ("sway", 2.0, 1)
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_config_without_deserialize() {
+        struct Compiled {
+            pattern: Box<dyn Fn(&str) -> bool>,
+        }
+
+        impl Config<OptConf> for Compiled {
+            fn create_from_optional(optional: OptConf) -> Self {
+                let what = optional.what.unwrap_or_default();
+                Compiled {
+                    pattern: Box::new(move |text| text.contains(&what)),
+                }
+            }
+        }
+
+        let conf = parse_config_str::<Compiled, OptConf>("what = \"ping\"")
+            .expect("Could not parse config.");
+        assert!((conf.pattern)("pingpang"));
+        assert!(!(conf.pattern)("pong"));
+    }
+
+    #[test]
+    fn test_try_config_errors() {
+        let config_dir = test_dir("testfolder_try_errors");
+        let error = try_create_config::<Conf, OptConf>(&config_dir, "config.toml", "something = ")
+            .expect_err("Broken default was parsed successfully.");
+        assert!(matches!(error, ConfigError::DefaultConfigInvalid(_)));
+
+        fs::write(config_dir.join("config.toml"), "something = ")
+            .expect("Could not write test config.");
+        let error = try_create_config::<Conf, OptConf>(&config_dir, "config.toml", "")
+            .expect_err("Broken config was parsed successfully.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+
+        let not_a_dir = config_dir.join("config.toml");
+        let error = try_create_config::<Conf, OptConf>(&not_a_dir, "config.toml", "")
+            .expect_err("Config inside of a file was created successfully.");
+        assert!(matches!(error, ConfigError::Io(_, _)));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[derive(Debug)]
+    struct RequiredConf {
+        output: String,
+    }
+
+    #[derive(Debug)]
+    struct MissingOutput;
+
+    impl std::fmt::Display for MissingOutput {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("output is required")
+        }
+    }
+
+    impl std::error::Error for MissingOutput {}
 
     #[derive(Debug, Deserialize)]
-    struct Conf {
-        something: u32,
-        what: String,
+    struct OptRequiredConf {
+        output: Option<String>,
     }
 
-    impl Config<OptConf> for Conf {
-        fn create_from_optional(optional: OptConf) -> Conf {
-            let something = if let Some(something) = optional.something {
-                something
-            } else {
-                0
-            };
-            let what = if let Some(what) = optional.what {
-                what
-            } else {
-                String::from("pingpang")
-            };
-            Conf { something, what }
+    impl ConfigOptional for OptRequiredConf {}
+
+    impl TryConfig<OptRequiredConf> for RequiredConf {
+        type Error = MissingOutput;
+
+        fn try_create_from_optional(optional: OptRequiredConf) -> Result<Self, MissingOutput> {
+            match optional.output {
+                Some(output) => Ok(RequiredConf { output }),
+                None => Err(MissingOutput),
+            }
         }
     }
 
+    #[test]
+    fn test_try_config_conversion() {
+        let config_dir = test_dir("testfolder_try_conversion");
+        let conf = try_create_config::<RequiredConf, OptRequiredConf>(
+            &config_dir,
+            "config.toml",
+            "output = \"DP-1\"",
+        )
+        .expect("Could not create config.");
+        assert_eq!(conf.output, "DP-1");
+
+        fs::write(config_dir.join("config.toml"), "# no output")
+            .expect("Could not write test config.");
+        let error = try_create_config::<RequiredConf, OptRequiredConf>(
+            &config_dir,
+            "config.toml",
+            "output = \"DP-1\"",
+        )
+        .expect_err("Config without output was created.");
+        assert!(matches!(error, ConfigError::Conversion(_, _)));
+        assert!(error.to_string().contains("output is required"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
     #[derive(Debug, Deserialize)]
-    struct OptConf {
-        something: Option<u32>,
-        what: Option<String>,
+    struct WidthConf {
+        min_width: u32,
+        max_width: u32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OptWidthConf {
+        min_width: Option<u32>,
+        max_width: Option<u32>,
+    }
+
+    impl ConfigOptional for OptWidthConf {}
+
+    impl Config<OptWidthConf> for WidthConf {
+        fn create_from_optional(optional: OptWidthConf) -> Self {
+            WidthConf {
+                min_width: optional.min_width.unwrap_or(100),
+                max_width: optional.max_width.unwrap_or(500),
+            }
+        }
+
+        fn validate(&self) -> Result<(), Vec<ValidationError>> {
+            if self.min_width > self.max_width {
+                return Err(vec![ValidationError::new(
+                    "min_width",
+                    "must not be larger than max_width",
+                )]);
+            }
+            Ok(())
+        }
     }
 
-    impl ConfigOptional for OptConf {}
+    #[test]
+    fn test_try_config_validation() {
+        let config_dir = test_dir("testfolder_validation");
+        let conf = try_create_config::<WidthConf, OptWidthConf>(&config_dir, "config.toml", "")
+            .expect("Could not create config.");
+        assert_eq!((conf.min_width, conf.max_width), (100, 500));
+
+        fs::write(config_dir.join("config.toml"), "min_width = 600")
+            .expect("Could not write test config.");
+        let error = try_create_config::<WidthConf, OptWidthConf>(&config_dir, "config.toml", "")
+            .expect_err("Invalid config was accepted.");
+        match &error {
+            ConfigError::Validation(_, errors) => {
+                assert_eq!(
+                    errors,
+                    &[ValidationError::new(
+                        "min_width",
+                        "must not be larger than max_width"
+                    )]
+                )
+            }
+            _ => panic!("Expected a validation error, got {:?}", error),
+        }
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
 
     #[test]
-    fn test_config_folder() {
-        let config_dir = create_config_folder("testfolder");
-        assert!(&config_dir.is_dir());
-        fs::remove_dir(&config_dir).expect("Could not remove testfolder again.");
+    fn test_try_config_size_limit() {
+        let config_dir = test_dir("testfolder_size_limit");
+        fs::write(
+            config_dir.join("config.toml"),
+            "what = \"a rather long value\"",
+        )
+        .expect("Could not write test config.");
+        let error =
+            try_create_config_with_limit::<Conf, OptConf>(&config_dir, "config.toml", "", 10)
+                .expect_err("Too large config was read.");
+        match error {
+            ConfigError::FileTooLarge { size, limit, .. } => {
+                assert_eq!(size, 28);
+                assert_eq!(limit, 10);
+            }
+            _ => panic!("Expected a file too large error, got {:?}", error),
+        }
+        let conf =
+            try_create_config_with_limit::<Conf, OptConf>(&config_dir, "config.toml", "", 28)
+                .expect("Could not create config.");
+        assert_eq!(conf.what, "a rather long value");
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
 
     #[test]
-    fn test_config() {
-        let config_dir = create_config_folder("testfolder2");
-        let conf = create_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 10");
-        assert_eq!(conf.something, 10);
-        assert_eq!(conf.what, String::from("pingpang"));
+    fn test_runtime_strings() {
+        let config_dir = test_dir("testfolder_runtime_strings");
+        let file_name = format!("{}.toml", "runtime");
+        let default_config = format!("what = \"{}\"", std::process::id());
+        let conf = create_config::<Conf, OptConf>(&config_dir, &file_name, &default_config);
+        assert_eq!(conf.what, std::process::id().to_string());
+
+        let css_file = String::from("runtime.css");
+        let css_content = format!(".pid-{} {{}}", std::process::id());
+        let css = create_css(&config_dir, &css_file, &css_content);
+        assert_eq!(fs::read_to_string(css).unwrap(), css_content);
         fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
 
@@ -187,6 +2551,42 @@ mod tests {
         fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
     }
 
+    #[test]
+    fn test_try_css() {
+        let config_dir = test_dir("testfolder_try_css");
+        let (css, state) = try_create_css(&config_dir, "style.css", ".a { color: red; }")
+            .expect("Could not create css file.");
+        assert_eq!(state, CssState::DefaultWritten);
+        fs::write(&css, ".b { color: blue; }").expect("Could not write css file.");
+        let (css, state) = try_create_css(&config_dir, "style.css", ".a { color: red; }")
+            .expect("Could not create css file.");
+        assert_eq!(state, CssState::Existing);
+        let read_css = fs::read_to_string(css).expect("Could not read created css file.");
+        assert_eq!(read_css, ".b { color: blue; }");
+
+        let error = try_create_css(&config_dir.join("style.css"), "style.css", "")
+            .expect_err("Css inside of a file was created.");
+        assert!(matches!(error, ConfigError::Io(_, _)));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_custom_css_runtime_path() {
+        let config_dir = test_dir("test_css_runtime_path");
+        let path = config_dir.join("style.css");
+        fs::write(&path, ".a {}").expect("Could not create test file");
+        assert_eq!(
+            read_specific_css(&path).expect("Could not read css."),
+            ".a {}"
+        );
+        let as_string: String = path.to_string_lossy().into_owned();
+        assert_eq!(
+            read_specific_css(as_string).expect("Could not read css."),
+            ".a {}"
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
     #[test]
     fn test_custom_css() {
         let mut file = fs::File::create("test.css").expect("Could not create test file");
@@ -198,6 +2598,35 @@ mod tests {
         fs::remove_file("test.css").expect("Could not remove testfolder again.");
     }
 
+    #[test]
+    fn test_custom_css_not_found() {
+        let error = read_specific_css("does_not_exist.css").expect_err("Missing file was read.");
+        assert_eq!(error.path(), Some(Path::new("does_not_exist.css")));
+        assert!(error.to_string().contains("does_not_exist.css"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_custom_css_not_readable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::write("test_unreadable.css", ".a {}").expect("Could not create test file");
+        fs::set_permissions("test_unreadable.css", fs::Permissions::from_mode(0o000))
+            .expect("Could not change permissions of test file.");
+        // privileged users can read the file regardless of its mode
+        if fs::read("test_unreadable.css").is_err() {
+            let error =
+                read_specific_css("test_unreadable.css").expect_err("Unreadable file was read.");
+            assert!(matches!(error, ConfigError::PermissionDenied(_, _)));
+            assert!(error.to_string().contains("Permission denied"));
+        }
+        fs::remove_file("test_unreadable.css").expect("Could not remove testfolder again.");
+
+        let error =
+            read_specific_css(std::env::temp_dir()).expect_err("Directory was read as css.");
+        assert!(matches!(error, ConfigError::NotAFile(_)));
+    }
+
     #[test]
     fn test_custom_config() {
         let mut file = fs::File::create("test.toml").expect("Could not create test file");
@@ -214,4 +2643,62 @@ mod tests {
         assert_eq!(read_config.what, "pingpang");
         fs::remove_file("test.toml").expect("Could not remove testfolder again.");
     }
+
+    #[test]
+    fn test_custom_config_empty() {
+        fs::write("test_empty.toml", "").expect("Could not create test file");
+        let read_config = read_specific_config::<Conf, OptConf>("test_empty.toml")
+            .expect("Could not read empty config.");
+        assert_eq!(read_config.something, 0);
+        assert_eq!(read_config.what, "pingpang");
+        fs::remove_file("test_empty.toml").expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_custom_config_not_found() {
+        let error = read_specific_config::<Conf, OptConf>("does_not_exist.toml")
+            .expect_err("Missing file was read successfully.");
+        assert!(matches!(error, ConfigError::NotFound(_)));
+        assert!(error.to_string().contains("does_not_exist.toml"));
+    }
+
+    #[test]
+    fn test_custom_config_directory() {
+        fs::create_dir_all("test_config_dir.toml").expect("Could not create test folder.");
+        let error = read_specific_config::<Conf, OptConf>("test_config_dir.toml")
+            .expect_err("Directory was read as a config file.");
+        assert!(matches!(error, ConfigError::NotAFile(_)));
+        fs::remove_dir("test_config_dir.toml").expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_custom_config_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::write("test_unreadable.toml", "something = 10").expect("Could not create test file");
+        fs::set_permissions("test_unreadable.toml", fs::Permissions::from_mode(0o000))
+            .expect("Could not change permissions of test file.");
+        // privileged users can read the file regardless of its mode
+        if fs::read("test_unreadable.toml").is_err() {
+            let error = read_specific_config::<Conf, OptConf>("test_unreadable.toml")
+                .expect_err("Unreadable file was read.");
+            assert!(matches!(error, ConfigError::PermissionDenied(_, _)));
+        }
+        fs::remove_file("test_unreadable.toml").expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_custom_config_parse_error() {
+        let mut file = fs::File::create("test_broken.toml").expect("Could not create test file");
+        file.write_all(b"something = \"ten\"")
+            .expect("Could not write to test file.");
+        let error = read_specific_config::<Conf, OptConf>("test_broken.toml")
+            .expect_err("Broken toml was parsed successfully.");
+        assert!(matches!(error, ConfigError::Parse(_)));
+        assert!(std::error::Error::source(&error).is_some());
+        assert_eq!(error.path(), Some(Path::new("test_broken.toml")));
+        assert!(error.to_string().contains("test_broken.toml"));
+        fs::remove_file("test_broken.toml").expect("Could not remove testfolder again.");
+    }
 }
@@ -0,0 +1,160 @@
+use std::{path::Path, sync::Arc};
+
+use arc_swap::ArcSwap;
+
+use crate::{try_create_config, ConfigError, ConfigOptional, TryConfig};
+
+/// A config that can be replaced while other threads are reading it.
+/// Clones share the same config.
+#[derive(Debug)]
+pub struct LiveConfig<ConcreteConfig> {
+    current: Arc<ArcSwap<ConcreteConfig>>,
+}
+
+impl<ConcreteConfig> Clone for LiveConfig<ConcreteConfig> {
+    fn clone(&self) -> Self {
+        LiveConfig {
+            current: Arc::clone(&self.current),
+        }
+    }
+}
+
+impl<ConcreteConfig> LiveConfig<ConcreteConfig> {
+    pub fn new(config: ConcreteConfig) -> Self {
+        LiveConfig {
+            current: Arc::new(ArcSwap::from_pointee(config)),
+        }
+    }
+
+    /// A snapshot of the current config, unaffected by later replacements.
+    pub fn load(&self) -> Arc<ConcreteConfig> {
+        self.current.load_full()
+    }
+
+    pub fn replace(&self, config: ConcreteConfig) {
+        self.current.store(Arc::new(config));
+    }
+
+    /// Loads the config file at `path` and replaces the current config only if that succeeds.
+    pub fn reload_from<OptionalConfig>(
+        &self,
+        path: &Path,
+        default_config: &str,
+    ) -> Result<(), ConfigError>
+    where
+        ConcreteConfig: TryConfig<OptionalConfig>,
+        OptionalConfig: ConfigOptional,
+    {
+        let config = try_create_config::<ConcreteConfig, OptionalConfig>(path, "", default_config)?;
+        self.replace(config);
+        Ok(())
+    }
+
+    /// Replaces the config with a modified copy until the returned guard is dropped,
+    /// e.g. for previews: `let _preview = live.override_with(|config| config.font_size = 20);`.
+    /// Nothing is written to disk. See [`OverrideGuard`].
+    pub fn override_with(
+        &self,
+        modify: impl FnOnce(&mut ConcreteConfig),
+    ) -> OverrideGuard<ConcreteConfig>
+    where
+        ConcreteConfig: Clone,
+    {
+        let previous = self.current.load_full();
+        let mut modified = ConcreteConfig::clone(&previous);
+        modify(&mut modified);
+        self.replace(modified);
+        OverrideGuard {
+            live: self.clone(),
+            previous: Some(previous),
+        }
+    }
+}
+
+/// Restores the config that was current before [`LiveConfig::override_with`] when dropped,
+/// also while unwinding from a panic. Nested guards have to be dropped in reverse order,
+/// a config replaced while the guard is alive is overwritten as well.
+#[derive(Debug)]
+#[must_use = "the override is reverted as soon as the guard is dropped"]
+pub struct OverrideGuard<ConcreteConfig> {
+    live: LiveConfig<ConcreteConfig>,
+    previous: Option<Arc<ConcreteConfig>>,
+}
+
+impl<ConcreteConfig> OverrideGuard<ConcreteConfig> {
+    /// Keeps the modified config instead of restoring the previous one, e.g. once a preview
+    /// is accepted.
+    pub fn keep(mut self) {
+        self.previous = None;
+    }
+}
+
+impl<ConcreteConfig> Drop for OverrideGuard<ConcreteConfig> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            self.live.current.store(previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        test_support::{test_dir, Conf, OptConf},
+        LiveConfig,
+    };
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn test_live_config() {
+        let live = LiveConfig::new(Conf { something: 1 });
+        assert_send_sync(&live);
+        let snapshot = live.load();
+        let worker = live.clone();
+        std::thread::spawn(move || worker.replace(Conf { something: 2 }))
+            .join()
+            .unwrap();
+        assert_eq!(snapshot.something, 1);
+        assert_eq!(live.load().something, 2);
+
+        let config_dir = test_dir("test_live");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "something = 3").expect("Could not write test config.");
+        live.reload_from::<OptConf>(&path, "")
+            .expect("Could not reload config.");
+        assert_eq!(live.load().something, 3);
+        fs::write(&path, "something = ").expect("Could not write test config.");
+        live.reload_from::<OptConf>(&path, "")
+            .expect_err("Broken config was loaded.");
+        assert_eq!(live.load().something, 3);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_override_with() {
+        let live = LiveConfig::new(Conf { something: 1 });
+        let outer = live.override_with(|config| config.something = 2);
+        assert_eq!(live.load().something, 2);
+        let inner = live.override_with(|config| config.something *= 10);
+        assert_eq!(live.load().something, 20);
+        drop(inner);
+        assert_eq!(live.load().something, 2);
+        drop(outer);
+        assert_eq!(live.load().something, 1);
+
+        let worker = live.clone();
+        let result = std::panic::catch_unwind(move || {
+            let _preview = worker.override_with(|config| config.something = 3);
+            assert_eq!(worker.load().something, 3);
+            panic!("preview failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(live.load().something, 1);
+
+        live.override_with(|config| config.something = 4).keep();
+        assert_eq!(live.load().something, 4);
+    }
+}
@@ -0,0 +1,1658 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    apply_overrides, config_file_path, config_home, create_from_parsed,
+    env::{self, Overridden},
+    find_project_config_in,
+    format::{self, CustomFormat, FormatRef},
+    include,
+    interpolate::interpolate_overridden,
+    load_optional, parse_default_config, presence,
+    profile::take_profile,
+    read_file, try_create_config_folder_in,
+    value::merge_with,
+    warn_unknown_keys, ConfigError, ConfigFormat, ConfigLoadReport, ConfigOptional, Format,
+    PathSource, RawConfig, TryConfig, UnsetVar, ValueSource, DEFAULT_SIZE_LIMIT, PROFILE_SECTION,
+};
+
+/// Builder for loading config files from a folder in the user's config directory.
+/// Cloning a loader is cheap, which allows loading several files from the same folder:
+/// `loader.clone().file("other.toml").load::<Conf, OptConf>()`.
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    base_dir: Option<PathBuf>,
+    app_dir: PathBuf,
+    file_name: String,
+    default_config: String,
+    format: Option<Format>,
+    custom_format: Option<CustomFormat>,
+    registered_formats: Vec<CustomFormat>,
+    strict_format: bool,
+    strict: bool,
+    create_missing: bool,
+    includes: bool,
+    env_prefix: Option<String>,
+    path_env: Option<String>,
+    layers: Vec<Layer>,
+    xdg_config_dirs: Option<Vec<PathBuf>>,
+    system_file: Option<PathBuf>,
+    overrides: Vec<String>,
+    os_overrides: bool,
+    host_overrides: bool,
+    hostname: Option<String>,
+    project_start: Option<PathBuf>,
+    profile: Option<String>,
+    interpolation: Option<UnsetVar>,
+    array_merges: Vec<(String, ArrayMerge)>,
+    size_limit: u64,
+}
+
+/// How an array is combined with the array of a more important source,
+/// see [`ConfigLoader::array_merge`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArrayMerge {
+    /// The more important array replaces the other one.
+    #[default]
+    Replace,
+    /// The entries of the more important array are added after the other ones.
+    Append,
+    /// Entries are tables identified by this key, e.g. `[[outputs]]` by `name`. Entries with
+    /// the same key are merged like tables, new ones are appended. Fails with
+    /// [`ConfigError::MissingMergeKey`] if an entry has no such key.
+    MergeByKey(String),
+}
+
+/// A source of config values for [`ConfigLoader::layer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Layer {
+    /// Toml compiled into the program, e.g. the defaults of every field.
+    Toml(String),
+    /// A file like `/etc/myapp/config.toml`, skipped if it does not exist.
+    File(PathBuf),
+    /// The file of the loader in every folder of `XDG_CONFIG_DIRS`, e.g. the vendor defaults in
+    /// `/etc/xdg/myapp/config.toml`. Earlier folders win, only consulted on Linux unless
+    /// the folders are set with [`ConfigLoader::xdg_config_dirs`].
+    XdgConfigDirs,
+    /// The file of the loader, e.g. `~/.config/myapp/config.toml`, skipped if it does not exist.
+    UserFile,
+    /// The file found by [`crate::find_project_config_in`] from the current folder or the one
+    /// of [`ConfigLoader::project_start`], e.g. `.myapp.toml` of the project, skipped if there
+    /// is none.
+    Project {
+        file_name: String,
+        stop_at_repository: bool,
+    },
+    /// The environment variables starting with `<prefix>_`, see [`ConfigLoader::env_prefix`].
+    Env(String),
+}
+
+struct LoadedConfig<OptionalConfig> {
+    path: PathBuf,
+    parsed_conf: OptionalConfig,
+    /// Empty if the default config was used.
+    contents: String,
+}
+
+impl ConfigLoader {
+    /// Loads `config.toml` from `app_dir`, creating both if missing and without a default config.
+    pub fn new(app_dir: impl AsRef<Path>) -> Self {
+        ConfigLoader {
+            base_dir: None,
+            app_dir: app_dir.as_ref().to_path_buf(),
+            file_name: String::from("config.toml"),
+            default_config: String::new(),
+            format: None,
+            custom_format: None,
+            registered_formats: Vec::new(),
+            strict_format: false,
+            strict: false,
+            create_missing: true,
+            includes: false,
+            env_prefix: None,
+            path_env: None,
+            layers: Vec::new(),
+            xdg_config_dirs: None,
+            system_file: None,
+            overrides: Vec::new(),
+            os_overrides: false,
+            host_overrides: false,
+            hostname: None,
+            project_start: None,
+            profile: None,
+            interpolation: None,
+            array_merges: Vec::new(),
+            size_limit: DEFAULT_SIZE_LIMIT,
+        }
+    }
+
+    /// Resolve `app_dir` relative to `base_dir` instead of the user's config directory,
+    /// e.g. a temporary folder in tests or the folder of a portable install.
+    pub fn base_dir(mut self, base_dir: impl AsRef<Path>) -> Self {
+        self.base_dir = Some(base_dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn file(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = file_name.into();
+        self
+    }
+
+    /// Used when the file is missing or empty.
+    pub fn default_toml(mut self, default_config: impl Into<String>) -> Self {
+        self.default_config = default_config.into();
+        self
+    }
+
+    /// The format of the file and the default config.
+    /// Detected from the file extension if not set, see [`Format::detect`].
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self.custom_format = None;
+        self
+    }
+
+    /// Like [`ConfigLoader::format`], for a format implemented outside of OxiLib.
+    pub fn custom_format(mut self, format: impl ConfigFormat + Send + Sync + 'static) -> Self {
+        self.custom_format = Some(CustomFormat::new(format));
+        self.format = None;
+        self
+    }
+
+    /// Parse files with one of the extensions of `format` with it when no format is set.
+    /// Registered formats take precedence over the built-in ones.
+    pub fn register_format(mut self, format: impl ConfigFormat + Send + Sync + 'static) -> Self {
+        self.registered_formats.push(CustomFormat::new(format));
+        self
+    }
+
+    /// Fail with [`ConfigError::UnknownFormat`] instead of falling back to toml
+    /// if no format is set and the file extension is unknown.
+    pub fn strict_format(mut self, strict_format: bool) -> Self {
+        self.strict_format = strict_format;
+        self
+    }
+
+    /// Fail with [`ConfigError::UnknownKeys`] on keys the optional config does not know about.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether a missing folder and file are created. Without this, a missing file
+    /// falls back to the default config without touching the filesystem.
+    pub fn create_missing(mut self, create_missing: bool) -> Self {
+        self.create_missing = create_missing;
+        self
+    }
+
+    /// Merge the files listed in the `include` key of the config file, e.g.
+    /// `include = ["keybinds.toml"]`. Paths are relative to the including file,
+    /// which wins on conflicts. Included files may include further files.
+    pub fn includes(mut self, includes: bool) -> Self {
+        self.includes = includes;
+        self
+    }
+
+    /// Override fields with the environment variables starting with `<prefix>_`, a double
+    /// underscore separates nested keys: `MYAPP_WINDOW__WIDTH=3` sets `window.width`.
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Load the file the environment variable `name` points to instead, if it is set.
+    /// That file is never created, a missing one is [`ConfigError::NotFound`].
+    pub fn path_env(mut self, name: impl Into<String>) -> Self {
+        self.path_env = Some(name.into());
+        self
+    }
+
+    /// A file like `/etc/myapp/config.toml` that is used if the user has no config file
+    /// and merged below the user's file otherwise. It is never created or written,
+    /// if it may not be read, it is ignored with a warning.
+    pub fn system_file(mut self, system_file: impl AsRef<Path>) -> Self {
+        self.system_file = Some(system_file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets `path=value` pairs like `window.width=300` after everything else was loaded,
+    /// e.g. from `-o` arguments. See [`apply_overrides`].
+    pub fn overrides<I, S>(mut self, overrides: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.overrides.extend(overrides.into_iter().map(Into::into));
+        self
+    }
+
+    /// Merge a file with the operating system before the extension, e.g. `config.linux.toml`
+    /// or `config.macos.toml`, over the config file if it exists.
+    /// The system is the one of [`std::env::consts::OS`].
+    ///
+    /// The config file is overridden in this order, later ones win:
+    /// the file of the system, the file of the host, the table of the [profile](Self::profile),
+    /// the [environment variables](Self::env_prefix) and the [overrides](Self::overrides).
+    pub fn os_overrides(mut self, os_overrides: bool) -> Self {
+        self.os_overrides = os_overrides;
+        self
+    }
+
+    /// Merge a file with the hostname before the extension, e.g. `config.desktop.toml`,
+    /// over the config file and the one of [`ConfigLoader::os_overrides`] if it exists. The hostname is taken from the environment variable
+    /// `OXILIB_HOSTNAME` if it is set, see [`ConfigLoader::hostname`].
+    pub fn host_overrides(mut self, host_overrides: bool) -> Self {
+        self.host_overrides = host_overrides;
+        self
+    }
+
+    /// The hostname of [`ConfigLoader::host_overrides`] instead of the one of this machine.
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// The folders of [`Layer::XdgConfigDirs`] by importance instead of `XDG_CONFIG_DIRS`.
+    pub fn xdg_config_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.xdg_config_dirs = Some(dirs);
+        self
+    }
+
+    /// Search the file of [`Layer::Project`] from `dir` instead of the current folder.
+    pub fn project_start(mut self, dir: impl AsRef<Path>) -> Self {
+        self.project_start = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Merge the table `[profile.<profile>]` over the loaded files, see
+    /// [`crate::create_config_with_profile`]. The profiles may be defined in any of the files.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Expand environment variables in the strings of the files, see [`crate::interpolate_env`].
+    /// Values of [`ConfigLoader::env_prefix`] and the overrides are not expanded.
+    pub fn interpolate_env(mut self, unset: UnsetVar) -> Self {
+        self.interpolation = Some(unset);
+        self
+    }
+
+    /// Combines the arrays at the dotted `path`, e.g. `bar.blocks`, with `strategy` when files,
+    /// layers or overrides are merged. Arrays of other paths are replaced.
+    pub fn array_merge(mut self, path: impl Into<String>, strategy: ArrayMerge) -> Self {
+        self.array_merges.push((path.into(), strategy));
+        self
+    }
+
+    /// Adds a layer, later layers override the values of earlier ones and nested tables are
+    /// merged key by key. Once a layer is added, only the layers are loaded and nothing is created.
+    /// A layer that exists but can't be parsed fails the whole load.
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn size_limit(mut self, size_limit: u64) -> Self {
+        self.size_limit = size_limit;
+        self
+    }
+
+    /// The folder the files are loaded from, created if `create_missing` is set.
+    pub fn config_dir(&self) -> Result<PathBuf, ConfigError> {
+        self.resolve_dir(self.create_missing)
+    }
+
+    fn resolve_dir(&self, create: bool) -> Result<PathBuf, ConfigError> {
+        let config_home = match &self.base_dir {
+            Some(base_dir) => base_dir.clone(),
+            None => config_home()?,
+        };
+        if create {
+            try_create_config_folder_in(&config_home, &self.app_dir)
+        } else {
+            Ok(config_home.join(&self.app_dir))
+        }
+    }
+
+    /// The config file, taken from the variable set with [`ConfigLoader::path_env`] if it is set.
+    pub fn file_path(&self) -> Result<PathBuf, ConfigError> {
+        self.resolve_file().map(|(config_file, _)| config_file)
+    }
+
+    fn resolve_file(&self) -> Result<(PathBuf, PathSource), ConfigError> {
+        if let Some(name) = &self.path_env {
+            if let Some(path) = std::env::var_os(name).filter(|path| !path.is_empty()) {
+                return Ok((PathBuf::from(path), PathSource::EnvVar(name.clone())));
+            }
+        }
+        // layers are only read, the folder of the user file is not created for them
+        let config_dir = self.resolve_dir(self.create_missing && self.layers.is_empty())?;
+        let config_file = config_file_path(&config_dir, &self.file_name);
+        Ok((config_file, PathSource::ConfigDir))
+    }
+
+    pub fn load<ConcreteConfig, OptionalConfig>(&self) -> Result<ConcreteConfig, ConfigError>
+    where
+        ConcreteConfig: TryConfig<OptionalConfig>,
+        OptionalConfig: ConfigOptional,
+    {
+        self.load_with_raw().map(|(config, _)| config)
+    }
+
+    /// Like [`ConfigLoader::load`], additionally returning the document as written in the file.
+    pub fn load_with_raw<ConcreteConfig, OptionalConfig>(
+        &self,
+    ) -> Result<(ConcreteConfig, RawConfig), ConfigError>
+    where
+        ConcreteConfig: TryConfig<OptionalConfig>,
+        OptionalConfig: ConfigOptional,
+    {
+        let mut report = ConfigLoadReport::default();
+        let loaded = self.load_optional::<OptionalConfig>(&mut report)?;
+        let format = self.format_for(&loaded.path)?;
+        let raw = RawConfig::parse(&loaded.path, &loaded.contents, format)?;
+        let config = create_from_parsed(&loaded.path, loaded.parsed_conf)?;
+        Ok((config, raw))
+    }
+
+    /// Like [`ConfigLoader::load`], additionally returning what was found in the file
+    /// and where its path came from.
+    pub fn load_with_report<ConcreteConfig, OptionalConfig>(
+        &self,
+    ) -> Result<(ConcreteConfig, ConfigLoadReport), ConfigError>
+    where
+        ConcreteConfig: TryConfig<OptionalConfig>,
+        OptionalConfig: ConfigOptional + Serialize,
+    {
+        let mut report = ConfigLoadReport::default();
+        let loaded = self.load_optional::<OptionalConfig>(&mut report)?;
+        // layers are merged from several files, their fields are taken as they are
+        let from_file = !self.layers.is_empty() || !loaded.contents.is_empty();
+        report.fields = presence::file_field_statuses(&loaded.parsed_conf, from_file);
+        let config = create_from_parsed(&loaded.path, loaded.parsed_conf)?;
+        Ok((config, report))
+    }
+
+    fn load_optional<OptionalConfig>(
+        &self,
+        report: &mut ConfigLoadReport,
+    ) -> Result<LoadedConfig<OptionalConfig>, ConfigError>
+    where
+        OptionalConfig: ConfigOptional,
+    {
+        if !self.layers.is_empty() {
+            return self.load_layers(report);
+        }
+        let (mut config_file, path_source) = self.resolve_file()?;
+        // an explicit path is never created, a typo should not result in a new file
+        let create_missing = match path_source {
+            PathSource::ConfigDir => self.create_missing,
+            _ if !config_file.exists() => return Err(ConfigError::NotFound(config_file)),
+            _ => false,
+        };
+        report.path_source = path_source;
+        let system = match &self.system_file {
+            Some(system_file) => self.read_system_file(system_file)?,
+            None => None,
+        };
+        let mut unknown_keys = Vec::new();
+        let (parsed_conf, contents) = if self.includes
+            || self.env_prefix.is_some()
+            || !self.overrides.is_empty()
+            || self.os_overrides
+            || self.host_overrides
+            || self.profile.is_some()
+            || self.interpolation.is_some()
+            || system.is_some()
+        {
+            let variant_files = self.read_variant_files(&config_file, report)?;
+            let (mut document, contents) = match system {
+                Some((system_file, document, contents)) if !config_file.exists() => {
+                    log_debug!("Using the system config {}", system_file.display());
+                    let source = ValueSource::SystemFile(system_file.clone());
+                    report.provenance.record_table(&document, &source);
+                    report.path_source = PathSource::SystemFile(system_file.clone());
+                    report.consulted_files.push(system_file.clone());
+                    config_file = system_file;
+                    (document, contents)
+                }
+                system => {
+                    let loaded = load_optional::<toml::Table>(
+                        &config_file,
+                        &self.default_config,
+                        self.format_for(&config_file)?,
+                        self.size_limit,
+                        create_missing,
+                        &mut Vec::new(),
+                    )?;
+                    report.created = loaded.created;
+                    report.file_missing = loaded.missing;
+                    let document = self.resolve_includes(&config_file, loaded.parsed_conf)?;
+                    let source = if loaded.contents.is_empty() {
+                        ValueSource::Default
+                    } else {
+                        ValueSource::UserFile(config_file.clone())
+                    };
+                    let document = match system {
+                        Some((system_file, mut system_document, _)) => {
+                            let system_source = ValueSource::SystemFile(system_file.clone());
+                            report
+                                .provenance
+                                .record_table(&system_document, &system_source);
+                            report.provenance.record_table(&document, &source);
+                            report.consulted_files.push(system_file);
+                            report.consulted_files.push(config_file.clone());
+                            self.merge_tables(&mut system_document, document)?;
+                            system_document
+                        }
+                        None => {
+                            report.provenance.record_table(&document, &source);
+                            document
+                        }
+                    };
+                    (document, loaded.contents)
+                }
+            };
+            for (path, variant) in variant_files {
+                report
+                    .provenance
+                    .record_table(&variant, &ValueSource::File(path));
+                self.merge_tables(&mut document, variant)?;
+            }
+            let mut merged = Overridden::from(toml::Value::Table(document));
+            self.merge_overrides(&config_file, &mut merged, report)?;
+            let parsed_conf = format::from_value(&config_file, merged, &mut unknown_keys)?;
+            warn_unknown_keys(&config_file, &unknown_keys);
+            (parsed_conf, contents)
+        } else {
+            let loaded = load_optional(
+                &config_file,
+                &self.default_config,
+                self.format_for(&config_file)?,
+                self.size_limit,
+                create_missing,
+                &mut unknown_keys,
+            )?;
+            report.created = loaded.created;
+            report.file_missing = loaded.missing;
+            (loaded.parsed_conf, loaded.contents)
+        };
+        if self.strict && !unknown_keys.is_empty() {
+            return Err(ConfigError::UnknownKeys(config_file, unknown_keys));
+        }
+        report.unknown_keys = unknown_keys;
+        Ok(LoadedConfig {
+            path: config_file,
+            parsed_conf,
+            contents,
+        })
+    }
+
+    /// `None` if the system file is missing or may not be read, it never fails the load.
+    fn read_system_file(
+        &self,
+        system_file: &Path,
+    ) -> Result<Option<(PathBuf, toml::Table, String)>, ConfigError> {
+        let loaded = load_optional::<toml::Table>(
+            system_file,
+            &self.default_config,
+            self.format_for(system_file)?,
+            self.size_limit,
+            false,
+            &mut Vec::new(),
+        );
+        match loaded {
+            Ok(loaded) if loaded.missing => Ok(None),
+            Ok(loaded) => {
+                let document = self.resolve_includes(system_file, loaded.parsed_conf)?;
+                Ok(Some((system_file.to_path_buf(), document, loaded.contents)))
+            }
+            Err(error @ ConfigError::PermissionDenied(..)) => {
+                log_warn!("{}, ignoring the system config", error);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// The files of [`ConfigLoader::os_overrides`] and [`ConfigLoader::host_overrides`]
+    /// that exist, in the order they are merged.
+    fn read_variant_files(
+        &self,
+        config_file: &Path,
+        report: &mut ConfigLoadReport,
+    ) -> Result<Vec<(PathBuf, toml::Table)>, ConfigError> {
+        let mut documents = Vec::new();
+        if self.os_overrides {
+            if let Some(os_file) = self.read_variant_file(config_file, std::env::consts::OS)? {
+                report.os_file = Some(os_file.0.clone());
+                documents.push(os_file);
+            }
+        }
+        if self.host_overrides {
+            let hostname = match self
+                .hostname
+                .clone()
+                .or_else(|| hostname(std::env::var("OXILIB_HOSTNAME").ok()))
+            {
+                Some(hostname) => Some(hostname),
+                None => {
+                    log_warn!("Could not detect the hostname, ignoring host overrides");
+                    None
+                }
+            };
+            if let Some(hostname) = hostname {
+                if let Some(host_file) = self.read_variant_file(config_file, &hostname)? {
+                    report.host_file = Some(host_file.0.clone());
+                    documents.push(host_file);
+                }
+            }
+        }
+        Ok(documents)
+    }
+
+    /// `config.<variant>.toml` next to `config_file`, `None` if it is missing.
+    fn read_variant_file(
+        &self,
+        config_file: &Path,
+        variant: &str,
+    ) -> Result<Option<(PathBuf, toml::Table)>, ConfigError> {
+        let Some(variant_file) = variant_file_path(config_file, variant) else {
+            log_warn!("Invalid file name variant `{}`, ignoring it", variant);
+            return Ok(None);
+        };
+        if !variant_file.exists() {
+            return Ok(None);
+        }
+        log_debug!("Merging {}", variant_file.display());
+        let contents = read_file(&variant_file, self.size_limit)?;
+        let document =
+            self.format_for(&variant_file)?
+                .parse(&variant_file, &contents, &mut Vec::new())?;
+        let document = self.resolve_includes(&variant_file, document)?;
+        Ok(Some((variant_file, document)))
+    }
+
+    /// The profile, the interpolation, the environment variables of [`ConfigLoader::env_prefix`]
+    /// and the overrides on top of them.
+    fn merge_overrides(
+        &self,
+        config_file: &Path,
+        merged: &mut Overridden,
+        report: &mut ConfigLoadReport,
+    ) -> Result<(), ConfigError> {
+        if let Some(profile) = &self.profile {
+            report.provenance.remove(PROFILE_SECTION);
+            if let Some(selected) = take_profile(config_file, merged, Some(profile))? {
+                let source = ValueSource::Profile(profile.clone());
+                report.provenance.record(&selected, &source);
+                self.merge_into(merged, selected)?;
+            }
+        }
+        if let Some(unset) = self.interpolation {
+            interpolate_overridden(merged, unset)?;
+        }
+        if let Some(prefix) = &self.env_prefix {
+            self.merge_env(merged, prefix, report)?;
+        }
+        if !self.overrides.is_empty() {
+            let mut overrides = toml::Value::Table(toml::Table::new());
+            apply_overrides(&mut overrides, &self.overrides)?;
+            let overrides = Overridden::from(overrides);
+            report.provenance.record(&overrides, &ValueSource::Override);
+            self.merge_into(merged, overrides)?;
+        }
+        Ok(())
+    }
+
+    /// The environment variables starting with `<prefix>_` over `merged`.
+    fn merge_env(
+        &self,
+        merged: &mut Overridden,
+        prefix: &str,
+        report: &mut ConfigLoadReport,
+    ) -> Result<(), ConfigError> {
+        let vars = Overridden::new(toml::Table::new(), prefix, env::vars());
+        report.provenance.record(&vars, &ValueSource::Override);
+        self.merge_into(merged, vars)
+    }
+
+    /// Merges `other` over `merged` with the strategies of [`ConfigLoader::array_merge`].
+    fn merge_into(&self, merged: &mut Overridden, other: Overridden) -> Result<(), ConfigError> {
+        merged.merge_with(other, &self.array_merges, &mut Vec::new())
+    }
+
+    fn merge_tables(&self, base: &mut toml::Table, other: toml::Table) -> Result<(), ConfigError> {
+        merge_with(base, other, &self.array_merges, &mut Vec::new())
+    }
+
+    fn resolve_includes(
+        &self,
+        path: &Path,
+        document: toml::Table,
+    ) -> Result<toml::Table, ConfigError> {
+        if !self.includes {
+            return Ok(document);
+        }
+        include::resolve_includes(path, document, self.size_limit, &|path| {
+            self.format_for(path)
+        })
+    }
+
+    fn load_layers<OptionalConfig>(
+        &self,
+        report: &mut ConfigLoadReport,
+    ) -> Result<LoadedConfig<OptionalConfig>, ConfigError>
+    where
+        OptionalConfig: ConfigOptional,
+    {
+        let (config_file, path_source) = self.resolve_file()?;
+        if path_source != PathSource::ConfigDir && !config_file.exists() {
+            return Err(ConfigError::NotFound(config_file));
+        }
+        report.path_source = path_source;
+        report.file_missing = !config_file.exists();
+        let mut merged = Overridden::Table(Vec::new());
+        let mut contents = String::new();
+        for layer in &self.layers {
+            match layer {
+                Layer::Toml(defaults) => {
+                    let document: toml::Table = parse_default_config(
+                        &config_file,
+                        defaults,
+                        Format::Toml.into(),
+                        &mut Vec::new(),
+                    )?;
+                    report
+                        .provenance
+                        .record_table(&document, &ValueSource::Default);
+                    self.merge_into(&mut merged, Overridden::from(toml::Value::Table(document)))?;
+                }
+                Layer::File(path) => {
+                    self.merge_layer(&mut merged, path, ValueSource::File(path.clone()), report)?;
+                }
+                Layer::XdgConfigDirs => {
+                    let dirs = match &self.xdg_config_dirs {
+                        Some(dirs) => dirs.clone(),
+                        None => xdg_config_dirs(),
+                    };
+                    // the first folder is the most important one and merged last
+                    for dir in dirs.iter().rev() {
+                        let path = config_file_path(&dir.join(&self.app_dir), &self.file_name);
+                        let source = ValueSource::SystemFile(path.clone());
+                        self.merge_layer(&mut merged, &path, source, report)?;
+                    }
+                }
+                Layer::UserFile => {
+                    let source = ValueSource::UserFile(config_file.clone());
+                    if let Some(user_contents) =
+                        self.merge_layer(&mut merged, &config_file, source, report)?
+                    {
+                        contents = user_contents;
+                    }
+                    for (path, document) in self.read_variant_files(&config_file, report)? {
+                        let source = ValueSource::File(path.clone());
+                        report.provenance.record_table(&document, &source);
+                        self.merge_into(
+                            &mut merged,
+                            Overridden::from(toml::Value::Table(document)),
+                        )?;
+                        report.consulted_files.push(path);
+                    }
+                }
+                Layer::Project {
+                    file_name,
+                    stop_at_repository,
+                } => {
+                    let start = match &self.project_start {
+                        Some(start) => Some(start.clone()),
+                        None => std::env::current_dir().ok(),
+                    };
+                    let project_file = start.and_then(|start| {
+                        find_project_config_in(&start, file_name, *stop_at_repository)
+                    });
+                    if let Some(project_file) = project_file {
+                        let source = ValueSource::File(project_file.clone());
+                        self.merge_layer(&mut merged, &project_file, source, report)?;
+                        report.project_file = Some(project_file);
+                    }
+                }
+                Layer::Env(prefix) => self.merge_env(&mut merged, prefix, report)?,
+            }
+        }
+        self.merge_overrides(&config_file, &mut merged, report)?;
+        let mut unknown_keys = Vec::new();
+        let parsed_conf = format::from_value(&config_file, merged, &mut unknown_keys)?;
+        warn_unknown_keys(&config_file, &unknown_keys);
+        if self.strict && !unknown_keys.is_empty() {
+            return Err(ConfigError::UnknownKeys(config_file, unknown_keys));
+        }
+        report.unknown_keys = unknown_keys;
+        Ok(LoadedConfig {
+            path: config_file,
+            parsed_conf,
+            contents,
+        })
+    }
+
+    /// Merges the file at `path` into `merged`, returns its contents or `None` if it does not exist.
+    fn merge_layer(
+        &self,
+        merged: &mut Overridden,
+        path: &Path,
+        source: ValueSource,
+        report: &mut ConfigLoadReport,
+    ) -> Result<Option<String>, ConfigError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = read_file(path, self.size_limit)?;
+        let document = self
+            .format_for(path)?
+            .parse(path, &contents, &mut Vec::new())?;
+        let document = self.resolve_includes(path, document)?;
+        report.provenance.record_table(&document, &source);
+        self.merge_into(merged, Overridden::from(toml::Value::Table(document)))?;
+        report.consulted_files.push(path.to_path_buf());
+        Ok(Some(contents))
+    }
+
+    fn format_for(&self, config_file: &Path) -> Result<FormatRef<'_>, ConfigError> {
+        if let Some(custom) = &self.custom_format {
+            return Ok(FormatRef::Custom(custom));
+        }
+        if let Some(format) = self.format {
+            return Ok(format.into());
+        }
+        let registered = self
+            .registered_formats
+            .iter()
+            .rev()
+            .find(|custom| custom.matches(config_file));
+        match (registered, Format::from_path(config_file)) {
+            (Some(custom), _) => Ok(FormatRef::Custom(custom)),
+            (None, Some(format)) => Ok(format.into()),
+            (None, None) if self.strict_format => {
+                Err(ConfigError::UnknownFormat(config_file.to_path_buf()))
+            }
+            (None, None) => Ok(Format::Toml.into()),
+        }
+    }
+}
+
+/// `configured`, the value of `OXILIB_HOSTNAME`, or the hostname of this machine.
+fn hostname(configured: Option<String>) -> Option<String> {
+    if let Some(hostname) = configured.filter(|hostname| !hostname.is_empty()) {
+        return Some(hostname);
+    }
+    for path in ["/proc/sys/kernel/hostname", "/etc/hostname"] {
+        if let Ok(hostname) = std::fs::read_to_string(path) {
+            let hostname = hostname.trim();
+            if !hostname.is_empty() {
+                return Some(hostname.to_string());
+            }
+        }
+    }
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|hostname| !hostname.is_empty())
+}
+
+/// `config.toml` becomes `config.<variant>.toml`, `None` if the variant is no valid file name.
+fn variant_file_path(config_file: &Path, variant: &str) -> Option<PathBuf> {
+    if variant.is_empty() || variant.contains(['/', '\\']) || variant.starts_with('.') {
+        return None;
+    }
+    let stem = config_file.file_stem()?.to_string_lossy();
+    let file_name = match config_file.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, variant, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, variant),
+    };
+    Some(config_file.with_file_name(file_name))
+}
+
+/// The folders of `XDG_CONFIG_DIRS` by importance, `/etc/xdg` if it is not set.
+#[cfg(target_os = "linux")]
+fn xdg_config_dirs() -> Vec<PathBuf> {
+    parse_xdg_config_dirs(std::env::var_os("XDG_CONFIG_DIRS"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn xdg_config_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Relative paths are ignored, as the XDG base directory specification demands.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_xdg_config_dirs(dirs: Option<std::ffi::OsString>) -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = match dirs {
+        Some(dirs) => std::env::split_paths(&dirs)
+            .filter(|dir| dir.is_absolute())
+            .collect(),
+        None => Vec::new(),
+    };
+    if dirs.is_empty() {
+        vec![PathBuf::from("/etc/xdg")]
+    } else {
+        dirs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, fs, path::PathBuf};
+
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use crate::{
+        test_support::{test_dir, Conf, OptConf},
+        ArrayMerge, Config, ConfigError, ConfigFormat, ConfigLoader, ConfigOptional, FieldStatus,
+        Format, FormatError, Layer, PathSource, UnsetVar, ValueSource, MAX_INCLUDE_DEPTH,
+    };
+
+    use super::{hostname, parse_xdg_config_dirs, variant_file_path};
+
+    /// `key = value` lines, values are integers if they parse as one and strings otherwise.
+    struct KeyValue;
+
+    impl ConfigFormat for KeyValue {
+        fn parse<OptionalConfig: DeserializeOwned>(
+            &self,
+            contents: &str,
+        ) -> Result<OptionalConfig, FormatError> {
+            let mut table = toml::Table::new();
+            let mut offset = 0;
+            for line in contents.split_inclusive('\n') {
+                let trimmed = line.trim();
+                if !trimmed.is_empty() {
+                    let Some((key, value)) = trimmed.split_once('=') else {
+                        let span = offset..offset + line.trim_end().len();
+                        return Err(FormatError::new("expected `key = value`").with_span(span));
+                    };
+                    let value = match value.trim().parse::<i64>() {
+                        Ok(number) => toml::Value::Integer(number),
+                        Err(_) => toml::Value::String(value.trim().to_string()),
+                    };
+                    table.insert(key.trim().to_string(), value);
+                }
+                offset += line.len();
+            }
+            OptionalConfig::deserialize(toml::Value::Table(table))
+                .map_err(|error| FormatError::new(error.message()).with_source(error))
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["kv"]
+        }
+
+        fn name(&self) -> &'static str {
+            "key=value"
+        }
+    }
+
+    #[test]
+    fn test_loader_custom_format() {
+        let base_dir = test_dir("testfolder_loader_custom");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .register_format(KeyValue);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        fs::write(config_dir.join("config.kv"), "something = 7\n")
+            .expect("Could not write test config.");
+        let conf = loader
+            .clone()
+            .file("config.kv")
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 7);
+
+        fs::write(config_dir.join("config.txt"), "something = 8\nwhat = 9\n")
+            .expect("Could not write test config.");
+        let custom = loader.clone().file("config.txt").custom_format(KeyValue);
+        let conf = custom
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 8);
+        let error = custom
+            .clone()
+            .strict(true)
+            .load::<Conf, OptConf>()
+            .expect_err("Config with unknown keys was accepted.");
+        assert!(matches!(error, ConfigError::UnknownKeys(_, _)));
+
+        fs::write(config_dir.join("config.kv"), "something = 7\nbroken\n")
+            .expect("Could not write test config.");
+        let error = loader
+            .clone()
+            .file("config.kv")
+            .load::<Conf, OptConf>()
+            .expect_err("Broken config was parsed successfully.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.format(), Format::Custom("key=value"));
+                assert_eq!(parse.location(), Some((2, 1)));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        fs::write(config_dir.join("config.kv"), "something = many\n")
+            .expect("Could not write test config.");
+        let error = loader
+            .file("config.kv")
+            .load::<Conf, OptConf>()
+            .expect_err("Broken config was parsed successfully.");
+        assert!(error.to_string().ends_with("(format: key=value)"));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_includes() {
+        let base_dir = test_dir("testfolder_loader_includes");
+        let loader = ConfigLoader::new("app").base_dir(&base_dir).includes(true);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        fs::create_dir_all(config_dir.join("parts")).expect("Could not create test folder.");
+        fs::write(
+            config_dir.join("config.toml"),
+            "include = [\"parts/first.toml\", \"second.toml\"]",
+        )
+        .expect("Could not write test config.");
+        fs::write(
+            config_dir.join("parts").join("first.toml"),
+            "include = \"../second.toml\"\nsomething = 3",
+        )
+        .expect("Could not write test config.");
+        fs::write(config_dir.join("second.toml"), "something = 4")
+            .expect("Could not write test config.");
+        let conf = loader
+            .clone()
+            .strict(true)
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 4);
+
+        fs::write(
+            config_dir.join("other.toml"),
+            "include = \"second.toml\"\nsomething = 5",
+        )
+        .expect("Could not write test config.");
+        let conf = loader
+            .clone()
+            .file("other.toml")
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 5);
+
+        fs::write(config_dir.join("second.toml"), "include = \"config.toml\"")
+            .expect("Could not write test config.");
+        let error = loader
+            .load::<Conf, OptConf>()
+            .expect_err("Include cycle was accepted.");
+        match &error {
+            ConfigError::IncludeCycle(chain) => assert_eq!(
+                chain,
+                &vec![
+                    config_dir.join("config.toml"),
+                    config_dir.join("parts/first.toml"),
+                    config_dir.join("parts/../second.toml"),
+                    config_dir.join("parts/../config.toml"),
+                ]
+            ),
+            _ => panic!("Expected an include cycle, got {:?}", error),
+        }
+
+        for depth in 0..=MAX_INCLUDE_DEPTH {
+            fs::write(
+                config_dir.join(format!("deep{}.toml", depth)),
+                format!("include = \"deep{}.toml\"", depth + 1),
+            )
+            .expect("Could not write test config.");
+        }
+        fs::write(config_dir.join("deep9.toml"), "").expect("Could not write test config.");
+        let error = loader
+            .clone()
+            .file("deep0.toml")
+            .load::<Conf, OptConf>()
+            .expect_err("Too deep includes were accepted.");
+        assert!(matches!(error, ConfigError::IncludeTooDeep(_)));
+        let conf = loader
+            .clone()
+            .file("deep1.toml")
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 1);
+
+        fs::write(config_dir.join("config.toml"), "include = 1").expect("Could not write config.");
+        let error = loader
+            .load::<Conf, OptConf>()
+            .expect_err("Invalid include was accepted.");
+        assert!(matches!(error, ConfigError::InvalidInclude(_)));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_env_prefix() {
+        let base_dir = test_dir("testfolder_loader_env");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("something = 2")
+            .env_prefix("OXILIB_TEST_LOADER_ENV");
+        let conf = loader
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+
+        std::env::set_var("OXILIB_TEST_LOADER_ENV_SOMETHING", "42");
+        let conf = loader.load::<Conf, OptConf>();
+        std::env::set_var("OXILIB_TEST_LOADER_ENV_SOMETHING", "many");
+        let error = loader.load::<Conf, OptConf>();
+        std::env::remove_var("OXILIB_TEST_LOADER_ENV_SOMETHING");
+        assert_eq!(conf.expect("Could not load config.").something, 42);
+        let error = error.expect_err("Invalid override was parsed.");
+        assert!(error
+            .to_string()
+            .contains("OXILIB_TEST_LOADER_ENV_SOMETHING"));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_path_env() {
+        let base_dir = test_dir("testfolder_loader_path_env");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("something = 2")
+            .path_env("OXILIB_TEST_LOADER_PATH");
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        let alternate = config_dir.join("alternate.toml");
+        fs::write(&alternate, "something = 3").expect("Could not write test config.");
+        let missing = config_dir.join("missing.toml");
+
+        std::env::set_var("OXILIB_TEST_LOADER_PATH", &alternate);
+        let alternate_result = loader.load_with_report::<Conf, OptConf>();
+        std::env::set_var("OXILIB_TEST_LOADER_PATH", &missing);
+        let missing_result = loader.load::<Conf, OptConf>();
+        std::env::remove_var("OXILIB_TEST_LOADER_PATH");
+
+        let (conf, report) = alternate_result.expect("Could not load config.");
+        assert_eq!(conf.something, 3);
+        assert_eq!(
+            report.path_source,
+            PathSource::EnvVar(String::from("OXILIB_TEST_LOADER_PATH"))
+        );
+        assert_eq!(report.field_status("something"), Some(FieldStatus::Present));
+        let error = missing_result.expect_err("Missing config was loaded.");
+        assert!(matches!(error, ConfigError::NotFound(path) if path == missing));
+        assert!(!missing.exists());
+
+        let (conf, report) = loader
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        assert_eq!(report.path_source, PathSource::ConfigDir);
+        assert!(report.created);
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_layers() {
+        let base_dir = test_dir("testfolder_loader_layers");
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptWindow {
+            width: Option<u32>,
+            height: Option<u32>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptLayered {
+            something: Option<u32>,
+            window: Option<OptWindow>,
+        }
+
+        impl ConfigOptional for OptLayered {}
+
+        impl Config<OptLayered> for OptLayered {
+            fn create_from_optional(optional: OptLayered) -> Self {
+                optional
+            }
+        }
+
+        let loader = ConfigLoader::new("app").base_dir(&base_dir);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        let system = config_dir.join("system.toml");
+        fs::write(&system, "[window]\nwidth = 2").expect("Could not write test config.");
+        fs::write(config_dir.join("config.toml"), "[window]\nheight = 3")
+            .expect("Could not write test config.");
+        let layered = loader
+            .layer(Layer::Toml(String::from(
+                "something = 1\n[window]\nwidth = 1\nheight = 1",
+            )))
+            .layer(Layer::File(system.clone()))
+            .layer(Layer::File(config_dir.join("missing.toml")))
+            .layer(Layer::UserFile)
+            .layer(Layer::Env(String::from("OXILIB_TEST_LOADER_LAYERS")));
+
+        std::env::set_var("OXILIB_TEST_LOADER_LAYERS_SOMETHING", "4");
+        let result = layered.load::<OptLayered, OptLayered>();
+        std::env::remove_var("OXILIB_TEST_LOADER_LAYERS_SOMETHING");
+        let conf = result.expect("Could not load layers.");
+        let window = conf.window.expect("Window was not loaded.");
+        assert_eq!(conf.something, Some(4));
+        assert_eq!((window.width, window.height), (Some(2), Some(3)));
+        assert!(!config_dir.join("missing.toml").exists());
+        let (_, report) = layered
+            .load_with_report::<OptLayered, OptLayered>()
+            .expect("Could not load layers.");
+        assert_eq!(
+            report.consulted_files,
+            vec![system.clone(), config_dir.join("config.toml")]
+        );
+
+        fs::write(&system, "[window]\nwidth = ").expect("Could not write test config.");
+        let error = layered
+            .load::<OptLayered, OptLayered>()
+            .expect_err("Broken layer was parsed.");
+        assert_eq!(error.path(), Some(system.as_path()));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_parse_xdg_config_dirs() {
+        let dirs = parse_xdg_config_dirs(Some(OsString::from("/usr/share/xdg:relative:/etc/xdg")));
+        assert_eq!(
+            dirs,
+            vec![PathBuf::from("/usr/share/xdg"), PathBuf::from("/etc/xdg")]
+        );
+        assert_eq!(parse_xdg_config_dirs(None), vec![PathBuf::from("/etc/xdg")]);
+        assert_eq!(
+            parse_xdg_config_dirs(Some(OsString::new())),
+            vec![PathBuf::from("/etc/xdg")]
+        );
+    }
+
+    #[test]
+    fn test_loader_xdg_config_dirs() {
+        let base_dir = test_dir("testfolder_loader_xdg");
+        let vendor = base_dir.join("vendor");
+        let distro = base_dir.join("distro");
+        for dir in [&vendor, &distro] {
+            fs::create_dir_all(dir.join("myapp")).expect("Could not create test folder.");
+        }
+        fs::write(vendor.join("myapp/config.toml"), "something = 5")
+            .expect("Could not write test config.");
+        fs::write(distro.join("myapp/config.toml"), "something = 6")
+            .expect("Could not write test config.");
+        let loader = ConfigLoader::new("myapp")
+            .base_dir(base_dir.join("home"))
+            .create_missing(false)
+            .layer(Layer::Toml(String::from("something = 4")))
+            .layer(Layer::XdgConfigDirs)
+            .layer(Layer::UserFile)
+            .xdg_config_dirs(vec![distro.clone(), vendor.clone()]);
+
+        let (conf, report) = loader
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load layers.");
+        assert_eq!(conf.something, 6);
+        assert_eq!(
+            report.consulted_files,
+            vec![
+                vendor.join("myapp/config.toml"),
+                distro.join("myapp/config.toml")
+            ]
+        );
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_system_file() {
+        let base_dir = test_dir("testfolder_loader_system");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("something = 2");
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        let system_file = config_dir.join("system.toml");
+        fs::write(&system_file, "something = 7").expect("Could not write test config.");
+        let with_system = loader.clone().system_file(&system_file);
+
+        let (conf, report) = with_system
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 7);
+        assert_eq!(
+            report.path_source,
+            PathSource::SystemFile(system_file.clone())
+        );
+        assert!(!config_dir.join("config.toml").exists());
+
+        fs::write(config_dir.join("config.toml"), "something = 8")
+            .expect("Could not write test config.");
+        let (conf, report) = with_system
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 8);
+        assert_eq!(report.path_source, PathSource::ConfigDir);
+        assert_eq!(
+            report.consulted_files,
+            vec![system_file.clone(), config_dir.join("config.toml")]
+        );
+
+        fs::remove_file(config_dir.join("config.toml")).expect("Could not remove test config.");
+        let conf = loader
+            .clone()
+            .system_file(config_dir.join("missing.toml"))
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        assert!(config_dir.join("config.toml").is_file());
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_loader_system_file_permission_denied() {
+        let base_dir = test_dir("testfolder_loader_system_denied");
+        use std::os::unix::fs::PermissionsExt;
+
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .create_missing(false)
+            .default_toml("something = 2");
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        fs::create_dir_all(&config_dir).expect("Could not create test folder.");
+        let system_file = config_dir.join("system.toml");
+        fs::write(&system_file, "something = 7").expect("Could not write test config.");
+        fs::set_permissions(&system_file, fs::Permissions::from_mode(0o000))
+            .expect("Could not change permissions of test file.");
+        // privileged users can read the file regardless of its mode
+        if fs::read(&system_file).is_err() {
+            let conf = loader
+                .system_file(&system_file)
+                .load::<Conf, OptConf>()
+                .expect("Unreadable system file failed the load.");
+            assert_eq!(conf.something, 2);
+        }
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_host_overrides() {
+        let base_dir = test_dir("testfolder_loader_host");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("something = 2")
+            .host_overrides(true);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        fs::write(config_dir.join("config.desk.toml"), "something = 5")
+            .expect("Could not write test config.");
+        let (conf, report) = loader
+            .clone()
+            .hostname("desk")
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 5);
+        assert_eq!(report.host_file, Some(config_dir.join("config.desk.toml")));
+
+        let (conf, report) = loader
+            .clone()
+            .hostname("laptop")
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        assert_eq!(report.host_file, None);
+
+        assert_eq!(
+            hostname(Some(String::from("desk"))).as_deref(),
+            Some("desk")
+        );
+        assert_eq!(hostname(Some(String::new())), hostname(None));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+
+        let config_file = PathBuf::from("/etc/myapp/config.toml");
+        assert_eq!(
+            variant_file_path(&config_file, "desk"),
+            Some(PathBuf::from("/etc/myapp/config.desk.toml"))
+        );
+        assert_eq!(
+            variant_file_path(&PathBuf::from("myapp/config"), "desk"),
+            Some(PathBuf::from("myapp/config.desk"))
+        );
+        assert_eq!(variant_file_path(&config_file, "../desk"), None);
+    }
+
+    #[test]
+    fn test_loader_variant_precedence() {
+        let base_dir = test_dir("testfolder_loader_variants");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .hostname("desk");
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        let os_file = config_dir.join(format!("config.{}.toml", std::env::consts::OS));
+        fs::write(
+            config_dir.join("config.toml"),
+            "something = 1\n[profile.work]\nsomething = 4\n",
+        )
+        .expect("Could not write test config.");
+        fs::write(&os_file, "something = 2").expect("Could not write test config.");
+        fs::write(config_dir.join("config.desk.toml"), "something = 3")
+            .expect("Could not write test config.");
+        let load = |loader: ConfigLoader| {
+            loader
+                .load_with_report::<Conf, OptConf>()
+                .map(|(conf, report)| (conf.something, report))
+        };
+
+        let (something, report) = load(loader.clone().os_overrides(true)).unwrap();
+        assert_eq!(something, 2);
+        assert_eq!(report.os_file, Some(os_file.clone()));
+        assert_eq!(report.host_file, None);
+        let with_variants = loader.clone().os_overrides(true).host_overrides(true);
+        assert_eq!(load(with_variants.clone()).unwrap().0, 3);
+        let with_profile = with_variants.profile("work");
+        assert_eq!(load(with_profile.clone()).unwrap().0, 4);
+        assert_eq!(
+            load(with_profile.clone().overrides(["something=5"]))
+                .unwrap()
+                .0,
+            5
+        );
+        let error = load(with_profile.profile("home")).expect_err("Unknown profile was loaded.");
+        assert!(matches!(error, ConfigError::UnknownProfile { profile, .. } if profile == "home"));
+
+        let layered = loader
+            .clone()
+            .os_overrides(true)
+            .host_overrides(true)
+            .layer(Layer::UserFile);
+        let (something, report) = load(layered).unwrap();
+        assert_eq!(something, 3);
+        assert_eq!(
+            report.consulted_files,
+            vec![
+                config_dir.join("config.toml"),
+                os_file,
+                config_dir.join("config.desk.toml")
+            ]
+        );
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_layers_create_nothing() {
+        let base_dir = test_dir("testfolder_loader_layers_create");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .layer(Layer::Toml(String::from("something = 1")))
+            .layer(Layer::UserFile);
+        let config_dir = base_dir.join("app");
+        let conf = loader
+            .load::<Conf, OptConf>()
+            .expect("Could not load layers.");
+        assert_eq!(conf.something, 1);
+        assert_eq!(
+            loader.file_path().expect("Could not resolve file."),
+            config_dir.join("config.toml")
+        );
+        assert!(!config_dir.exists());
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_project_layer() {
+        let base_dir = test_dir("testfolder_loader_project");
+        let start = base_dir.join("project").join("src");
+        fs::create_dir_all(&start).expect("Could not create test folder.");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .project_start(&start)
+            .layer(Layer::UserFile)
+            .layer(Layer::Project {
+                file_name: String::from(".oxilib_test_project.toml"),
+                stop_at_repository: false,
+            });
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        fs::write(config_dir.join("config.toml"), "something = 2")
+            .expect("Could not write test config.");
+        let (conf, report) = loader
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        assert_eq!(report.project_file, None);
+
+        let project_file = base_dir.join("project").join(".oxilib_test_project.toml");
+        fs::write(&project_file, "something = 3").expect("Could not write test config.");
+        let (conf, report) = loader
+            .load_with_report::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 3);
+        assert_eq!(report.project_file, Some(project_file));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_interpolate_env() {
+        let base_dir = test_dir("testfolder_loader_interpolate");
+        #[derive(Debug, Deserialize)]
+        struct OptSocket {
+            socket: Option<String>,
+        }
+
+        impl ConfigOptional for OptSocket {}
+
+        struct Socket(String);
+
+        impl Config<OptSocket> for Socket {
+            fn create_from_optional(optional: OptSocket) -> Self {
+                Socket(optional.socket.unwrap_or_default())
+            }
+        }
+
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("socket = \"${OXILIB_TEST_RUNTIME_DIR}/myapp.sock\"");
+        std::env::set_var("OXILIB_TEST_RUNTIME_DIR", "/run/user/1000");
+        let conf = loader
+            .clone()
+            .interpolate_env(UnsetVar::Error)
+            .load::<Socket, OptSocket>();
+        std::env::remove_var("OXILIB_TEST_RUNTIME_DIR");
+        assert_eq!(
+            conf.expect("Could not load config.").0,
+            "/run/user/1000/myapp.sock"
+        );
+
+        let conf = loader
+            .clone()
+            .interpolate_env(UnsetVar::Keep)
+            .load::<Socket, OptSocket>()
+            .expect("Could not load config.");
+        assert_eq!(conf.0, "${OXILIB_TEST_RUNTIME_DIR}/myapp.sock");
+        let error = loader
+            .clone()
+            .interpolate_env(UnsetVar::Error)
+            .load::<Socket, OptSocket>()
+            .err()
+            .expect("Unset variable was interpolated.");
+        assert!(matches!(error, ConfigError::UnsetVariable(key, _) if key == "socket"));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_provenance() {
+        let base_dir = test_dir("testfolder_loader_provenance");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .layer(Layer::Toml(String::from(
+                "something = 1\ntimeout = 1\nretries = 1\ndelay = 1",
+            )))
+            .layer(Layer::UserFile)
+            .layer(Layer::Env(String::from("OXILIB_TEST_PROVENANCE")))
+            .profile("work")
+            .overrides(["something=5"]);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        let config_file = config_dir.join("config.toml");
+        fs::write(&config_file, "timeout = 2\n[profile.work]\nretries = 3")
+            .expect("Could not write test config.");
+        std::env::set_var("OXILIB_TEST_PROVENANCE_DELAY", "4");
+        let loaded = loader.load_with_report::<Conf, OptConf>();
+        std::env::remove_var("OXILIB_TEST_PROVENANCE_DELAY");
+        let (conf, report) = loaded.expect("Could not load config.");
+        assert_eq!(conf.something, 5);
+        assert_eq!(
+            report.provenance.iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    "delay",
+                    &ValueSource::EnvVar(String::from("OXILIB_TEST_PROVENANCE_DELAY"))
+                ),
+                ("retries", &ValueSource::Profile(String::from("work"))),
+                ("something", &ValueSource::Override),
+                ("timeout", &ValueSource::UserFile(config_file)),
+            ]
+        );
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_array_merge() {
+        let base_dir = test_dir("testfolder_loader_array_merge");
+        #[derive(Debug, Deserialize)]
+        struct OptBar {
+            blocks: Option<Vec<String>>,
+            extra_css_files: Option<Vec<String>>,
+        }
+
+        impl ConfigOptional for OptBar {}
+
+        struct Bar(Vec<String>, Vec<String>);
+
+        impl Config<OptBar> for Bar {
+            fn create_from_optional(optional: OptBar) -> Self {
+                Bar(
+                    optional.blocks.unwrap_or_default(),
+                    optional.extra_css_files.unwrap_or_default(),
+                )
+            }
+        }
+
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .layer(Layer::Toml(String::from(
+                "blocks = [\"clock\"]\nextra_css_files = [\"base.css\"]",
+            )))
+            .layer(Layer::UserFile)
+            .array_merge("extra_css_files", ArrayMerge::Append);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        fs::write(
+            config_dir.join("config.toml"),
+            "blocks = [\"tray\"]\nextra_css_files = [\"user.css\"]",
+        )
+        .expect("Could not write test config.");
+        let bar = loader
+            .load::<Bar, OptBar>()
+            .expect("Could not load config.");
+        assert_eq!(bar.0, vec![String::from("tray")]);
+        assert_eq!(
+            bar.1,
+            vec![String::from("base.css"), String::from("user.css")]
+        );
+
+        let error = loader
+            .array_merge("blocks", ArrayMerge::MergeByKey(String::from("name")))
+            .load::<Bar, OptBar>()
+            .err()
+            .expect("Array without keys was merged by key.");
+        assert!(matches!(error, ConfigError::MissingMergeKey(path, _) if path == "blocks"));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader_overrides() {
+        let base_dir = test_dir("testfolder_loader_overrides");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("something = 2");
+        let conf = loader
+            .clone()
+            .overrides(["something=3", "something=4"])
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 4);
+        let error = loader
+            .clone()
+            .overrides(["something=many"])
+            .load::<Conf, OptConf>()
+            .expect_err("Invalid override was applied.");
+        assert!(matches!(error, ConfigError::InvalidOverride(item, _) if item == "something=many"));
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_loader() {
+        let base_dir = test_dir("testfolder_loader");
+        let loader = ConfigLoader::new("app")
+            .base_dir(&base_dir)
+            .default_toml("something = 2");
+        let conf = loader
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        let config_dir = loader.config_dir().expect("Could not get config folder.");
+        assert_eq!(config_dir, base_dir.join("app"));
+        assert!(config_dir.join("config.toml").is_file());
+
+        fs::write(config_dir.join("other.toml"), "something = 3\nwhat = 4")
+            .expect("Could not write test config.");
+        let other = loader.clone().file("other.toml");
+        let conf = other
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 3);
+        let (_, raw) = other
+            .load_with_raw::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(raw.get::<u32>("what").unwrap(), Some(4));
+        let error = other
+            .strict(true)
+            .load::<Conf, OptConf>()
+            .expect_err("Config with unknown keys was accepted.");
+        assert!(matches!(error, ConfigError::UnknownKeys(_, _)));
+
+        let conf = loader
+            .clone()
+            .file("missing.toml")
+            .create_missing(false)
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        assert!(!config_dir.join("missing.toml").exists());
+
+        fs::write(config_dir.join("config.conf"), "something = 5")
+            .expect("Could not write test config.");
+        let conf = loader
+            .clone()
+            .file("config.conf")
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 5);
+        let error = loader
+            .clone()
+            .file("config.conf")
+            .strict_format(true)
+            .load::<Conf, OptConf>()
+            .expect_err("Unknown extension was accepted.");
+        assert!(matches!(error, ConfigError::UnknownFormat(_)));
+        let conf = loader
+            .clone()
+            .file("config.conf")
+            .format(Format::Toml)
+            .strict_format(true)
+            .load::<Conf, OptConf>()
+            .expect("Could not load config.");
+        assert_eq!(conf.something, 5);
+        fs::remove_dir_all(&base_dir).expect("Could not remove testfolder again.");
+    }
+}
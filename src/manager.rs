@@ -0,0 +1,298 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use serde::Serialize;
+
+use crate::{
+    config_file_path, try_create_config, watch_config_with_changes, ConfigChange, ConfigError,
+    ConfigOptional, ConfigWatcher, TryConfig,
+};
+
+type Subscriber<ConcreteConfig> = Box<dyn FnMut(&ConcreteConfig, &ConfigChange) + Send>;
+
+struct Shared<ConcreteConfig> {
+    current: RwLock<Arc<ConcreteConfig>>,
+    subscribers: Mutex<Vec<Subscriber<ConcreteConfig>>>,
+}
+
+/// Keeps a config up to date with its file: loads it like [`try_create_config`], watches the
+/// file like [`watch_config`](crate::watch_config) and informs subscribers after every
+/// successful reload. Failed reloads keep the current config. Stops watching once dropped.
+pub struct ConfigManager<ConcreteConfig> {
+    shared: Arc<Shared<ConcreteConfig>>,
+    watcher: ConfigWatcher,
+}
+
+impl<ConcreteConfig> ConfigManager<ConcreteConfig>
+where
+    ConcreteConfig: Serialize + Send + Sync + 'static,
+{
+    pub fn new<OptionalConfig>(
+        config_dir: &Path,
+        config_file_name: &str,
+        default_config: &str,
+    ) -> Result<Self, ConfigError>
+    where
+        ConcreteConfig: TryConfig<OptionalConfig>,
+        OptionalConfig: ConfigOptional,
+    {
+        let config = try_create_config::<ConcreteConfig, OptionalConfig>(
+            config_dir,
+            config_file_name,
+            default_config,
+        )?;
+        let shared = Arc::new(Shared {
+            current: RwLock::new(Arc::new(config)),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let path = config_file_path(config_dir, config_file_name);
+        let reloaded = Arc::clone(&shared);
+        let watcher = watch_config_with_changes::<ConcreteConfig, OptionalConfig, _>(
+            path,
+            default_config,
+            move |result| match result {
+                Ok(reload) => reloaded.publish(reload.config, &reload.change),
+                Err(error) => log_warn!("Could not reload config: {}", error),
+            },
+        )?;
+        Ok(ConfigManager { shared, watcher })
+    }
+}
+
+impl<ConcreteConfig> ConfigManager<ConcreteConfig> {
+    /// A snapshot of the current config, cheap to clone and send to other threads.
+    pub fn get(&self) -> Arc<ConcreteConfig> {
+        Arc::clone(&read(&self.shared.current))
+    }
+
+    /// Calls `subscriber` after every successful reload, on the thread of the watcher.
+    /// A panicking subscriber is logged and does not keep the others from being called.
+    /// Subscribers may subscribe others, those are called from the next reload on.
+    pub fn subscribe(
+        &self,
+        subscriber: impl FnMut(&ConcreteConfig, &ConfigChange) + Send + 'static,
+    ) {
+        lock(&self.shared.subscribers).push(Box::new(subscriber));
+    }
+
+    /// Replaces the config with a modified copy until the returned guard is dropped,
+    /// like `LiveConfig::override_with` of the `hot-swap` feature. Subscribers are
+    /// not called and nothing is written to disk. See [`ManagerOverrideGuard`].
+    pub fn override_with(
+        &self,
+        modify: impl FnOnce(&mut ConcreteConfig),
+    ) -> ManagerOverrideGuard<ConcreteConfig>
+    where
+        ConcreteConfig: Clone,
+    {
+        let previous = self.get();
+        let mut modified = ConcreteConfig::clone(&previous);
+        modify(&mut modified);
+        *write(&self.shared.current) = Arc::new(modified);
+        ManagerOverrideGuard {
+            shared: Arc::clone(&self.shared),
+            previous: Some(previous),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.watcher.path()
+    }
+
+    pub fn watcher(&mut self) -> &mut ConfigWatcher {
+        &mut self.watcher
+    }
+}
+
+impl<ConcreteConfig> std::fmt::Debug for ConfigManager<ConcreteConfig> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigManager")
+            .field("watcher", &self.watcher)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Restores the config that was current before [`ConfigManager::override_with`] when dropped,
+/// also while unwinding from a panic. Nested guards have to be dropped in reverse order,
+/// a config reloaded while the guard is alive is overwritten as well.
+#[must_use = "the override is reverted as soon as the guard is dropped"]
+pub struct ManagerOverrideGuard<ConcreteConfig> {
+    shared: Arc<Shared<ConcreteConfig>>,
+    previous: Option<Arc<ConcreteConfig>>,
+}
+
+impl<ConcreteConfig> ManagerOverrideGuard<ConcreteConfig> {
+    /// Keeps the modified config instead of restoring the previous one, e.g. once a preview
+    /// is accepted. It stays until the next reload.
+    pub fn keep(mut self) {
+        self.previous = None;
+    }
+}
+
+impl<ConcreteConfig> Drop for ManagerOverrideGuard<ConcreteConfig> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            *write(&self.shared.current) = previous;
+        }
+    }
+}
+
+impl<ConcreteConfig> std::fmt::Debug for ManagerOverrideGuard<ConcreteConfig> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagerOverrideGuard")
+            .field("kept", &self.previous.is_none())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<ConcreteConfig> Shared<ConcreteConfig> {
+    fn publish(&self, config: ConcreteConfig, change: &ConfigChange) {
+        let config = Arc::new(config);
+        *write(&self.current) = Arc::clone(&config);
+        // not locked while calling them, a subscriber may subscribe another one
+        let mut subscribers = std::mem::take(&mut *lock(&self.subscribers));
+        for subscriber in subscribers.iter_mut() {
+            if catch_unwind(AssertUnwindSafe(|| subscriber(&config, change))).is_err() {
+                log_warn!("A config subscriber panicked");
+            }
+        }
+        let mut current = lock(&self.subscribers);
+        subscribers.append(&mut current);
+        *current = subscribers;
+    }
+}
+
+// panics of subscribers are caught, neither lock is left inconsistent
+fn read<T>(lock: &RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|error| error.into_inner())
+}
+
+fn write<T>(lock: &RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|error| error.into_inner())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{mpsc, Arc, OnceLock, Weak},
+        time::Duration,
+    };
+
+    use crate::{
+        test_support::{test_dir, Conf, OptConf},
+        ConfigManager,
+    };
+
+    #[test]
+    fn test_config_manager() {
+        let config_dir = test_dir("test_manager");
+        let path = config_dir.join("config.toml");
+        let _ = fs::remove_file(&path);
+        let manager =
+            ConfigManager::<Conf>::new::<OptConf>(&config_dir, "config.toml", "something = 2")
+                .expect("Could not load config.");
+        assert_eq!(manager.get().something, 2);
+        assert_eq!(manager.path(), path);
+
+        // keeps the subscriber after it from being called unless caught
+        manager.subscribe(|_, _| panic!("Subscriber failed."));
+        let (sender, receiver) = mpsc::channel();
+        manager.subscribe(move |config, change| {
+            let keys: Vec<String> = change.keys().map(str::to_string).collect();
+            let _ = sender.send((config.something, keys));
+        });
+        let snapshot = manager.get();
+        fs::write(&path, "something = 3").expect("Could not write test config.");
+        let (something, keys) = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("No reload arrived.");
+        assert_eq!(something, 3);
+        assert_eq!(keys, vec!["something"]);
+        assert_eq!(manager.get().something, 3);
+        assert_eq!(snapshot.something, 2);
+
+        // failed reloads keep the current config
+        fs::write(&path, "something = ").expect("Could not write test config.");
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(manager.get().something, 3);
+        fs::write(&path, "something = 4").expect("Could not write test config.");
+        let (something, _) = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("No reload arrived.");
+        assert_eq!(something, 4);
+
+        drop(manager);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_manager_override_with() {
+        let config_dir = test_dir("test_manager_override");
+        fs::write(config_dir.join("config.toml"), "something = 1")
+            .expect("Could not write test config.");
+        let manager = ConfigManager::<Conf>::new::<OptConf>(&config_dir, "config.toml", "")
+            .expect("Could not load config.");
+        let outer = manager.override_with(|config| config.something = 2);
+        assert_eq!(manager.get().something, 2);
+        let inner = manager.override_with(|config| config.something *= 10);
+        assert_eq!(manager.get().something, 20);
+        drop(inner);
+        assert_eq!(manager.get().something, 2);
+        drop(outer);
+        assert_eq!(manager.get().something, 1);
+
+        manager.override_with(|config| config.something = 4).keep();
+        assert_eq!(manager.get().something, 4);
+
+        drop(manager);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_subscribe_from_subscriber() {
+        let config_dir = test_dir("test_manager_subscribe");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "something = 2").expect("Could not write test config.");
+        let manager = Arc::new(
+            ConfigManager::<Conf>::new::<OptConf>(&config_dir, "config.toml", "")
+                .expect("Could not load config."),
+        );
+        let slot: Arc<OnceLock<Weak<ConfigManager<Conf>>>> = Arc::new(OnceLock::new());
+        slot.set(Arc::downgrade(&manager)).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let (subscribed, subscribed_receiver) = mpsc::channel();
+        let mut sender = Some(sender);
+        manager.subscribe(move |_, _| {
+            let (Some(manager), Some(sender)) = (slot.get().and_then(Weak::upgrade), sender.take())
+            else {
+                return;
+            };
+            manager.subscribe(move |config, _| {
+                let _ = sender.send(config.something);
+            });
+            let _ = subscribed.send(());
+        });
+
+        fs::write(&path, "something = 3").expect("Could not write test config.");
+        subscribed_receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Subscriber was not called.");
+        assert!(receiver.try_recv().is_err());
+        fs::write(&path, "something = 4").expect("Could not write test config.");
+        let something = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("No reload arrived.");
+        assert_eq!(something, 4);
+
+        drop(manager);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
@@ -0,0 +1,84 @@
+/// Implements [`ConfigOptional`](crate::ConfigOptional) with a field by field
+/// [`merge`](crate::ConfigOptional::merge) for an optional config, every field has to be listed.
+/// A set field of the other config replaces the field, including `Option<Vec<T>>` fields.
+/// The fields after `nested` are optional configs themselves and merged recursively:
+/// `impl_merge!(OptConf { something, modules }, nested { window });`
+#[macro_export]
+macro_rules! impl_merge {
+    ($optional:ty { $($field:ident),* $(,)? } $(, nested { $($nested:ident),* $(,)? })? $(,)?) => {
+        impl $crate::ConfigOptional for $optional {
+            fn merge(self, other: Self) -> Self {
+                Self {
+                    $($field: other.$field.or(self.$field),)*
+                    $($(
+                        $nested: match (self.$nested, other.$nested) {
+                            (
+                                ::core::option::Option::Some(base),
+                                ::core::option::Option::Some(other),
+                            ) => ::core::option::Option::Some(
+                                $crate::ConfigOptional::merge(base, other),
+                            ),
+                            (base, other) => other.or(base),
+                        },
+                    )*)?
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::ConfigOptional;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct OptWindow {
+        width: Option<u32>,
+        height: Option<u32>,
+    }
+
+    crate::impl_merge!(OptWindow { width, height });
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct OptConf {
+        something: Option<u32>,
+        modules: Option<Vec<String>>,
+        window: Option<OptWindow>,
+    }
+
+    crate::impl_merge!(OptConf { something, modules }, nested { window });
+
+    #[test]
+    fn test_impl_merge() {
+        let system: OptConf = toml::from_str(
+            "something = 1\nmodules = [\"clock\", \"tray\"]\n[window]\nwidth = 2\nheight = 3",
+        )
+        .unwrap();
+        let user: OptConf =
+            toml::from_str("modules = [\"battery\"]\n[window]\nheight = 4").unwrap();
+        assert_eq!(
+            system.merge(user),
+            OptConf {
+                something: Some(1),
+                modules: Some(vec![String::from("battery")]),
+                window: Some(OptWindow {
+                    width: Some(2),
+                    height: Some(4),
+                }),
+            }
+        );
+
+        let user: OptConf = toml::from_str("[window]\nwidth = 5").unwrap();
+        let empty: OptConf = toml::from_str("").unwrap();
+        let merged = empty.merge(user);
+        assert_eq!(
+            merged.window,
+            Some(OptWindow {
+                width: Some(5),
+                height: None,
+            })
+        );
+    }
+}
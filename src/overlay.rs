@@ -0,0 +1,50 @@
+use crate::{Config, ConfigOptional};
+
+/// Applies every field of an optional config that is set onto an existing config.
+/// Implemented by `#[derive(OptionalConfig)]` or [`impl_overlay!`](crate::impl_overlay).
+pub trait Overlay<ConcreteConfig> {
+    fn apply(self, config: &mut ConcreteConfig);
+}
+
+/// Marker for configs whose fallbacks are just their [`Default`] values.
+/// [`Config`] is implemented for them by applying the optional config onto the default.
+pub trait DefaultConfig {}
+
+impl<ConcreteConfig, OptionalConfig> Config<OptionalConfig> for ConcreteConfig
+where
+    ConcreteConfig: DefaultConfig + Default,
+    OptionalConfig: ConfigOptional + Overlay<ConcreteConfig>,
+{
+    fn create_from_optional(optional: OptionalConfig) -> Self {
+        from_optional_with_default(optional)
+    }
+}
+
+pub fn from_optional_with_default<ConcreteConfig, OptionalConfig>(
+    optional: OptionalConfig,
+) -> ConcreteConfig
+where
+    ConcreteConfig: Default,
+    OptionalConfig: Overlay<ConcreteConfig>,
+{
+    let mut config = ConcreteConfig::default();
+    optional.apply(&mut config);
+    config
+}
+
+/// Implements [`Overlay`] for an optional config whose fields are `Option`s of the
+/// concrete fields with the same name: `impl_overlay!(OptConf => Conf { something, what });`
+#[macro_export]
+macro_rules! impl_overlay {
+    ($optional:ty => $concrete:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::Overlay<$concrete> for $optional {
+            fn apply(self, config: &mut $concrete) {
+                $(
+                    if let ::core::option::Option::Some(value) = self.$field {
+                        config.$field = value;
+                    }
+                )*
+            }
+        }
+    };
+}
@@ -0,0 +1,122 @@
+use crate::ConfigError;
+
+/// Sets the `path=value` pairs of `overrides` in `value`, e.g. `window.width=300` from
+/// `myapp -o window.width=300`. The value is parsed as toml, so strings have to be quoted.
+/// Missing tables along the path are created, later overrides win over earlier ones.
+pub fn apply_overrides(value: &mut toml::Value, overrides: &[String]) -> Result<(), ConfigError> {
+    for item in overrides {
+        let invalid = |reason: &str| ConfigError::InvalidOverride(item.clone(), reason.to_string());
+        let Some((path, raw)) = item.split_once('=') else {
+            return Err(invalid("expected `path=value`"));
+        };
+        let path: Vec<&str> = path.trim().split('.').map(str::trim).collect();
+        if path.iter().any(|key| !is_bare_key(key)) {
+            return Err(invalid("invalid path"));
+        }
+        let parsed = match parse_value(raw.trim()) {
+            Some(parsed) => parsed,
+            None => return Err(invalid("invalid toml value, strings have to be quoted")),
+        };
+        set_path(value, &path, parsed).map_err(|key| {
+            invalid(&format!(
+                "`{}` is neither a table nor a valid array index",
+                key
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn parse_value(raw: &str) -> Option<toml::Value> {
+    let mut document: toml::Table = toml::from_str(&format!("value = {}", raw)).ok()?;
+    document.remove("value")
+}
+
+/// On failure returns the key that could not be descended into.
+fn set_path<'a>(
+    value: &mut toml::Value,
+    path: &[&'a str],
+    new: toml::Value,
+) -> Result<(), &'a str> {
+    let Some((key, rest)) = path.split_first() else {
+        *value = new;
+        return Ok(());
+    };
+    let child = match value {
+        toml::Value::Table(table) => {
+            if rest.is_empty() {
+                table.insert(key.to_string(), new);
+                return Ok(());
+            }
+            table
+                .entry(key.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        }
+        toml::Value::Array(array) => match key.parse::<usize>() {
+            Ok(index) if index < array.len() => &mut array[index],
+            _ => return Err(key),
+        },
+        _ => return Err(key),
+    };
+    match child {
+        toml::Value::Table(_) | toml::Value::Array(_) => set_path(child, rest, new),
+        _ => Err(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{apply_overrides, ConfigError};
+
+    fn overrides(overrides: &[&str]) -> Vec<String> {
+        overrides.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut value: toml::Value =
+            toml::from_str("something = 1\n[[outputs]]\nname = \"DP-1\"").unwrap();
+        apply_overrides(
+            &mut value,
+            &overrides(&[
+                "something=42",
+                "window.width = 300",
+                "window.title=\"bar\"",
+                "window.enabled=true",
+                "outputs.0.name='HDMI-A-1'",
+                "modules=[\"clock\", \"tray\"]",
+                "something=43",
+            ]),
+        )
+        .expect("Could not apply overrides.");
+        let expected: toml::Value = toml::from_str(
+            "something = 43\nmodules = [\"clock\", \"tray\"]\n[window]\nwidth = 300\n\
+             title = \"bar\"\nenabled = true\n[[outputs]]\nname = \"HDMI-A-1\"",
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+
+        for invalid in [
+            "something",
+            "=1",
+            "window..width=1",
+            "what=hello",
+            "something.x=1",
+        ] {
+            let error = apply_overrides(&mut value, &overrides(&[invalid]))
+                .expect_err("Invalid override was applied.");
+            match &error {
+                ConfigError::InvalidOverride(item, _) => assert_eq!(item, invalid),
+                _ => panic!("Expected an invalid override, got {:?}", error),
+            }
+            assert!(error.to_string().contains(invalid));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config_file_path, create_from_parsed, format, load_optional, warn_unknown_keys, ConfigError,
+    ConfigOptional, Format, RawConfig, TryConfig, DEFAULT_SIZE_LIMIT,
+};
+
+/// The table holding one `[plugin.<name>]` table per plugin.
+pub const PLUGIN_SECTION: &str = "plugin";
+
+/// Turns the table of a plugin into its config, registered with [`PluginRegistry::register`].
+pub type SectionParser = Box<dyn Fn(&toml::Value) -> Result<Box<dyn Any>, ConfigError>>;
+
+/// Configs of plugins the host binary does not know at compile time.
+/// Each plugin registers a parser for its `[plugin.<name>]` table, the file is parsed once
+/// and every table is handed to its parser.
+#[derive(Default)]
+pub struct PluginRegistry {
+    sections: Vec<(String, SectionParser)>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        PluginRegistry::default()
+    }
+
+    /// Registers `parser` for `[plugin.<name>]`, replacing an earlier parser of the same name.
+    /// A missing table is passed to the parser as an empty table.
+    pub fn register(&mut self, name: impl Into<String>, parser: SectionParser) {
+        let name = name.into();
+        self.sections.retain(|(existing, _)| *existing != name);
+        self.sections.push((name, parser));
+    }
+
+    /// Registers a parser that loads `[plugin.<name>]` like any other config file.
+    pub fn register_config<ConcreteConfig, OptionalConfig>(&mut self, name: impl Into<String>)
+    where
+        ConcreteConfig: TryConfig<OptionalConfig> + 'static,
+        OptionalConfig: ConfigOptional,
+    {
+        let name = name.into();
+        let section = format!("{}.{}", PLUGIN_SECTION, name);
+        let parser = move |value: &toml::Value| -> Result<Box<dyn Any>, ConfigError> {
+            let mut unknown_keys = Vec::new();
+            let parsed_conf: OptionalConfig =
+                format::from_value(Path::new(""), value.clone(), &mut unknown_keys)?;
+            warn_unknown_keys(Path::new(&section), &unknown_keys);
+            let config: ConcreteConfig = create_from_parsed(Path::new(""), parsed_conf)?;
+            Ok(Box::new(config))
+        };
+        self.register(name, Box::new(parser));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sections.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Reads the config file and parses the table of every registered plugin.
+    /// A missing file is treated like an empty one, nothing is created.
+    pub fn load(
+        &self,
+        config_dir: &Path,
+        config_file_name: &str,
+    ) -> Result<PluginConfigs, ConfigError> {
+        let config_file = config_file_path(config_dir, config_file_name);
+        let loaded = load_optional::<toml::Table>(
+            &config_file,
+            "",
+            Format::detect(&config_file).into(),
+            DEFAULT_SIZE_LIMIT,
+            false,
+            &mut Vec::new(),
+        )?;
+        self.dispatch(&RawConfig::new(
+            &config_file,
+            toml::Value::Table(loaded.parsed_conf),
+        ))
+    }
+
+    /// Parses the table of every registered plugin from a document the host already loaded,
+    /// e.g. with [`crate::ConfigLoader::load_with_raw`].
+    pub fn dispatch(&self, raw: &RawConfig) -> Result<PluginConfigs, ConfigError> {
+        let empty = toml::Table::new();
+        let plugins = match raw.value().get(PLUGIN_SECTION) {
+            Some(toml::Value::Table(plugins)) => plugins,
+            Some(_) => {
+                let path = raw.path().to_path_buf();
+                return Err(ConfigError::InvalidSection(
+                    path,
+                    PLUGIN_SECTION.to_string(),
+                ));
+            }
+            None => &empty,
+        };
+        let empty_value = toml::Value::Table(toml::Table::new());
+        let mut configs = HashMap::new();
+        for (name, parser) in &self.sections {
+            let value = plugins.get(name).unwrap_or(&empty_value);
+            let section = format!("{}.{}", PLUGIN_SECTION, name);
+            let config = parser(value).map_err(|error| error.in_section(raw.path(), &section))?;
+            configs.insert(name.clone(), config);
+        }
+        let unclaimed = plugins
+            .keys()
+            .filter(|name| !configs.contains_key(*name))
+            .cloned()
+            .collect();
+        Ok(PluginConfigs {
+            path: raw.path().to_path_buf(),
+            configs,
+            unclaimed,
+        })
+    }
+}
+
+impl Debug for PluginRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// The configs parsed by a [`PluginRegistry`], by plugin name.
+#[derive(Debug)]
+pub struct PluginConfigs {
+    path: PathBuf,
+    configs: HashMap<String, Box<dyn Any>>,
+    unclaimed: Vec<String>,
+}
+
+impl PluginConfigs {
+    /// The config of the plugin `name`, `None` if it is not registered or of a different type.
+    pub fn get<T: 'static>(&self, name: &str) -> Option<&T> {
+        self.configs
+            .get(name)
+            .and_then(|config| config.downcast_ref())
+    }
+
+    /// Removes the config of the plugin `name`, e.g. to hand it over to the plugin.
+    /// Keeps it if it is of a different type.
+    pub fn take<T: 'static>(&mut self, name: &str) -> Option<T> {
+        let config = self.configs.remove(name)?;
+        match config.downcast() {
+            Ok(config) => Some(*config),
+            Err(config) => {
+                self.configs.insert(name.to_string(), config);
+                None
+            }
+        }
+    }
+
+    /// Tables below `[plugin]` that no registered plugin claimed, e.g. of uninstalled plugins.
+    pub fn unclaimed(&self) -> &[String] {
+        &self.unclaimed
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::Any, fs};
+
+    use serde::Deserialize;
+
+    use crate::{test_support::test_dir, Config, ConfigError, ConfigOptional, PluginRegistry};
+
+    #[derive(Debug, PartialEq)]
+    struct Clock {
+        interval: u32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OptClock {
+        interval: Option<u32>,
+    }
+
+    impl ConfigOptional for OptClock {}
+
+    impl Config<OptClock> for Clock {
+        fn create_from_optional(optional: OptClock) -> Self {
+            Clock {
+                interval: optional.interval.unwrap_or(60),
+            }
+        }
+    }
+
+    #[test]
+    fn test_plugin_registry() {
+        let config_dir = test_dir("test_plugins");
+        fs::write(
+            config_dir.join("config.toml"),
+            "[plugin.clock]\ninterval = 5\n[plugin.weather]\ncity = \"Bern\"\n",
+        )
+        .expect("Could not write test config.");
+        let mut registry = PluginRegistry::new();
+        registry.register_config::<Clock, OptClock>("clock");
+        registry.register_config::<Clock, OptClock>("battery");
+        registry.register(
+            "tray",
+            Box::new(|value| -> Result<Box<dyn Any>, ConfigError> {
+                Ok(Box::new(value.as_table().map_or(0, |table| table.len())))
+            }),
+        );
+
+        let mut configs = registry
+            .load(&config_dir, "config.toml")
+            .expect("Could not load plugin configs.");
+        assert_eq!(configs.get::<Clock>("clock"), Some(&Clock { interval: 5 }));
+        assert_eq!(configs.get::<u32>("clock"), None);
+        assert_eq!(configs.get::<usize>("tray"), Some(&0));
+        assert_eq!(
+            configs.take::<Clock>("battery"),
+            Some(Clock { interval: 60 })
+        );
+        assert_eq!(configs.take::<Clock>("battery"), None);
+        assert_eq!(configs.unclaimed(), &[String::from("weather")]);
+
+        fs::write(
+            config_dir.join("config.toml"),
+            "[plugin.clock]\ninterval = \"a\"",
+        )
+        .expect("Could not write test config.");
+        let error = registry
+            .load(&config_dir, "config.toml")
+            .expect_err("Invalid plugin config was parsed.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.path(), config_dir.join("config.toml"));
+                assert!(parse
+                    .key_path()
+                    .is_some_and(|key| key.starts_with("plugin.clock")));
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+
+        fs::write(config_dir.join("config.toml"), "plugin = 1").expect("Could not write config.");
+        let error = registry
+            .load(&config_dir, "config.toml")
+            .expect_err("Invalid plugin table was parsed.");
+        assert!(matches!(error, ConfigError::InvalidSection(_, _)));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt::Display;
+
+use serde::ser::{self, Serialize};
+
+use crate::FieldStatus;
+
+/// Records which fields of a serialized optional config are `None`.
+/// Unlike `toml::Value::try_from`, this also reports fields that are skipped or `None`.
+pub(crate) fn field_statuses<T: Serialize>(value: &T) -> Vec<(String, FieldStatus)> {
+    let mut fields = Vec::new();
+    let probe = Probe {
+        path: String::new(),
+        is_field: false,
+        fields: &mut fields,
+    };
+    // a probe never fails on its own, errors only come from custom Serialize impls
+    let _ = value.serialize(probe);
+    fields
+}
+
+/// Like [`field_statuses`], every field is missing unless the config was parsed `from_file`,
+/// values of the default config are not present in the file.
+pub(crate) fn file_field_statuses<T: Serialize>(
+    value: &T,
+    from_file: bool,
+) -> Vec<(String, FieldStatus)> {
+    let mut fields = field_statuses(value);
+    if !from_file {
+        for (_, status) in &mut fields {
+            *status = FieldStatus::Missing;
+        }
+    }
+    fields
+}
+
+#[derive(Debug)]
+pub(crate) struct ProbeError(String);
+
+impl Display for ProbeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ProbeError {}
+
+impl ser::Error for ProbeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ProbeError(msg.to_string())
+    }
+}
+
+struct Probe<'a> {
+    path: String,
+    is_field: bool,
+    fields: &'a mut Vec<(String, FieldStatus)>,
+}
+
+impl Probe<'_> {
+    fn record(self, status: FieldStatus) -> Result<(), ProbeError> {
+        if self.is_field {
+            self.fields.push((self.path, status));
+        }
+        Ok(())
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+impl<'a> ser::Serializer for Probe<'a> {
+    type Ok = ();
+    type Error = ProbeError;
+    type SerializeSeq = Ignore;
+    type SerializeTuple = Ignore;
+    type SerializeTupleStruct = Ignore;
+    type SerializeTupleVariant = Ignore;
+    type SerializeMap = Ignore;
+    type SerializeStruct = ProbeStruct<'a>;
+    type SerializeStructVariant = Ignore;
+
+    fn serialize_bool(self, _: bool) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_char(self, _: char) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_str(self, _: &str) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_none(self) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Missing)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), ProbeError> {
+        if self.is_field {
+            self.fields.push((self.path.clone(), FieldStatus::Present));
+        }
+        value.serialize(Probe {
+            path: self.path,
+            is_field: false,
+            fields: self.fields,
+        })
+    }
+
+    fn serialize_unit(self) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProbeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), ProbeError> {
+        self.record(FieldStatus::Present)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Ignore, ProbeError> {
+        self.record(FieldStatus::Present)?;
+        Ok(Ignore)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Ignore, ProbeError> {
+        self.record(FieldStatus::Present)?;
+        Ok(Ignore)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Ignore, ProbeError> {
+        self.record(FieldStatus::Present)?;
+        Ok(Ignore)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Ignore, ProbeError> {
+        self.record(FieldStatus::Present)?;
+        Ok(Ignore)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Ignore, ProbeError> {
+        self.record(FieldStatus::Present)?;
+        Ok(Ignore)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<ProbeStruct<'a>, ProbeError> {
+        if self.is_field {
+            self.fields.push((self.path.clone(), FieldStatus::Present));
+        }
+        Ok(ProbeStruct {
+            path: self.path,
+            fields: self.fields,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Ignore, ProbeError> {
+        self.record(FieldStatus::Present)?;
+        Ok(Ignore)
+    }
+}
+
+pub(crate) struct ProbeStruct<'a> {
+    path: String,
+    fields: &'a mut Vec<(String, FieldStatus)>,
+}
+
+impl ser::SerializeStruct for ProbeStruct<'_> {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ProbeError> {
+        value.serialize(Probe {
+            path: join(&self.path, key),
+            is_field: true,
+            fields: self.fields,
+        })
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), ProbeError> {
+        self.fields
+            .push((join(&self.path, key), FieldStatus::Missing));
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
+
+pub(crate) struct Ignore;
+
+impl ser::SerializeSeq for Ignore {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Ignore {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Ignore {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for Ignore {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Ignore {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Ignore {
+    type Ok = ();
+    type Error = ProbeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), ProbeError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), ProbeError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use serde::Serialize;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::{
+    read_file, write::write_atomic, write_config, ConfigError, ParseError, DEFAULT_SIZE_LIMIT,
+};
+
+/// Like [`write_config`], but only the values that changed are replaced in the existing file.
+/// Comments, formatting, key order and keys unknown to the config are left untouched.
+pub fn write_config_preserving<ConcreteConfig>(
+    path: &Path,
+    config: &ConcreteConfig,
+) -> Result<(), ConfigError>
+where
+    ConcreteConfig: Serialize,
+{
+    if !path.is_file() {
+        return write_config(path, config);
+    }
+    let serialized = match toml::to_string(config) {
+        Ok(serialized) => serialized,
+        Err(error) => return Err(ConfigError::Serialize(path.to_path_buf(), Box::new(error))),
+    };
+    let updated: DocumentMut = serialized
+        .parse()
+        .expect("toml_edit rejected toml written by toml.");
+    let contents = read_file(path, DEFAULT_SIZE_LIMIT)?;
+    // parsing with toml first reports errors in the same shape as the loaders do
+    if let Err(error) = contents.parse::<toml::Table>() {
+        return Err(ConfigError::Parse(Box::new(ParseError::new(
+            path, &contents, error,
+        ))));
+    }
+    let mut document: DocumentMut = contents
+        .parse()
+        .expect("toml_edit rejected a document accepted by toml.");
+    update_table(document.as_table_mut(), updated.as_table());
+    let updated = document.to_string();
+    if updated == contents {
+        return Ok(());
+    }
+    write_atomic(path, &updated)
+}
+
+fn update_table(existing: &mut Table, updated: &Table) {
+    for (key, item) in updated.iter() {
+        match (existing.get_mut(key), item) {
+            (Some(Item::Table(existing)), Item::Table(updated)) => update_table(existing, updated),
+            (Some(Item::Value(existing)), Item::Value(updated)) => {
+                if !same_value(existing, updated) {
+                    let decor = existing.decor().clone();
+                    *existing = updated.clone();
+                    *existing.decor_mut() = decor;
+                }
+            }
+            (Some(existing), updated) => {
+                if existing.to_string().trim() != updated.to_string().trim() {
+                    *existing = updated.clone();
+                }
+            }
+            (None, updated) => {
+                existing.insert(key, updated.clone());
+            }
+        }
+    }
+}
+
+fn same_value(existing: &toml_edit::Value, updated: &toml_edit::Value) -> bool {
+    let mut existing = existing.clone();
+    existing.decor_mut().clear();
+    let mut updated = updated.clone();
+    updated.decor_mut().clear();
+    existing.to_string() == updated.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde::Serialize;
+
+    use crate::{test_support::test_dir, write_config_preserving};
+
+    #[derive(Serialize)]
+    struct Window {
+        width: u32,
+        height: u32,
+    }
+
+    #[derive(Serialize)]
+    struct Conf {
+        something: u32,
+        what: String,
+        window: Window,
+    }
+
+    #[test]
+    fn test_write_config_preserving() {
+        let config_dir = test_dir("test_preserving");
+        let path = config_dir.join("config.toml");
+        fs::write(
+            &path,
+            "# the answer\nsomething = 42 # do not change\nwhat = \"a\"\nunknown = true\n\n\
+             # window settings\n[window]\nwidth = 100 # pixels\nheight = 50\n",
+        )
+        .expect("Could not write test config.");
+        let config = Conf {
+            something: 7,
+            what: String::from("a"),
+            window: Window {
+                width: 100,
+                height: 60,
+            },
+        };
+        write_config_preserving(&path, &config).expect("Could not write config.");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# the answer\nsomething = 7 # do not change\nwhat = \"a\"\nunknown = true\n\n\
+             # window settings\n[window]\nwidth = 100 # pixels\nheight = 60\n"
+        );
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
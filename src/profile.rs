@@ -0,0 +1,161 @@
+use std::path::Path;
+
+use crate::{
+    config_file_path, create_from_parsed, env::Overridden, format, load_optional,
+    warn_unknown_keys, ConfigError, ConfigOptional, Format, TryConfig, DEFAULT_SIZE_LIMIT,
+};
+
+/// The table holding one `[profile.<name>]` table per profile.
+pub const PROFILE_SECTION: &str = "profile";
+
+/// Like [`crate::try_create_config`], with the table `[profile.<profile>]` merged over the rest of
+/// the file, nested tables key by key. Without a profile only the rest of the file is used.
+/// Selecting a profile the file does not have fails with [`ConfigError::UnknownProfile`].
+pub fn create_config_with_profile<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    profile: Option<&str>,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let loaded = load_optional::<toml::Table>(
+        &config_file,
+        default_config,
+        Format::detect(&config_file).into(),
+        DEFAULT_SIZE_LIMIT,
+        true,
+        &mut Vec::new(),
+    )?;
+    let mut document = Overridden::from(toml::Value::Table(loaded.parsed_conf));
+    if let Some(selected) = take_profile(&config_file, &mut document, profile)? {
+        document.merge(selected);
+    }
+    let mut unknown_keys = Vec::new();
+    let parsed_conf: OptionalConfig =
+        format::from_value(&config_file, document, &mut unknown_keys)?;
+    warn_unknown_keys(&config_file, &unknown_keys);
+    create_from_parsed(&config_file, parsed_conf)
+}
+
+/// Removes the profiles of `document` and returns the selected one, which is merged over the rest.
+pub(crate) fn take_profile(
+    path: &Path,
+    document: &mut Overridden,
+    profile: Option<&str>,
+) -> Result<Option<Overridden>, ConfigError> {
+    let invalid = |section: String| ConfigError::InvalidSection(path.to_path_buf(), section);
+    let profiles = match document.remove(PROFILE_SECTION) {
+        Some(Overridden::Table(profiles)) => profiles,
+        Some(_) => return Err(invalid(PROFILE_SECTION.to_string())),
+        None => Vec::new(),
+    };
+    let Some(profile) = profile else {
+        return Ok(None);
+    };
+    let available = profiles.iter().map(|(name, _)| name.clone()).collect();
+    match profiles.into_iter().find(|(name, _)| name == profile) {
+        Some((_, selected @ Overridden::Table(_))) => Ok(Some(selected)),
+        Some(_) => Err(invalid(format!("{}.{}", PROFILE_SECTION, profile))),
+        None => Err(ConfigError::UnknownProfile {
+            path: path.to_path_buf(),
+            profile: profile.to_string(),
+            available,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde::Deserialize;
+
+    use crate::{
+        create_config_with_profile, test_support::test_dir, Config, ConfigError, ConfigOptional,
+    };
+
+    #[derive(Debug, Deserialize)]
+    struct OptMonitor {
+        scale: Option<f64>,
+        name: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OptConf {
+        font_size: Option<u32>,
+        monitor: Option<OptMonitor>,
+    }
+
+    impl ConfigOptional for OptConf {}
+
+    struct Conf {
+        font_size: u32,
+        scale: f64,
+        name: String,
+    }
+
+    impl Config<OptConf> for Conf {
+        fn create_from_optional(optional: OptConf) -> Self {
+            let monitor = optional.monitor.unwrap_or(OptMonitor {
+                scale: None,
+                name: None,
+            });
+            Conf {
+                font_size: optional.font_size.unwrap_or(12),
+                scale: monitor.scale.unwrap_or(1.0),
+                name: monitor.name.unwrap_or_default(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_with_profile() {
+        let config_dir = test_dir("test_profile");
+        fs::write(
+            config_dir.join("config.toml"),
+            "font_size = 14\n[monitor]\nscale = 1.0\nname = \"eDP-1\"\n\
+             [profile.desk]\nfont_size = 11\n[profile.desk.monitor]\nname = \"DP-1\"\n\
+             [profile.laptop.monitor]\nscale = 1.5\n",
+        )
+        .expect("Could not write test config.");
+        let load = |profile| {
+            create_config_with_profile::<Conf, OptConf>(&config_dir, "config.toml", "", profile)
+        };
+
+        let conf = load(None).expect("Could not load config.");
+        assert_eq!(
+            (conf.font_size, conf.scale, conf.name.as_str()),
+            (14, 1.0, "eDP-1")
+        );
+        let conf = load(Some("desk")).expect("Could not load profile.");
+        assert_eq!(
+            (conf.font_size, conf.scale, conf.name.as_str()),
+            (11, 1.0, "DP-1")
+        );
+        let conf = load(Some("laptop")).expect("Could not load profile.");
+        assert_eq!(
+            (conf.font_size, conf.scale, conf.name.as_str()),
+            (14, 1.5, "eDP-1")
+        );
+
+        let error = load(Some("tv")).err().expect("Unknown profile was loaded.");
+        match &error {
+            ConfigError::UnknownProfile {
+                profile, available, ..
+            } => {
+                assert_eq!(profile, "tv");
+                assert_eq!(
+                    available,
+                    &vec![String::from("desk"), String::from("laptop")]
+                );
+            }
+            _ => panic!("Expected an unknown profile, got {:?}", error),
+        }
+        assert!(error.to_string().contains("desk, laptop"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
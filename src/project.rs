@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+/// Searches `file_name`, e.g. `.myapp.toml`, in the current folder and its parents,
+/// like git does for `.gitignore`. See [`find_project_config_in`].
+pub fn find_project_config(file_name: &str) -> Option<PathBuf> {
+    let start = std::env::current_dir().ok()?;
+    find_project_config_in(&start, file_name, false)
+}
+
+/// Searches `file_name` in `start` and its parents, the closest one is returned.
+/// The search ends at the filesystem root and never leaves a home folder, so the folder
+/// containing the home folders of all users is not searched. With `stop_at_repository`,
+/// it also ends at the first folder containing `.git`.
+pub fn find_project_config_in(
+    start: &Path,
+    file_name: &str,
+    stop_at_repository: bool,
+) -> Option<PathBuf> {
+    let home = directories_next::BaseDirs::new().map(|base| base.home_dir().to_path_buf());
+    find_in(start, file_name, stop_at_repository, home.as_deref())
+}
+
+fn find_in(
+    start: &Path,
+    file_name: &str,
+    stop_at_repository: bool,
+    home: Option<&Path>,
+) -> Option<PathBuf> {
+    let homes = home.and_then(Path::parent);
+    for dir in start.ancestors() {
+        if Some(dir) == homes {
+            return None;
+        }
+        let candidate = dir.join(file_name);
+        if candidate.is_file() {
+            log_debug!("Found the project config {}", candidate.display());
+            return Some(candidate);
+        }
+        if stop_at_repository && dir.join(".git").exists() {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::find_in;
+    use crate::test_support::test_dir;
+
+    #[test]
+    fn test_find_project_config() {
+        let root = test_dir("test_project");
+        let project = root.join("home").join("user").join("project");
+        let nested = project.join("src").join("module");
+        fs::create_dir_all(&nested).expect("Could not create test folder.");
+        fs::create_dir_all(project.join(".git")).expect("Could not create test folder.");
+        let home = root.join("home").join("user");
+        let find = |stop_at_repository| find_in(&nested, ".myapp.toml", stop_at_repository, None);
+
+        assert_eq!(find(false), None);
+        fs::write(root.join(".myapp.toml"), "").expect("Could not write test config.");
+        assert_eq!(find(false), Some(root.join(".myapp.toml")));
+        assert_eq!(find(true), None);
+        assert_eq!(find_in(&nested, ".myapp.toml", false, Some(&home)), None);
+
+        fs::write(home.join(".myapp.toml"), "").expect("Could not write test config.");
+        assert_eq!(
+            find_in(&nested, ".myapp.toml", false, Some(&home)),
+            Some(home.join(".myapp.toml"))
+        );
+        fs::write(project.join(".myapp.toml"), "").expect("Could not write test config.");
+        assert_eq!(find(true), Some(project.join(".myapp.toml")));
+        fs::remove_dir_all(&root).expect("Could not remove testfolder again.");
+    }
+}
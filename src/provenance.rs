@@ -0,0 +1,165 @@
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf};
+
+use crate::env::Overridden;
+
+/// Where the value of a key came from, see [`Provenance`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueSource {
+    /// The default config, or a [`crate::Layer::Toml`].
+    Default,
+    /// A system-wide file like `/etc/xdg/myapp/config.toml`.
+    SystemFile(PathBuf),
+    /// The config file of the user.
+    UserFile(PathBuf),
+    /// Another file, e.g. a [`crate::Layer::File`] or `config.<hostname>.toml`.
+    File(PathBuf),
+    /// The table of the selected profile.
+    Profile(String),
+    /// The environment variable of this name.
+    EnvVar(String),
+    /// A `path=value` override, see [`crate::ConfigLoader::overrides`].
+    Override,
+}
+
+impl Display for ValueSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueSource::Default => f.write_str("the default config"),
+            ValueSource::SystemFile(path) => write!(f, "the system config {}", path.display()),
+            ValueSource::UserFile(path) | ValueSource::File(path) => {
+                write!(f, "{}", path.display())
+            }
+            ValueSource::Profile(profile) => write!(f, "the profile `{}`", profile),
+            ValueSource::EnvVar(name) => write!(f, "the environment variable {}", name),
+            ValueSource::Override => f.write_str("an override"),
+        }
+    }
+}
+
+/// The source of every value of a merged config by dotted key path, e.g. `window.width`.
+/// Arrays are tracked as a whole.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    sources: BTreeMap<String, ValueSource>,
+}
+
+impl Provenance {
+    /// The source of `key` or of the array containing it, e.g. `outputs` for `outputs.0.name`.
+    pub fn source(&self, key: &str) -> Option<&ValueSource> {
+        let mut key = key;
+        loop {
+            if let Some(source) = self.sources.get(key) {
+                return Some(source);
+            }
+            key = &key[..key.rfind('.')?];
+        }
+    }
+
+    /// Every key with its source, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ValueSource)> {
+        self.sources
+            .iter()
+            .map(|(key, source)| (key.as_str(), source))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Records the values of `document`, which is merged over everything recorded before.
+    /// Environment variables are recorded as [`ValueSource::EnvVar`] instead of `source`.
+    pub(crate) fn record(&mut self, document: &Overridden, source: &ValueSource) {
+        self.record_at(&mut Vec::new(), document, source);
+    }
+
+    pub(crate) fn record_table(&mut self, document: &toml::Table, source: &ValueSource) {
+        for (key, value) in document {
+            self.record_value(&mut vec![key.clone()], value, source);
+        }
+    }
+
+    fn record_at(&mut self, key: &mut Vec<String>, document: &Overridden, source: &ValueSource) {
+        match document {
+            Overridden::Table(entries) => {
+                for (name, entry) in entries {
+                    key.push(name.clone());
+                    self.record_at(key, entry, source);
+                    key.pop();
+                }
+            }
+            Overridden::Var(var) => self.insert(key, ValueSource::EnvVar(var.name.clone())),
+            Overridden::Value(value) => self.record_value(key, value, source),
+        }
+    }
+
+    fn record_value(&mut self, key: &mut Vec<String>, value: &toml::Value, source: &ValueSource) {
+        match value {
+            toml::Value::Table(table) => {
+                for (name, value) in table {
+                    key.push(name.clone());
+                    self.record_value(key, value, source);
+                    key.pop();
+                }
+            }
+            _ => self.insert(key, source.clone()),
+        }
+    }
+
+    /// A value replaces the tables it was merged over and the value of the table it is in.
+    fn insert(&mut self, key: &[String], source: ValueSource) {
+        let key = key.join(".");
+        self.remove(&key);
+        for (index, _) in key.match_indices('.') {
+            self.sources.remove(&key[..index]);
+        }
+        self.sources.insert(key, source);
+    }
+
+    /// Forgets `key` and the keys nested in it.
+    pub(crate) fn remove(&mut self, key: &str) {
+        let prefix = format!("{}.", key);
+        self.sources
+            .retain(|existing, _| existing != key && !existing.starts_with(&prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{env::Overridden, Provenance, ValueSource};
+
+    #[test]
+    fn test_provenance() {
+        let mut provenance = Provenance::default();
+        let defaults: toml::Table =
+            toml::from_str("timeout = 1\nwindow = 3\n[[outputs]]\nname = \"DP-1\"").unwrap();
+        provenance.record_table(&defaults, &ValueSource::Default);
+        let user: toml::Table = toml::from_str("[window]\nwidth = 2\nheight = 4").unwrap();
+        let user_file = ValueSource::UserFile("config.toml".into());
+        provenance.record_table(&user, &user_file);
+        let env = Overridden::new(
+            toml::Table::new(),
+            "APP",
+            [(String::from("APP_WINDOW__WIDTH"), String::from("5"))],
+        );
+        provenance.record(&env, &ValueSource::Override);
+
+        assert_eq!(provenance.source("timeout"), Some(&ValueSource::Default));
+        assert_eq!(provenance.source("window.height"), Some(&user_file));
+        assert_eq!(
+            provenance.source("window.width"),
+            Some(&ValueSource::EnvVar(String::from("APP_WINDOW__WIDTH")))
+        );
+        assert_eq!(provenance.source("window"), None);
+        assert_eq!(
+            provenance.source("outputs.0.name"),
+            Some(&ValueSource::Default)
+        );
+        assert_eq!(
+            provenance.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            vec!["outputs", "timeout", "window.height", "window.width"]
+        );
+        provenance.remove("window");
+        assert_eq!(provenance.source("window.height"), None);
+    }
+}
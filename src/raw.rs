@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use crate::{format::FormatRef, value::get_path, ConfigError, ParseError};
+
+/// The untyped document of a config file, for keys the config structs don't know about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawConfig {
+    path: PathBuf,
+    value: toml::Value,
+}
+
+impl RawConfig {
+    pub fn new(path: impl AsRef<Path>, value: toml::Value) -> Self {
+        RawConfig {
+            path: path.as_ref().to_path_buf(),
+            value,
+        }
+    }
+
+    /// Parses `contents` read from `path`, empty contents are an empty table.
+    pub(crate) fn parse(
+        path: &Path,
+        contents: &str,
+        format: FormatRef<'_>,
+    ) -> Result<Self, ConfigError> {
+        let table: toml::Table = format.parse(path, contents, &mut Vec::new())?;
+        Ok(RawConfig::new(path, toml::Value::Table(table)))
+    }
+
+    /// Looks up a dotted path like `plugins.clock.interval` or `outputs.0.name`.
+    /// Returns `None` if the key is missing and [`ConfigError::Parse`] if it has the wrong type.
+    pub fn get<T>(&self, key_path: &str) -> Result<Option<T>, ConfigError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = match get_path(&self.value, key_path) {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        };
+        match value.try_into() {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => {
+                let error =
+                    ParseError::new(&self.path, "", error).with_key_path(key_path.to_string());
+                Err(ConfigError::Parse(Box::new(error)))
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn value(&self) -> &toml::Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> toml::Value {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{ConfigError, Format, RawConfig};
+
+    #[test]
+    fn test_raw_get() {
+        let raw = RawConfig::parse(
+            Path::new("raw.toml"),
+            "[plugins.clock]\ninterval = 5\n[[outputs]]\nname = \"DP-1\"\n",
+            Format::Toml.into(),
+        )
+        .expect("Could not parse raw config.");
+        assert_eq!(raw.get::<u32>("plugins.clock.interval").unwrap(), Some(5));
+        assert_eq!(
+            raw.get::<String>("outputs.0.name").unwrap().as_deref(),
+            Some("DP-1")
+        );
+        assert_eq!(raw.get::<u32>("plugins.clock.missing").unwrap(), None);
+        assert_eq!(raw.get::<u32>("outputs.1.name").unwrap(), None);
+
+        let error = raw
+            .get::<String>("plugins.clock.interval")
+            .expect_err("Integer was read as a string.");
+        match &error {
+            ConfigError::Parse(parse) => {
+                assert_eq!(parse.key_path(), Some("plugins.clock.interval"))
+            }
+            _ => panic!("Expected a parse error, got {:?}", error),
+        }
+        assert!(error.to_string().contains("raw.toml"));
+
+        let empty = RawConfig::parse(Path::new("raw.toml"), "", Format::Toml.into())
+            .expect("Could not parse raw config.");
+        assert_eq!(empty.value(), &toml::Value::Table(toml::Table::new()));
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::Provenance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldStatus {
+    /// The field was set in the parsed file.
+    Present,
+    /// The field was absent and `create_from_optional` had to fall back, or the file was
+    /// empty and its value came from the default config.
+    Missing,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConfigLoadReport {
+    /// Dotted paths of keys that were present in the file but not used by the optional config,
+    /// e.g. `window.heigth`.
+    pub unknown_keys: Vec<String>,
+    /// Every field of the optional config in declaration order, nested sections as dotted paths.
+    /// Fields of a missing section are not listed.
+    pub fields: Vec<(String, FieldStatus)>,
+    /// Deprecated keys that were found in the file.
+    pub deprecations: Vec<DeprecationNotice>,
+    /// Copy of the unparsable config file that was made before falling back to the defaults.
+    pub backup: Option<PathBuf>,
+    /// The config file did not exist and was created, not set for existing empty files.
+    pub created: bool,
+    /// No config file was found and the default config was used without creating one.
+    pub file_missing: bool,
+    /// Fragments of the `config.d` folder that were merged, in the order they were applied.
+    pub fragments: Vec<PathBuf>,
+    /// Files of the `config.d` folder that were ignored, e.g. because they could not be parsed.
+    pub skipped_fragments: Vec<SkippedFragment>,
+    /// Where the path of the config file came from.
+    pub path_source: PathSource,
+    /// The files that were merged by a layered loader or with a system file,
+    /// from the least to the most important one.
+    pub consulted_files: Vec<PathBuf>,
+    /// The file of a [`crate::Layer::Project`] that was found.
+    pub project_file: Option<PathBuf>,
+    /// The file of [`crate::ConfigLoader::os_overrides`] that was merged over the config file.
+    pub os_file: Option<PathBuf>,
+    /// The file of [`crate::ConfigLoader::host_overrides`] that was merged over the config file.
+    pub host_file: Option<PathBuf>,
+    /// Where each value came from, if the loader merged several sources.
+    pub provenance: Provenance,
+}
+
+impl ConfigLoadReport {
+    pub fn field_status(&self, key: &str) -> Option<FieldStatus> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == key)
+            .map(|(_, status)| *status)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecationNotice {
+    pub old_key: String,
+    pub new_key: String,
+}
+
+impl Display for DeprecationNotice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` is deprecated, use `{}` instead",
+            self.old_key, self.new_key
+        )
+    }
+}
+
+/// Where the path of a loaded config file came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathSource {
+    /// The config folder and the file name, e.g. `~/.config/myapp/config.toml`.
+    #[default]
+    ConfigDir,
+    /// The environment variable of this name, see [`crate::ConfigLoader::path_env`].
+    EnvVar(String),
+    /// There is no user config, the file of [`crate::ConfigLoader::system_file`] was used instead.
+    SystemFile(PathBuf),
+}
+
+impl Display for PathSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathSource::ConfigDir => f.write_str("the config folder"),
+            PathSource::EnvVar(name) => write!(f, "the environment variable {}", name),
+            PathSource::SystemFile(path) => write!(f, "the system config {}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFragment {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl Display for SkippedFragment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Skipped {}: {}", self.path.display(), self.reason)
+    }
+}
+
+/// Checks the raw document for any of the `(old, new)` dotted key paths in `deprecated_keys`.
+pub(crate) fn find_deprecations(
+    document: &toml::Value,
+    deprecated_keys: &[(&str, &str)],
+) -> Vec<DeprecationNotice> {
+    deprecated_keys
+        .iter()
+        .filter(|(old_key, _)| crate::value::get_path(document, old_key).is_some())
+        .map(|(old_key, new_key)| DeprecationNotice {
+            old_key: old_key.to_string(),
+            new_key: new_key.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backup::free_bak_path, config_file_path, create_from_parsed, parse_default_config,
+    write::write_atomic, ConfigError, ConfigOptional, Format, TryConfig,
+};
+
+/// What [`reset_config`] would do, returned by [`reset_config_dry_run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResetPreview {
+    /// The contents that would be written to the config file.
+    pub contents: String,
+    /// Where the existing file would be backed up to, `None` if there is no file.
+    pub backup: Option<PathBuf>,
+}
+
+/// Backs up the config file to `<file>.bak`, or `<file>.bak.<n>` if that is taken,
+/// replaces it with `default_config` and returns the default config.
+pub fn reset_config<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    let config = parse_reset_default(&config_file, default_config)?;
+    if config_file.is_file() {
+        let backup = free_bak_path(&config_file);
+        if let Err(error) = fs::copy(&config_file, &backup) {
+            return Err(ConfigError::from_io(backup, error));
+        }
+    }
+    write_atomic(&config_file, default_config)?;
+    Ok(config)
+}
+
+/// Like [`reset_config`], but only reports what would be written without touching the disk.
+pub fn reset_config_dry_run<ConcreteConfig, OptionalConfig>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+) -> Result<ResetPreview, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let config_file = config_file_path(config_dir, config_file_name);
+    parse_reset_default::<ConcreteConfig, OptionalConfig>(&config_file, default_config)?;
+    let backup = if config_file.is_file() {
+        Some(free_bak_path(&config_file))
+    } else {
+        None
+    };
+    Ok(ResetPreview {
+        contents: default_config.to_string(),
+        backup,
+    })
+}
+
+fn parse_reset_default<ConcreteConfig, OptionalConfig>(
+    config_file: &Path,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let parsed_conf = parse_default_config(
+        config_file,
+        default_config,
+        Format::detect(config_file).into(),
+        &mut Vec::new(),
+    )?;
+    create_from_parsed(config_file, parsed_conf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        reset_config, reset_config_dry_run,
+        test_support::{test_dir, Conf, OptConf},
+        ConfigError,
+    };
+
+    #[test]
+    fn test_reset_config() {
+        let config_dir = test_dir("test_reset");
+        let config_file = config_dir.join("config.toml");
+        fs::write(&config_file, "something = ").expect("Could not write test config.");
+
+        let preview =
+            reset_config_dry_run::<Conf, OptConf>(&config_dir, "config.toml", "something = 1")
+                .expect("Could not preview reset.");
+        assert_eq!(preview.contents, "something = 1");
+        assert_eq!(preview.backup, Some(config_dir.join("config.toml.bak")));
+        assert_eq!(fs::read_to_string(&config_file).unwrap(), "something = ");
+
+        let conf = reset_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 1")
+            .expect("Could not reset config.");
+        assert_eq!(conf.something, 1);
+        assert_eq!(fs::read_to_string(&config_file).unwrap(), "something = 1");
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml.bak")).unwrap(),
+            "something = "
+        );
+
+        reset_config::<Conf, OptConf>(&config_dir, "config.toml", "something = 2")
+            .expect("Could not reset config.");
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml.bak")).unwrap(),
+            "something = "
+        );
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml.bak.1")).unwrap(),
+            "something = 1"
+        );
+
+        let error = reset_config::<Conf, OptConf>(&config_dir, "config.toml", "something = ")
+            .expect_err("Broken default config was written.");
+        assert!(matches!(error, ConfigError::DefaultConfigInvalid(_)));
+        assert_eq!(fs::read_to_string(&config_file).unwrap(), "something = 2");
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
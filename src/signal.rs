@@ -0,0 +1,136 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use signal_hook::{consts::SIGHUP, SigId};
+
+use crate::ConfigError;
+
+/// Set whenever the process receives `SIGHUP`, the conventional request of daemons to reload
+/// their config, e.g. via `kill -HUP`. The handler only sets a flag, other handlers of the
+/// signal, including the ones of the application, keep working. Unregistered once dropped.
+pub struct ReloadSignal {
+    flag: Arc<AtomicBool>,
+    id: SigId,
+}
+
+impl ReloadSignal {
+    pub fn new() -> Result<Self, ConfigError> {
+        let flag = Arc::new(AtomicBool::new(false));
+        let id = match signal_hook::flag::register(SIGHUP, Arc::clone(&flag)) {
+            Ok(id) => id,
+            Err(error) => return Err(ConfigError::Signal(error)),
+        };
+        Ok(ReloadSignal { flag, id })
+    }
+
+    /// Whether a `SIGHUP` arrived since the last call, e.g. to poll it from a main loop.
+    pub fn take(&self) -> bool {
+        self.flag.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl std::fmt::Debug for ReloadSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReloadSignal")
+            .field("pending", &self.flag.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ReloadSignal {
+    fn drop(&mut self) {
+        signal_hook::low_level::unregister(self.id);
+    }
+}
+
+/// Calls `forward` on a separate thread for every `SIGHUP` until it returns `false`
+/// or this is dropped.
+#[cfg(feature = "watch")]
+pub(crate) struct HupForwarder {
+    handle: signal_hook::iterator::Handle,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "watch")]
+impl HupForwarder {
+    pub(crate) fn spawn(
+        mut forward: impl FnMut() -> bool + Send + 'static,
+    ) -> Result<Self, ConfigError> {
+        let mut signals = match signal_hook::iterator::Signals::new([SIGHUP]) {
+            Ok(signals) => signals,
+            Err(error) => return Err(ConfigError::Signal(error)),
+        };
+        let handle = signals.handle();
+        let thread = std::thread::spawn(move || {
+            for _ in signals.forever() {
+                if !forward() {
+                    break;
+                }
+            }
+        });
+        Ok(HupForwarder {
+            handle,
+            thread: Some(thread),
+        })
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Drop for HupForwarder {
+    fn drop(&mut self) {
+        self.handle.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use signal_hook::{consts::SIGHUP, low_level::raise};
+
+    use crate::ReloadSignal;
+
+    // the only test raising SIGHUP, the handlers are registered before
+    #[test]
+    fn test_reload_signal() {
+        let signal = ReloadSignal::new().expect("Could not register handler.");
+        let other = ReloadSignal::new().expect("Could not register handler.");
+        assert!(!signal.take());
+        raise(SIGHUP).expect("Could not raise signal.");
+        assert!(signal.take());
+        assert!(!signal.take());
+        assert!(other.take());
+        drop(other);
+
+        #[cfg(feature = "watch")]
+        {
+            use std::{fs, sync::mpsc, time::Duration};
+
+            use crate::test_support::{test_dir, Conf, OptConf};
+
+            let config_dir = test_dir("test_signal");
+            let path = config_dir.join("config.toml");
+            fs::write(&path, "something = 2").expect("Could not write test config.");
+            let (sender, receiver) = mpsc::channel();
+            let mut watcher = crate::watch_config::<Conf, OptConf, _>(&path, "", move |result| {
+                let _ = sender.send(result);
+            })
+            .expect("Could not watch config.");
+            watcher
+                .reload_on_hup()
+                .expect("Could not register handler.");
+            raise(SIGHUP).expect("Could not raise signal.");
+            let reloaded = receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("No reload arrived.")
+                .expect("Could not reload config.");
+            assert_eq!(reloaded.something, 2);
+            assert!(signal.take());
+            drop(watcher);
+            fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{create_config_folder_in, Config, ConfigOptional};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct OptConf {
+    pub(crate) something: Option<u32>,
+}
+
+impl ConfigOptional for OptConf {}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct Conf {
+    pub(crate) something: u32,
+}
+
+impl Config<OptConf> for Conf {
+    fn create_from_optional(optional: OptConf) -> Self {
+        Conf {
+            something: optional.something.unwrap_or(1),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct OptWhatConf {
+    pub(crate) something: Option<u32>,
+    pub(crate) what: Option<String>,
+}
+
+impl ConfigOptional for OptWhatConf {}
+
+/// [`Conf`] with a string, `what` falls back to `pingpang`.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub(crate) struct WhatConf {
+    pub(crate) something: u32,
+    pub(crate) what: String,
+}
+
+impl Config<OptWhatConf> for WhatConf {
+    fn create_from_optional(optional: OptWhatConf) -> Self {
+        WhatConf {
+            something: optional.something.unwrap_or_default(),
+            what: optional.what.unwrap_or_else(|| String::from("pingpang")),
+        }
+    }
+}
+
+/// An empty config folder below the temporary folder, so tests don't write into `~/.config`.
+/// Leftovers of an earlier run are removed.
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let _ = fs::remove_dir_all(std::env::temp_dir().join(format!("oxilib_{}", name)));
+    create_config_folder_in(&std::env::temp_dir(), format!("oxilib_{}", name))
+}
@@ -0,0 +1,117 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{ensure_dir, read_specific_css, ConfigError};
+
+/// The folder below the config folder that themes are read from, one `*.css` file per theme.
+pub const THEMES_DIR: &str = "themes";
+
+/// The themes folder of `config_dir`, created if it does not exist yet.
+pub fn themes_dir(config_dir: &Path) -> Result<PathBuf, ConfigError> {
+    ensure_dir(config_dir.join(THEMES_DIR))
+}
+
+/// The names of the themes in `config_dir`, the file stems of the `*.css` files of
+/// [`THEMES_DIR`], sorted. A missing folder has no themes and is not created, a folder that
+/// can't be read has none either, the reason is logged.
+pub fn list_themes(config_dir: &Path) -> Vec<String> {
+    match read_themes(config_dir) {
+        Ok(themes) => themes,
+        Err(error) => {
+            log_warn!("Could not list themes: {}", error);
+            Vec::new()
+        }
+    }
+}
+
+/// Reads the theme `name` of `config_dir`, failing with [`ConfigError::UnknownTheme`]
+/// if there is no such theme.
+pub fn load_theme(config_dir: &Path, name: &str) -> Result<String, ConfigError> {
+    let available = read_themes(config_dir)?;
+    if !available.iter().any(|theme| theme == name) {
+        return Err(ConfigError::UnknownTheme {
+            path: config_dir.join(THEMES_DIR),
+            theme: name.to_string(),
+            available,
+        });
+    }
+    read_specific_css(config_dir.join(THEMES_DIR).join(format!("{}.css", name)))
+}
+
+/// Like [`load_theme`], returning `fallback`, e.g. a theme shipped with the application,
+/// as long as no theme was added to the folder.
+pub fn load_theme_or(config_dir: &Path, name: &str, fallback: &str) -> Result<String, ConfigError> {
+    match load_theme(config_dir, name) {
+        Err(ConfigError::UnknownTheme { available, .. }) if available.is_empty() => {
+            Ok(fallback.to_string())
+        }
+        result => result,
+    }
+}
+
+fn read_themes(config_dir: &Path) -> Result<Vec<String>, ConfigError> {
+    let dir = config_dir.join(THEMES_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(ConfigError::from_io(dir, error)),
+    };
+    let mut themes = Vec::new();
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(error) => return Err(ConfigError::from_io(dir, error)),
+        };
+        if path.extension().is_some_and(|extension| extension == "css") && path.is_file() {
+            if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                themes.push(stem.to_string());
+            }
+        }
+    }
+    themes.sort();
+    Ok(themes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        list_themes, load_theme, load_theme_or, test_support::test_dir, themes_dir, ConfigError,
+        THEMES_DIR,
+    };
+
+    #[test]
+    fn test_themes() {
+        let config_dir = test_dir("test_themes");
+        let dir = config_dir.join(THEMES_DIR);
+        assert!(list_themes(&config_dir).is_empty());
+        let theme = load_theme_or(&config_dir, "nord", "* { color: black; }")
+            .expect("Could not load fallback theme.");
+        assert_eq!(theme, "* { color: black; }");
+        assert!(!dir.exists());
+        assert_eq!(
+            themes_dir(&config_dir).expect("Could not create themes folder."),
+            dir
+        );
+        assert!(dir.is_dir());
+
+        fs::write(dir.join("nord.css"), "* { color: blue; }").expect("Could not write theme.");
+        fs::write(dir.join("gruvbox.css"), "* { color: brown; }").expect("Could not write theme.");
+        fs::write(dir.join("notes.txt"), "").expect("Could not write file.");
+        assert_eq!(list_themes(&config_dir), vec!["gruvbox", "nord"]);
+        let theme = load_theme(&config_dir, "nord").expect("Could not load theme.");
+        assert_eq!(theme, "* { color: blue; }");
+
+        let error = load_theme_or(&config_dir, "../style", "").expect_err("Unknown theme loaded.");
+        assert!(matches!(
+            &error,
+            ConfigError::UnknownTheme { theme, available, .. }
+                if theme == "../style" && available == &["gruvbox", "nord"]
+        ));
+        assert!(error.to_string().contains("gruvbox, nord"));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{ArrayMerge, ConfigError};
+
+/// Looks up a dotted path like `window.margins.top` or `outputs.0.name`.
+pub(crate) fn get_path<'a>(value: &'a toml::Value, path: &str) -> Option<&'a toml::Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        toml::Value::Table(table) => table.get(key),
+        toml::Value::Array(array) => key.parse::<usize>().ok().and_then(|index| array.get(index)),
+        _ => None,
+    })
+}
+
+/// Merges `overlay` into `base`, tables are merged recursively and any other value is replaced.
+pub(crate) fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Like [`merge`], arrays at the dotted paths of `strategies` are combined with their strategy.
+pub(crate) fn merge_with(
+    base: &mut toml::Table,
+    overlay: toml::Table,
+    strategies: &[(String, ArrayMerge)],
+    path: &mut Vec<String>,
+) -> Result<(), ConfigError> {
+    for (key, value) in overlay {
+        path.push(key.clone());
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_with(base, overlay, strategies, path)?
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(overlay)) => {
+                merge_arrays(base, overlay, strategies, path)?
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Combines the arrays at `path` with the strategy registered for it, [`ArrayMerge::Replace`]
+/// if there is none.
+pub(crate) fn merge_arrays(
+    base: &mut Vec<toml::Value>,
+    overlay: Vec<toml::Value>,
+    strategies: &[(String, ArrayMerge)],
+    path: &[String],
+) -> Result<(), ConfigError> {
+    let path = path.join(".");
+    let strategy = strategies
+        .iter()
+        .rev()
+        .find(|(strategy_path, _)| *strategy_path == path)
+        .map(|(_, strategy)| strategy);
+    match strategy {
+        None | Some(ArrayMerge::Replace) => *base = overlay,
+        Some(ArrayMerge::Append) => base.extend(overlay),
+        Some(ArrayMerge::MergeByKey(key)) => {
+            let entry_key = |entry: &toml::Value| entry.as_table()?.get(key).cloned();
+            if base
+                .iter()
+                .chain(&overlay)
+                .any(|entry| entry_key(entry).is_none())
+            {
+                return Err(ConfigError::MissingMergeKey(path, key.clone()));
+            }
+            for entry in overlay {
+                let entry_key = entry_key(&entry);
+                let existing = base.iter_mut().find(|existing| {
+                    existing.as_table().and_then(|table| table.get(key)) == entry_key.as_ref()
+                });
+                match (existing, entry) {
+                    (Some(toml::Value::Table(existing)), toml::Value::Table(entry)) => {
+                        merge(existing, entry)
+                    }
+                    (_, entry) => base.push(entry),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArrayMerge, ConfigError};
+
+    use super::merge_with;
+
+    fn table(toml: &str) -> toml::Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_merge_with() {
+        let strategies = vec![
+            (String::from("extra_css_files"), ArrayMerge::Append),
+            (
+                String::from("outputs"),
+                ArrayMerge::MergeByKey(String::from("name")),
+            ),
+        ];
+        let mut base = table(
+            "blocks = [\"clock\", \"tray\"]\nextra_css_files = [\"a.css\"]\n\
+             [[outputs]]\nname = \"DP-1\"\nscale = 1\n[[outputs]]\nname = \"DP-2\"\nscale = 1",
+        );
+        let overlay = table(
+            "blocks = [\"battery\"]\nextra_css_files = [\"b.css\"]\n\
+             [[outputs]]\nname = \"DP-2\"\nscale = 2\n[[outputs]]\nname = \"HDMI-A-1\"",
+        );
+        merge_with(&mut base, overlay, &strategies, &mut Vec::new()).expect("Could not merge.");
+        let expected = table(
+            "blocks = [\"battery\"]\nextra_css_files = [\"a.css\", \"b.css\"]\n\
+             [[outputs]]\nname = \"DP-1\"\nscale = 1\n[[outputs]]\nname = \"DP-2\"\nscale = 2\n\
+             [[outputs]]\nname = \"HDMI-A-1\"",
+        );
+        assert_eq!(base, expected);
+
+        let overlay = table("[[outputs]]\nscale = 3");
+        let error = merge_with(&mut base, overlay, &strategies, &mut Vec::new())
+            .expect_err("Entry without a key was merged.");
+        assert!(
+            matches!(&error, ConfigError::MissingMergeKey(path, key) if path == "outputs" && key == "name")
+        );
+        assert!(error.to_string().contains("`name`"));
+    }
+}
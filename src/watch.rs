@@ -0,0 +1,1167 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, RecvError, RecvTimeoutError, TryRecvError},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
+
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use serde::Serialize;
+
+use crate::{
+    create_from_parsed, diff::to_value, load_optional, read_specific_css, ConfigChange,
+    ConfigError, ConfigOptional, Format, TryConfig, DEFAULT_SIZE_LIMIT,
+};
+
+/// How long [`watch_config`] waits for further changes before reloading.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// How often [`WatchMode::Poll`] checks the file by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How changes of a file are noticed, see [`watch_config_with_options`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WatchMode {
+    /// The native watcher of the platform, polling with [`DEFAULT_POLL_INTERVAL`] if it can't
+    /// be initialized.
+    #[default]
+    Auto,
+    Native,
+    /// Compares the modification time and size of the file every interval, for file systems
+    /// without change notifications like NFS.
+    Poll(Duration),
+}
+
+/// The way a [`ConfigWatcher`] notices changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchBackend {
+    Native,
+    Poll(Duration),
+}
+
+/// How [`watch_config_with_options`] watches a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchOptions {
+    pub debounce: Duration,
+    pub mode: WatchMode,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            debounce: DEFAULT_DEBOUNCE,
+            mode: WatchMode::Auto,
+        }
+    }
+}
+
+/// Dropping a backend stops watching.
+enum Backend {
+    // only held to keep watching
+    #[allow(dead_code)]
+    Native(RecommendedWatcher),
+    Poll(Poller),
+}
+
+/// Stats the files every interval on a separate thread until dropped.
+struct Poller {
+    interval: Duration,
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Poller {
+    fn spawn(
+        paths: Vec<PathBuf>,
+        interval: Duration,
+        changed: impl Fn(&Path) + Send + 'static,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        // taken before returning, so changes right after are noticed
+        let mut stamps: Vec<_> = paths
+            .into_iter()
+            .map(|path| {
+                let stamp = stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                for (path, last) in &mut stamps {
+                    let current = stamp(path);
+                    if current != *last {
+                        *last = current;
+                        changed(path);
+                    }
+                }
+            }
+        });
+        Poller {
+            interval,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The modification time and size of `path`, `None` if it doesn't exist.
+fn stamp(path: &Path) -> Option<(Option<SystemTime>, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+/// Watches a config file until it is dropped, see [`watch_config`].
+pub struct ConfigWatcher {
+    // never empty
+    paths: Vec<PathBuf>,
+    watcher: Option<Backend>,
+    // lets other sources than the watcher trigger a reload
+    sender: Option<mpsc::Sender<PathBuf>>,
+    #[cfg(all(unix, feature = "signal"))]
+    hup: Option<crate::signal::HupForwarder>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// The watched file, the folder for [`watch_dir`] or the config for
+    /// [`watch_config_and_css`].
+    pub fn path(&self) -> &Path {
+        &self.paths[0]
+    }
+
+    /// How changes are noticed, e.g. whether [`WatchMode::Auto`] fell back to polling.
+    pub fn backend(&self) -> WatchBackend {
+        match &self.watcher {
+            Some(Backend::Poll(poller)) => WatchBackend::Poll(poller.interval),
+            _ => WatchBackend::Native,
+        }
+    }
+
+    /// Also reloads the config whenever the process receives `SIGHUP`, exactly like after a
+    /// change of every watched file, for [`watch_dir`] the folder is passed to the callback.
+    /// Other handlers of the signal keep working.
+    #[cfg(all(unix, feature = "signal"))]
+    pub fn reload_on_hup(&mut self) -> Result<(), ConfigError> {
+        if self.hup.is_some() {
+            return Ok(());
+        }
+        let Some(sender) = self.sender.clone() else {
+            return Ok(());
+        };
+        let paths = self.paths.clone();
+        self.hup = Some(crate::signal::HupForwarder::spawn(move || {
+            paths.iter().all(|path| sender.send(path.clone()).is_ok())
+        })?);
+        Ok(())
+    }
+
+    fn spawn(
+        paths: Vec<PathBuf>,
+        watcher: Backend,
+        sender: mpsc::Sender<PathBuf>,
+        worker: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        ConfigWatcher {
+            paths,
+            watcher: Some(watcher),
+            sender: Some(sender),
+            #[cfg(all(unix, feature = "signal"))]
+            hup: None,
+            worker: Some(std::thread::spawn(worker)),
+        }
+    }
+}
+
+impl std::fmt::Debug for ConfigWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigWatcher")
+            .field("paths", &self.paths)
+            .field("backend", &self.backend())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        // the worker stops once every sender is gone
+        #[cfg(all(unix, feature = "signal"))]
+        self.hup.take();
+        self.sender.take();
+        self.watcher.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Loads the config at `path` again whenever it changes and passes the result to `callback`,
+/// on a separate thread. The file is never created, a missing one is not reported.
+/// The folder of the file is watched, so saves that replace the file, like the ones of most
+/// editors, are noticed as well. Changes within [`DEFAULT_DEBOUNCE`] of each other are
+/// reloaded once.
+pub fn watch_config<ConcreteConfig, OptionalConfig, Callback>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+    callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+    Callback: FnMut(Result<ConcreteConfig, ConfigError>) + Send + 'static,
+{
+    watch_config_with_debounce(path, default_config, DEFAULT_DEBOUNCE, callback)
+}
+
+/// Like [`watch_config`], the file is reloaded once no change arrived for `debounce`.
+/// Every further change restarts the wait, so a burst of writes results in one reload.
+pub fn watch_config_with_debounce<ConcreteConfig, OptionalConfig, Callback>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+    debounce: Duration,
+    callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+    Callback: FnMut(Result<ConcreteConfig, ConfigError>) + Send + 'static,
+{
+    let options = WatchOptions {
+        debounce,
+        ..WatchOptions::default()
+    };
+    watch_config_with_options(path, default_config, options, callback)
+}
+
+/// Like [`watch_config`], with the debounce and the way changes are noticed taken from
+/// `options`, e.g. to poll a file on NFS.
+pub fn watch_config_with_options<ConcreteConfig, OptionalConfig, Callback>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+    options: WatchOptions,
+    mut callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+    Callback: FnMut(Result<ConcreteConfig, ConfigError>) + Send + 'static,
+{
+    let default_config = default_config.to_string();
+    start_watching(
+        vec![path.as_ref().to_path_buf()],
+        options.mode,
+        move |receiver| {
+            let mut debouncer = Debouncer::new(options.debounce);
+            while debouncer.wait(&receiver) {
+                for config_file in debouncer.take_due() {
+                    // replacing a file removes it for a moment
+                    if config_file.is_file() {
+                        callback(load::<ConcreteConfig, OptionalConfig>(
+                            &config_file,
+                            &default_config,
+                        ));
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// A successful reload of [`watch_config_with_changes`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ConfigReload<ConcreteConfig> {
+    pub config: ConcreteConfig,
+    /// Compared to the previous reload, or the config when watching started.
+    pub change: ConfigChange,
+}
+
+/// Like [`watch_config`], additionally reporting which keys the reload changed, see
+/// [`ConfigChange::between`]. Reloads that changed nothing, e.g. after saving the file
+/// without modifications, are reported as well.
+pub fn watch_config_with_changes<ConcreteConfig, OptionalConfig, Callback>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+    mut callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + Serialize,
+    OptionalConfig: ConfigOptional,
+    Callback: FnMut(Result<ConfigReload<ConcreteConfig>, ConfigError>) + Send + 'static,
+{
+    let path = path.as_ref();
+    // a file that can't be loaded yet counts as empty
+    let mut previous = load::<ConcreteConfig, OptionalConfig>(path, default_config)
+        .and_then(|config| to_value(&config))
+        .unwrap_or_else(|_| toml::Value::Table(toml::Table::new()));
+    watch_config::<ConcreteConfig, OptionalConfig, _>(path, default_config, move |result| {
+        let reload = result.and_then(|config| {
+            let value = to_value(&config)?;
+            let change = ConfigChange::between_values(&previous, &value);
+            previous = value;
+            Ok(ConfigReload { config, change })
+        });
+        callback(reload);
+    })
+}
+
+/// Like [`watch_config`], reloads resulting in a config equal to the previous one are not
+/// passed to `callback`, errors always are.
+pub fn watch_config_if_changed<ConcreteConfig, OptionalConfig, Callback>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+    mut callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + PartialEq + Clone + Send + 'static,
+    OptionalConfig: ConfigOptional,
+    Callback: FnMut(Result<ConcreteConfig, ConfigError>) + Send + 'static,
+{
+    let path = path.as_ref();
+    let mut previous = load::<ConcreteConfig, OptionalConfig>(path, default_config).ok();
+    watch_config::<ConcreteConfig, OptionalConfig, _>(path, default_config, move |result| {
+        if let Ok(config) = &result {
+            if previous.as_ref() == Some(config) {
+                return;
+            }
+            previous = Some(config.clone());
+        }
+        callback(result);
+    })
+}
+
+/// Reads the css file at `path` again whenever it changes, like [`read_specific_css`], and
+/// passes the contents to `callback`, with the debounce and handling of replaced files of
+/// [`watch_config`].
+pub fn watch_css<Callback>(
+    path: impl AsRef<Path>,
+    mut callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    Callback: FnMut(Result<String, ConfigError>) + Send + 'static,
+{
+    let paths = vec![path.as_ref().to_path_buf()];
+    start_watching(paths, WatchMode::Auto, move |receiver| {
+        let mut debouncer = Debouncer::new(DEFAULT_DEBOUNCE);
+        while debouncer.wait(&receiver) {
+            for css_file in debouncer.take_due() {
+                if css_file.is_file() {
+                    callback(read_specific_css(&css_file));
+                }
+            }
+        }
+    })
+}
+
+/// A change reported by [`watch_config_and_css`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WatchedChange<ConcreteConfig> {
+    Config(Result<ConcreteConfig, ConfigError>),
+    Css(Result<String, ConfigError>),
+}
+
+/// Combines [`watch_config`] and [`watch_css`] in one watcher, the changes of both files are
+/// passed to `callback` tagged with the file they come from.
+pub fn watch_config_and_css<ConcreteConfig, OptionalConfig, Callback>(
+    config_path: impl AsRef<Path>,
+    default_config: &str,
+    css_path: impl AsRef<Path>,
+    mut callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+    Callback: FnMut(WatchedChange<ConcreteConfig>) + Send + 'static,
+{
+    let default_config = default_config.to_string();
+    let config_path = config_path.as_ref().to_path_buf();
+    let paths = vec![config_path.clone(), css_path.as_ref().to_path_buf()];
+    start_watching(paths, WatchMode::Auto, move |receiver| {
+        let mut debouncer = Debouncer::new(DEFAULT_DEBOUNCE);
+        while debouncer.wait(&receiver) {
+            for file in debouncer.take_due() {
+                if !file.is_file() {
+                    continue;
+                }
+                callback(if file == config_path {
+                    WatchedChange::Config(load::<ConcreteConfig, OptionalConfig>(
+                        &file,
+                        &default_config,
+                    ))
+                } else {
+                    WatchedChange::Css(read_specific_css(&file))
+                });
+            }
+        }
+    })
+}
+
+/// A change of a file watched by [`watch_config_channel`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigEvent<ConcreteConfig> {
+    Reloaded(ConcreteConfig),
+    /// The file changed but could not be loaded, e.g. because it can't be parsed.
+    ParseError(ConfigError),
+    /// The file was removed and did not come back within the debounce.
+    FileRemoved,
+}
+
+/// Like [`watch_config`], the changes are sent to the returned receiver instead, e.g. to poll it
+/// from a main loop. The watching thread never waits for the receiver: an event the receiver
+/// did not take yet is replaced by the next one, so it only ever sees the latest change.
+/// Dropping the receiver stops the thread with the next change, dropping the
+/// [`ConfigWatcher`] stops watching.
+pub fn watch_config_channel<ConcreteConfig, OptionalConfig>(
+    path: impl AsRef<Path>,
+    default_config: &str,
+) -> Result<(ConfigWatcher, ConfigEvents<ConcreteConfig>), ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + Send + 'static,
+    OptionalConfig: ConfigOptional,
+{
+    let default_config = default_config.to_string();
+    let latest = Arc::new(Latest {
+        slot: Mutex::new(Slot {
+            event: None,
+            disconnected: false,
+        }),
+        wakeup: Condvar::new(),
+    });
+    let sender = LatestSender(latest.clone());
+    let paths = vec![path.as_ref().to_path_buf()];
+    let watcher = start_watching(paths, WatchMode::Auto, move |receiver| {
+        let mut debouncer = Debouncer::new(DEFAULT_DEBOUNCE);
+        while debouncer.wait(&receiver) {
+            for config_file in debouncer.take_due() {
+                let event = if config_file.is_file() {
+                    match load::<ConcreteConfig, OptionalConfig>(&config_file, &default_config) {
+                        Ok(config) => ConfigEvent::Reloaded(config),
+                        Err(error) => ConfigEvent::ParseError(error),
+                    }
+                } else {
+                    ConfigEvent::FileRemoved
+                };
+                if !sender.send(event) {
+                    return;
+                }
+            }
+        }
+    })?;
+    Ok((watcher, ConfigEvents { latest }))
+}
+
+/// The receiver of [`watch_config_channel`], holding only the latest [`ConfigEvent`].
+/// Receiving fails with `Disconnected` once the [`ConfigWatcher`] is dropped and the
+/// last event was taken.
+pub struct ConfigEvents<ConcreteConfig> {
+    latest: Arc<Latest<ConfigEvent<ConcreteConfig>>>,
+}
+
+impl<ConcreteConfig> ConfigEvents<ConcreteConfig> {
+    /// Takes the latest event without waiting.
+    pub fn try_recv(&self) -> Result<ConfigEvent<ConcreteConfig>, TryRecvError> {
+        let mut slot = self.latest.lock();
+        match slot.event.take() {
+            Some(event) => Ok(event),
+            None if slot.disconnected => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Waits for the next event.
+    pub fn recv(&self) -> Result<ConfigEvent<ConcreteConfig>, RecvError> {
+        self.wait(None).map_err(|_| RecvError)
+    }
+
+    /// Waits for the next event for at most `timeout`.
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<ConfigEvent<ConcreteConfig>, RecvTimeoutError> {
+        self.wait(Some(Instant::now() + timeout))
+    }
+
+    fn wait(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<ConfigEvent<ConcreteConfig>, RecvTimeoutError> {
+        let mut slot = self.latest.lock();
+        loop {
+            if let Some(event) = slot.event.take() {
+                return Ok(event);
+            }
+            if slot.disconnected {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            slot = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    match self.latest.wakeup.wait_timeout(slot, deadline - now) {
+                        Ok((slot, _)) => slot,
+                        Err(error) => error.into_inner().0,
+                    }
+                }
+                None => self
+                    .latest
+                    .wakeup
+                    .wait(slot)
+                    .unwrap_or_else(|error| error.into_inner()),
+            };
+        }
+    }
+}
+
+impl<ConcreteConfig> std::fmt::Debug for ConfigEvents<ConcreteConfig> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigEvents").finish_non_exhaustive()
+    }
+}
+
+/// The event shared by the watching thread and [`ConfigEvents`].
+struct Latest<T> {
+    slot: Mutex<Slot<T>>,
+    wakeup: Condvar,
+}
+
+struct Slot<T> {
+    event: Option<T>,
+    /// Set once the watching thread stopped.
+    disconnected: bool,
+}
+
+impl<T> Latest<T> {
+    fn lock(&self) -> MutexGuard<'_, Slot<T>> {
+        self.slot.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+/// The side of the watching thread, marks the slot as disconnected when dropped.
+struct LatestSender<T>(Arc<Latest<T>>);
+
+impl<T> LatestSender<T> {
+    /// Replaces the event that was not taken yet, `false` if the receiver is gone.
+    fn send(&self, event: T) -> bool {
+        if Arc::strong_count(&self.0) == 1 {
+            return false;
+        }
+        self.0.lock().event = Some(event);
+        self.0.wakeup.notify_one();
+        true
+    }
+}
+
+impl<T> Drop for LatestSender<T> {
+    fn drop(&mut self) {
+        self.0.lock().disconnected = true;
+        self.0.wakeup.notify_all();
+    }
+}
+
+/// Runs `worker` on a new thread with the changed files of `paths`.
+fn start_watching(
+    paths: Vec<PathBuf>,
+    mode: WatchMode,
+    worker: impl FnOnce(Receiver<PathBuf>) + Send + 'static,
+) -> Result<ConfigWatcher, ConfigError> {
+    let (sender, receiver) = mpsc::channel();
+    let changed = sender.clone();
+    let changed = move |path: &Path| {
+        let _ = changed.send(path.to_path_buf());
+    };
+    let watcher = match mode {
+        WatchMode::Native => Backend::Native(files_watcher(&paths, changed)?),
+        WatchMode::Poll(interval) => Backend::Poll(Poller::spawn(paths.clone(), interval, changed)),
+        WatchMode::Auto => match files_watcher(&paths, changed.clone()) {
+            Ok(watcher) => Backend::Native(watcher),
+            Err(error) => {
+                log_warn!("{}, polling instead", error);
+                Backend::Poll(Poller::spawn(paths.clone(), DEFAULT_POLL_INTERVAL, changed))
+            }
+        },
+    };
+    Ok(ConfigWatcher::spawn(paths, watcher, sender, move || {
+        worker(receiver)
+    }))
+}
+
+/// Watches the folders of `paths` and calls `changed` with the file on the thread of notify
+/// whenever one of them changes, until the returned watcher is dropped.
+/// Files are told apart by their name.
+pub(crate) fn files_watcher(
+    paths: &[PathBuf],
+    changed: impl Fn(&Path) + Send + 'static,
+) -> Result<RecommendedWatcher, ConfigError> {
+    let mut files = Vec::new();
+    let mut dirs: Vec<(PathBuf, &Path)> = Vec::new();
+    for path in paths {
+        let Some(file_name) = path.file_name().map(OsString::from) else {
+            return Err(ConfigError::NotAFile(path.to_path_buf()));
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        if !dirs.iter().any(|(known, _)| *known == dir) {
+            dirs.push((dir, path));
+        }
+        files.push((file_name, path.clone()));
+    }
+    let mut watcher = match notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            for (file_name, path) in &files {
+                if concerns(&event, file_name) {
+                    changed(path);
+                }
+            }
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(error) => return Err(ConfigError::Watch(paths[0].clone(), Box::new(error))),
+    };
+    for (dir, path) in dirs {
+        if let Err(error) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            return Err(ConfigError::Watch(path.to_path_buf(), Box::new(error)));
+        }
+    }
+    Ok(watcher)
+}
+
+/// Coalesces the events of every path until none arrived for `debounce`.
+struct Debouncer {
+    debounce: Duration,
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    fn new(debounce: Duration) -> Self {
+        Debouncer {
+            debounce,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Waits for the next event or until a path is due.
+    /// Returns `false` once the sender is gone, pending events are dropped with it.
+    fn wait(&mut self, receiver: &Receiver<PathBuf>) -> bool {
+        let received = match self.pending.values().copied().min() {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(path) => {
+                self.pending.insert(path, Instant::now() + self.debounce);
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => true,
+            Err(RecvTimeoutError::Disconnected) => false,
+        }
+    }
+
+    /// The paths without events for `debounce`, sorted.
+    fn take_due(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let mut due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        due.sort();
+        for path in &due {
+            self.pending.remove(path);
+        }
+        due
+    }
+}
+
+/// Whether `event` changed the file, reading the file or its metadata does not.
+fn concerns(event: &Event, file_name: &OsString) -> bool {
+    changes(event)
+        && event
+            .paths
+            .iter()
+            .any(|path| path.file_name() == Some(file_name.as_os_str()))
+}
+
+/// Whether `event` changed its paths, reading them or their metadata does not.
+fn changes(event: &Event) -> bool {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Any | EventKind::Other => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        EventKind::Access(_) => false,
+    }
+}
+
+/// Watches `dir` and everything in it, including files and folders created later, and passes
+/// the path of every changed, created or removed entry to `callback`, on a separate thread.
+/// Entries matching one of the `ignore` globs are skipped, e.g. `*.swp` or `*~` for the
+/// temporary files of editors. `*` matches any part of a name and `?` a single character,
+/// globs without a `/` are matched against the name of the entry, the others against its path
+/// relative to `dir`. Changes of a path within [`DEFAULT_DEBOUNCE`] of each other are reported
+/// once.
+pub fn watch_dir<Callback>(
+    dir: impl AsRef<Path>,
+    ignore: &[&str],
+    mut callback: Callback,
+) -> Result<ConfigWatcher, ConfigError>
+where
+    Callback: FnMut(&Path) + Send + 'static,
+{
+    let dir = dir.as_ref().to_path_buf();
+    let ignore: Vec<String> = ignore.iter().map(|glob| glob.to_string()).collect();
+    let (sender, receiver) = mpsc::channel();
+    let root = dir.clone();
+    let changed = sender.clone();
+    let mut watcher = match notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if !changes(&event) {
+            return;
+        }
+        for path in event.paths {
+            if !is_ignored(&root, &path, &ignore) {
+                let _ = changed.send(path);
+            }
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(error) => return Err(ConfigError::Watch(dir, Box::new(error))),
+    };
+    if let Err(error) = watcher.watch(&dir, RecursiveMode::Recursive) {
+        return Err(ConfigError::Watch(dir, Box::new(error)));
+    }
+    Ok(ConfigWatcher::spawn(
+        vec![dir],
+        Backend::Native(watcher),
+        sender,
+        move || {
+            let mut debouncer = Debouncer::new(DEFAULT_DEBOUNCE);
+            while debouncer.wait(&receiver) {
+                for path in debouncer.take_due() {
+                    callback(&path);
+                }
+            }
+        },
+    ))
+}
+
+fn is_ignored(root: &Path, path: &Path, ignore: &[String]) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let name = relative.file_name().unwrap_or_default().to_string_lossy();
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    ignore.iter().any(|glob| {
+        let target = if glob.contains('/') {
+            &relative
+        } else {
+            &*name
+        };
+        glob_matches(glob.as_bytes(), target.as_bytes())
+    })
+}
+
+/// `*` matches any characters except `/`, `?` a single one.
+fn glob_matches(glob: &[u8], text: &[u8]) -> bool {
+    match glob.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len())
+            .take_while(|&skipped| skipped == 0 || text[skipped - 1] != b'/')
+            .any(|skipped| glob_matches(rest, &text[skipped..])),
+        Some((b'?', rest)) => {
+            matches!(text.split_first(), Some((c, text)) if *c != b'/' && glob_matches(rest, text))
+        }
+        Some((c, rest)) => {
+            matches!(text.split_first(), Some((t, text)) if t == c && glob_matches(rest, text))
+        }
+    }
+}
+
+fn load<ConcreteConfig, OptionalConfig>(
+    path: &Path,
+    default_config: &str,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig>,
+    OptionalConfig: ConfigOptional,
+{
+    let loaded = load_optional::<OptionalConfig>(
+        path,
+        default_config,
+        Format::detect(path).into(),
+        DEFAULT_SIZE_LIMIT,
+        false,
+        &mut Vec::new(),
+    )?;
+    create_from_parsed(path, loaded.parsed_conf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::mpsc::{self, RecvTimeoutError, TryRecvError},
+        time::{Duration, Instant},
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        test_support::{test_dir, Conf, OptConf},
+        watch_config, watch_config_and_css, watch_config_channel, watch_config_if_changed,
+        watch_config_with_changes, watch_config_with_options, watch_css, watch_dir, Config,
+        ConfigError, ConfigEvent, ConfigOptional, WatchBackend, WatchMode, WatchOptions,
+        WatchedChange,
+    };
+
+    use super::{glob_matches, is_ignored, Debouncer};
+
+    /// The value of the last reload of a burst of events.
+    fn next(receiver: &mpsc::Receiver<Result<Conf, ConfigError>>) -> Option<u32> {
+        let mut last = receiver.recv_timeout(Duration::from_secs(5)).ok()?;
+        while let Ok(result) = receiver.recv_timeout(Duration::from_millis(300)) {
+            last = result;
+        }
+        Some(last.expect("Could not reload config.").something)
+    }
+
+    #[test]
+    fn test_watch_config() {
+        let config_dir = test_dir("test_watch");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "something = 2").expect("Could not write test config.");
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_config::<Conf, OptConf, _>(&path, "", move |result| {
+            let _ = sender.send(result);
+        })
+        .expect("Could not watch config.");
+        assert_eq!(watcher.path(), path);
+
+        fs::write(config_dir.join("other.toml"), "something = 9")
+            .expect("Could not write test config.");
+        fs::write(&path, "something = 3").expect("Could not write test config.");
+        assert_eq!(next(&receiver), Some(3));
+
+        let temporary = config_dir.join(".config.toml.tmp");
+        fs::write(&temporary, "something = 4").expect("Could not write test config.");
+        fs::rename(&temporary, &path).expect("Could not replace test config.");
+        assert_eq!(next(&receiver), Some(4));
+        fs::write(&path, "something = 5").expect("Could not write test config.");
+        assert_eq!(next(&receiver), Some(5));
+
+        for something in 10..20 {
+            fs::write(&path, format!("something = {}", something))
+                .expect("Could not write test config.");
+        }
+        let reloaded = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Config was not reloaded.");
+        assert_eq!(reloaded.expect("Could not reload config.").something, 19);
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+
+        drop(watcher);
+        fs::write(&path, "something = 6").expect("Could not write test config.");
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_debouncer() {
+        let (sender, receiver) = mpsc::channel();
+        let debounce = Duration::from_millis(200);
+        let worker = std::thread::spawn(move || {
+            let start = Instant::now();
+            for _ in 0..3 {
+                sender.send(PathBuf::from("a.toml")).unwrap();
+            }
+            sender.send(PathBuf::from("b.toml")).unwrap();
+            std::thread::sleep(Duration::from_millis(120));
+            // resets the wait of a.toml only
+            sender.send(PathBuf::from("a.toml")).unwrap();
+            std::thread::sleep(Duration::from_millis(150));
+            start
+        });
+        let mut debouncer = Debouncer::new(debounce);
+        let mut fired = Vec::new();
+        while debouncer.wait(&receiver) {
+            fired.extend(
+                debouncer
+                    .take_due()
+                    .into_iter()
+                    .map(|path| (path, Instant::now())),
+            );
+        }
+        let start = worker.join().unwrap();
+        // the sender is gone before a.toml is due
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].0, PathBuf::from("b.toml"));
+        assert!(fired[0].1 - start >= debounce);
+        assert_eq!(debouncer.pending.len(), 1);
+    }
+
+    #[test]
+    fn test_watch_config_channel() {
+        let config_dir = test_dir("test_watch_channel");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "something = 2").expect("Could not write test config.");
+        let (watcher, events) =
+            watch_config_channel::<Conf, OptConf>(&path, "").expect("Could not watch config.");
+        let next = || events.recv_timeout(Duration::from_secs(5));
+
+        fs::write(&path, "something = 3").expect("Could not write test config.");
+        assert!(matches!(
+            next(),
+            Ok(ConfigEvent::Reloaded(Conf { something: 3 }))
+        ));
+        fs::write(&path, "something = ").expect("Could not write test config.");
+        assert!(matches!(
+            next(),
+            Ok(ConfigEvent::ParseError(ConfigError::Parse(_)))
+        ));
+        fs::remove_file(&path).expect("Could not remove test config.");
+        assert!(matches!(next(), Ok(ConfigEvent::FileRemoved)));
+
+        // nobody reads while the file changes, each event replaces the previous one
+        for something in 4..7 {
+            fs::write(&path, format!("something = {}", something))
+                .expect("Could not write test config.");
+            std::thread::sleep(Duration::from_millis(400));
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ConfigEvent::Reloaded(Conf { something: 6 }))
+        ));
+        assert!(matches!(events.try_recv(), Err(TryRecvError::Empty)));
+
+        drop(watcher);
+        assert!(matches!(
+            events.recv_timeout(Duration::from_secs(5)),
+            Err(RecvTimeoutError::Disconnected)
+        ));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        let root = Path::new("/config");
+        let ignore = [
+            "*.swp".to_string(),
+            "*~".to_string(),
+            "themes/?.css".to_string(),
+        ];
+        for ignored in [
+            ".config.toml.swp",
+            "style.css~",
+            "themes/a.css",
+            "themes/x.swp",
+        ] {
+            assert!(
+                is_ignored(root, &root.join(ignored), &ignore),
+                "{}",
+                ignored
+            );
+        }
+        for kept in [
+            "config.toml",
+            "style.css",
+            "themes/ab.css",
+            "other/themes/a.css",
+        ] {
+            assert!(!is_ignored(root, &root.join(kept), &ignore), "{}", kept);
+        }
+        assert!(glob_matches(b"*", b"config.toml"));
+        assert!(!glob_matches(b"*.toml", b"themes/config.toml"));
+    }
+
+    #[test]
+    fn test_watch_dir() {
+        let config_dir = test_dir("test_watch_dir");
+        fs::write(config_dir.join("config.toml"), "").expect("Could not write test config.");
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_dir(&config_dir, &["*.swp", "*~"], move |path| {
+            let _ = sender.send(path.to_path_buf());
+        })
+        .expect("Could not watch folder.");
+        assert_eq!(watcher.path(), config_dir);
+        let changed = || {
+            let mut paths = vec![receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("No change arrived.")];
+            while let Ok(path) = receiver.recv_timeout(Duration::from_millis(400)) {
+                paths.push(path);
+            }
+            paths.sort();
+            paths.dedup();
+            paths
+        };
+
+        fs::write(config_dir.join("config.toml"), "a = 1").expect("Could not write test config.");
+        fs::write(config_dir.join("config.toml~"), "").expect("Could not write test config.");
+        fs::write(config_dir.join(".config.toml.swp"), "").expect("Could not write test config.");
+        assert_eq!(changed(), vec![config_dir.join("config.toml")]);
+
+        // created after the watcher started
+        let themes = config_dir.join("themes");
+        fs::create_dir(&themes).expect("Could not create test folder.");
+        std::thread::sleep(Duration::from_millis(400));
+        assert_eq!(changed(), vec![themes.clone()]);
+        fs::write(themes.join("dark.css"), "").expect("Could not write test css.");
+        assert!(changed().contains(&themes.join("dark.css")));
+
+        drop(watcher);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_watch_config_polling() {
+        let config_dir = test_dir("test_watch_poll");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "something = 2").expect("Could not write test config.");
+        let (sender, receiver) = mpsc::channel();
+        let options = WatchOptions {
+            debounce: Duration::from_millis(50),
+            mode: WatchMode::Poll(Duration::from_millis(50)),
+        };
+        let watcher =
+            watch_config_with_options::<Conf, OptConf, _>(&path, "", options, move |result| {
+                let _ = sender.send(result);
+            })
+            .expect("Could not watch config.");
+        assert_eq!(
+            watcher.backend(),
+            WatchBackend::Poll(Duration::from_millis(50))
+        );
+
+        fs::write(&path, "something = 30").expect("Could not write test config.");
+        assert_eq!(next(&receiver), Some(30));
+        fs::remove_file(&path).expect("Could not remove test config.");
+        std::thread::sleep(Duration::from_millis(300));
+        fs::write(&path, "something = 4").expect("Could not write test config.");
+        assert_eq!(next(&receiver), Some(4));
+        drop(watcher);
+
+        let watcher =
+            watch_config::<Conf, OptConf, _>(&path, "", |_| ()).expect("Could not watch config.");
+        assert_eq!(watcher.backend(), WatchBackend::Native);
+        drop(watcher);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_watch_css() {
+        let config_dir = test_dir("test_watch_css");
+        let css = config_dir.join("style.css");
+        fs::write(&css, "").expect("Could not write test css.");
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_css(&css, move |result| {
+            let _ = sender.send(result);
+        })
+        .expect("Could not watch css.");
+        let temporary = config_dir.join(".style.css.tmp");
+        fs::write(&temporary, "window { color: red; }").expect("Could not write test css.");
+        fs::rename(&temporary, &css).expect("Could not replace test css.");
+        let reloaded = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("No reload arrived.");
+        assert_eq!(reloaded.unwrap(), "window { color: red; }");
+        drop(watcher);
+
+        let config = config_dir.join("config.toml");
+        fs::write(&config, "something = 2").expect("Could not write test config.");
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_config_and_css::<Conf, OptConf, _>(&config, "", &css, move |change| {
+            let _ = sender.send(change);
+        })
+        .expect("Could not watch files.");
+        assert_eq!(watcher.path(), config);
+        let next = || {
+            receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("No reload arrived.")
+        };
+        fs::write(&css, "label {}").expect("Could not write test css.");
+        assert!(matches!(next(), WatchedChange::Css(Ok(css)) if css == "label {}"));
+        fs::write(&config, "something = 3").expect("Could not write test config.");
+        assert!(matches!(
+            next(),
+            WatchedChange::Config(Ok(Conf { something: 3 }))
+        ));
+        drop(watcher);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_watch_config_with_changes() {
+        #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+        struct Colors {
+            font_size: Option<u32>,
+            color: Option<String>,
+        }
+
+        impl ConfigOptional for Colors {}
+
+        impl Config<Colors> for Colors {
+            fn create_from_optional(optional: Colors) -> Self {
+                optional
+            }
+        }
+
+        let config_dir = test_dir("test_watch_changes");
+        let path = config_dir.join("config.toml");
+        fs::write(&path, "font_size = 12").expect("Could not write test config.");
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_config_with_changes::<Colors, Colors, _>(&path, "", move |result| {
+            let _ = sender.send(result);
+        })
+        .expect("Could not watch config.");
+        let (if_sender, if_receiver) = mpsc::channel();
+        let if_changed = watch_config_if_changed::<Colors, Colors, _>(&path, "", move |result| {
+            let _ = if_sender.send(result);
+        })
+        .expect("Could not watch config.");
+        let next = || {
+            receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("No reload arrived.")
+                .expect("Could not reload config.")
+        };
+
+        fs::write(&path, "font_size = 12\ncolor = \"red\"").expect("Could not write test config.");
+        let reload = next();
+        assert_eq!(reload.config.color.as_deref(), Some("red"));
+        assert_eq!(reload.change.keys().collect::<Vec<_>>(), vec!["color"]);
+        assert!(if_receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        fs::write(&path, "color = \"red\"\nfont_size = 12").expect("Could not write test config.");
+        assert!(!next().change.is_changed());
+        assert!(if_receiver
+            .recv_timeout(Duration::from_millis(500))
+            .is_err());
+
+        drop(watcher);
+        drop(if_changed);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}
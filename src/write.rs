@@ -0,0 +1,281 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{load_config, ConfigError, ConfigOptional, Format, TryConfig};
+
+/// Serializes `config` into `path` in the format of its extension, see [`Format::detect`],
+/// creating missing parent folders.
+/// The file is replaced atomically, readers never see a partially written config.
+///
+/// Keys the config does not know about are dropped, unless both the optional and the
+/// concrete config keep them in a `#[serde(flatten)] extra: toml::Table` field.
+/// Such keys are not reported as unknown while loading.
+pub fn write_config<ConcreteConfig>(path: &Path, config: &ConcreteConfig) -> Result<(), ConfigError>
+where
+    ConcreteConfig: Serialize,
+{
+    let contents = serialize(path, config)?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        if let Err(error) = fs::create_dir_all(parent) {
+            return Err(ConfigError::Io(parent.to_path_buf(), error));
+        }
+    }
+    write_atomic(path, &contents)
+}
+
+/// Serializes the resolved `config`, e.g. to show the effective values with `--dump-config`.
+/// Fields keep the order they are declared in, so the output is stable.
+pub fn dump_config<ConcreteConfig>(
+    config: &ConcreteConfig,
+    format: Format,
+) -> Result<String, ConfigError>
+where
+    ConcreteConfig: Serialize,
+{
+    match format.serialize(config) {
+        Ok(contents) => Ok(contents),
+        Err(error) => Err(ConfigError::Serialize(PathBuf::new(), error)),
+    }
+}
+
+/// Loads the config, applies `update` and writes the result back atomically.
+/// Nothing is written if `update` did not change the serialized config.
+pub fn update_config<ConcreteConfig, OptionalConfig, F>(
+    config_dir: &Path,
+    config_file_name: &str,
+    default_config: &str,
+    update: F,
+) -> Result<ConcreteConfig, ConfigError>
+where
+    ConcreteConfig: TryConfig<OptionalConfig> + Serialize,
+    OptionalConfig: ConfigOptional,
+    F: FnOnce(&mut ConcreteConfig),
+{
+    let mut handle = load_config(config_dir, config_file_name, default_config)?;
+    let before = serialize(&handle.path, &handle.config)?;
+    update(&mut handle.config);
+    let after = serialize(&handle.path, &handle.config)?;
+    if before != after {
+        write_atomic(&handle.path, &after)?;
+    }
+    Ok(handle.config)
+}
+
+fn serialize<ConcreteConfig>(path: &Path, config: &ConcreteConfig) -> Result<String, ConfigError>
+where
+    ConcreteConfig: Serialize,
+{
+    match Format::detect(path).serialize(config) {
+        Ok(contents) => Ok(contents),
+        Err(error) => Err(ConfigError::Serialize(path.to_path_buf(), error)),
+    }
+}
+
+/// Writes into a temporary file next to `path` and renames it over `path`.
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> Result<(), ConfigError> {
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(format!(".tmp-{}", std::process::id()));
+    let temporary = PathBuf::from(temporary);
+    if let Err(error) = fs::write(&temporary, contents) {
+        return Err(ConfigError::from_io(temporary, error));
+    }
+    if let Err(error) = fs::rename(&temporary, path) {
+        let _ = fs::remove_file(&temporary);
+        return Err(ConfigError::from_io(path.to_path_buf(), error));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        create_config_with_raw, create_config_with_report, dump_config, load_config,
+        parse_config_str, read_specific_config,
+        test_support::{test_dir, OptWhatConf as OptConf, WhatConf as Conf},
+        update_config, write_config, Config, ConfigError, ConfigOptional, Format,
+    };
+
+    #[test]
+    fn test_write_config() {
+        let base = std::env::temp_dir().join("oxilib_test_write");
+        let path = base.join("nested").join("config.toml");
+        let config = Conf {
+            something: 4,
+            what: String::from("written"),
+        };
+        write_config(&path, &config).expect("Could not write config.");
+        let read_config =
+            read_specific_config::<Conf, OptConf>(&path).expect("Could not read config.");
+        assert_eq!(read_config, config);
+
+        let mut handle =
+            load_config::<Conf, OptConf>(&path, "", "").expect("Could not load config.");
+        handle.config.something = 5;
+        handle.save().expect("Could not save config.");
+        let read_config =
+            read_specific_config::<Conf, OptConf>(&path).expect("Could not read config.");
+        assert_eq!(read_config.something, 5);
+
+        // toml can't represent maps with non-string keys
+        let error = write_config(&path, &HashMap::from([(1, 2)]))
+            .expect_err("Unrepresentable config was written.");
+        assert!(matches!(error, ConfigError::Serialize(_, _)));
+        fs::remove_dir_all(&base).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_dump_config() {
+        let config = Conf {
+            something: 4,
+            what: String::from("dumped"),
+        };
+        let dumped = dump_config(&config, Format::Toml).expect("Could not dump config.");
+        assert_eq!(dumped, "something = 4\nwhat = \"dumped\"\n");
+        let parsed = parse_config_str::<Conf, OptConf>(&dumped).expect("Could not parse dump.");
+        assert_eq!(parsed, config);
+
+        let formats = [
+            #[cfg(feature = "json")]
+            Format::Json,
+            #[cfg(feature = "yaml")]
+            Format::Yaml,
+            #[cfg(feature = "ron")]
+            Format::Ron,
+            #[cfg(feature = "json5")]
+            Format::Json5,
+        ];
+        for format in formats {
+            let dumped = dump_config(&config, format).expect("Could not dump config.");
+            let parsed: OptConf = format
+                .parse_file(std::path::Path::new(""), &dumped, &mut Vec::new())
+                .expect("Could not parse dump.");
+            assert_eq!(Conf::create_from_optional(parsed), config, "{}", format);
+        }
+
+        let error = dump_config(&HashMap::from([(1, 2)]), Format::Toml)
+            .expect_err("Unrepresentable config was dumped.");
+        assert!(matches!(error, ConfigError::Serialize(_, _)));
+        assert_eq!(error.path(), None);
+    }
+
+    #[test]
+    fn test_write_config_keeps_extra() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct ExtraConf {
+            something: u32,
+            #[serde(flatten)]
+            extra: toml::Table,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        struct OptExtraConf {
+            something: Option<u32>,
+            #[serde(flatten)]
+            extra: toml::Table,
+        }
+
+        impl ConfigOptional for OptExtraConf {}
+
+        impl Config<OptExtraConf> for ExtraConf {
+            fn create_from_optional(optional: OptExtraConf) -> Self {
+                ExtraConf {
+                    something: optional.something.unwrap_or_default(),
+                    extra: optional.extra,
+                }
+            }
+        }
+
+        let config_dir = test_dir("test_keeps_extra");
+        fs::write(
+            config_dir.join("config.toml"),
+            "something = 1\n[future_feature]\nenabled = true\n",
+        )
+        .expect("Could not write test config.");
+        let (_, report) =
+            create_config_with_report::<ExtraConf, OptExtraConf>(&config_dir, "config.toml", "")
+                .expect("Could not load config.");
+        assert!(report.unknown_keys.is_empty());
+        update_config::<ExtraConf, OptExtraConf, _>(&config_dir, "config.toml", "", |conf| {
+            conf.something = 2
+        })
+        .expect("Could not update config.");
+        let (conf, raw) =
+            create_config_with_raw::<ExtraConf, OptExtraConf>(&config_dir, "config.toml", "")
+                .expect("Could not load config.");
+        assert_eq!(conf.something, 2);
+        assert_eq!(raw["future_feature"]["enabled"].as_bool(), Some(true));
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[test]
+    fn test_update_config() {
+        let config_dir = test_dir("test_update");
+        let path = config_dir.join("config.toml");
+        let conf = update_config::<Conf, OptConf, _>(
+            &config_dir,
+            "config.toml",
+            "something = 1",
+            |conf| conf.something += 1,
+        )
+        .expect("Could not update config.");
+        assert_eq!(conf.something, 2);
+        let read_config =
+            read_specific_config::<Conf, OptConf>(&path).expect("Could not read config.");
+        assert_eq!(read_config, conf);
+
+        fs::write(&path, "something = 2 # keep me").expect("Could not write test config.");
+        update_config::<Conf, OptConf, _>(&config_dir, "config.toml", "", |conf| {
+            conf.something = 2
+        })
+        .expect("Could not update config.");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "something = 2 # keep me"
+        );
+        assert_eq!(fs::read_dir(&config_dir).unwrap().count(), 1);
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_write_config_json() {
+        let config_dir = test_dir("test_write_json");
+        let path = config_dir.join("config.json");
+        fs::write(&path, r#"{"something": 1, "what": "json"}"#)
+            .expect("Could not write test config.");
+
+        let mut handle =
+            load_config::<Conf, OptConf>(&path, "", "").expect("Could not load config.");
+        handle.config.something = 2;
+        handle.save().expect("Could not save config.");
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).expect("Not saved as json.");
+        assert_eq!(written["something"], 2);
+        let read_config =
+            read_specific_config::<Conf, OptConf>(&path).expect("Could not read config.");
+        assert_eq!(read_config.something, 2);
+
+        update_config::<Conf, OptConf, _>(&config_dir, "config.json", "", |conf| {
+            conf.something = 3
+        })
+        .expect("Could not update config.");
+        let written: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+            .expect("Not updated as json.");
+        assert_eq!(written["something"], 3);
+        let read_config =
+            read_specific_config::<Conf, OptConf>(&path).expect("Could not read config.");
+        assert_eq!(read_config.what, "json");
+        fs::remove_dir_all(&config_dir).expect("Could not remove testfolder again.");
+    }
+}